@@ -0,0 +1,10 @@
+use anyhow::Result;
+
+pub(crate) fn read_text() -> Result<String> {
+    Ok(arboard::Clipboard::new()?.get_text()?)
+}
+
+pub(crate) fn write_text(value: String) -> Result<()> {
+    arboard::Clipboard::new()?.set_text(value)?;
+    Ok(())
+}
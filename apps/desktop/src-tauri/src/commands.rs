@@ -1,5 +1,13 @@
+use crate::focus::FocusRestore;
+use crate::gamepad::GamepadButton;
+use crate::hotkeys::{HotkeyAction, HotkeyBinding};
+use crate::input::{
+    CaptureCapabilities, CaptureSession, CaptureState, CaptureStopReason, CaptureTrigger,
+    ChatInputMode, ChatKeyPersistenceMode, ChatScrollModifier, ChordSuppression, ComposeState,
+    ImeCaret, InputCaptureState, InputTraceDump, ListenerStatus,
+};
 use crate::media::MediaProbe;
-use crate::input::InputCaptureState;
+use crate::quick_chat::QuickChatMacro;
 use crate::roblox::LogSettingsState;
 use tauri::AppHandle;
 
@@ -56,38 +64,282 @@ pub(crate) fn focus_roblox() -> bool {
 
 #[tauri::command]
 pub(crate) fn start_chat_capture(
-    mode: String,
-    input_mode: String,
+    app: AppHandle,
+    mode: ChatKeyPersistenceMode,
+    input_mode: ChatInputMode,
+    state: tauri::State<InputCaptureState>,
+) -> Result<CaptureSession, String> {
+    to_cmd(crate::input::start_chat_capture(
+        &app, &state, mode, input_mode,
+    ))
+}
+
+#[tauri::command]
+pub(crate) fn set_ime_caret(
+    app: AppHandle,
+    caret: ImeCaret,
+    state: tauri::State<InputCaptureState>,
+) -> Result<(), String> {
+    to_cmd(crate::input::set_ime_caret(&app, &state, caret))
+}
+
+#[tauri::command]
+pub(crate) fn focus_ime_bridge(
+    app: AppHandle,
     state: tauri::State<InputCaptureState>,
 ) -> Result<(), String> {
-    to_cmd(crate::input::start_chat_capture(&*state, &mode, &input_mode))
+    to_cmd(crate::input::focus_ime_bridge(&app, &state))
+}
+
+#[tauri::command]
+pub(crate) fn restore_capture_focus(
+    state: tauri::State<InputCaptureState>,
+) -> Result<FocusRestore, String> {
+    to_cmd(crate::input::restore_capture_focus(&state))
+}
+
+#[tauri::command]
+pub(crate) fn get_capture_state(
+    state: tauri::State<InputCaptureState>,
+) -> Result<CaptureState, String> {
+    to_cmd(crate::input::get_capture_state(&state))
+}
+
+#[tauri::command]
+pub(crate) fn set_compose_state(
+    text: String,
+    cursor: Option<usize>,
+    state: tauri::State<InputCaptureState>,
+) -> Result<ComposeState, String> {
+    to_cmd(crate::input::set_compose_state(&state, &text, cursor))
+}
+
+#[tauri::command]
+pub(crate) fn get_capture_capabilities(
+    state: tauri::State<InputCaptureState>,
+) -> Result<CaptureCapabilities, String> {
+    to_cmd(crate::input::get_capture_capabilities(&state))
 }
 
 #[tauri::command]
 pub(crate) fn stop_chat_capture(
-    reason: Option<String>,
+    app: AppHandle,
+    reason: Option<CaptureStopReason>,
     state: tauri::State<InputCaptureState>,
 ) -> Result<(), String> {
-    let _ = reason;
-    to_cmd(crate::input::stop_chat_capture(&*state))
+    to_cmd(crate::input::stop_chat_capture(
+        &app,
+        &state,
+        reason.unwrap_or_default(),
+    ))
+}
+
+#[tauri::command]
+pub(crate) fn get_input_listener_status(
+    state: tauri::State<InputCaptureState>,
+) -> Result<ListenerStatus, String> {
+    to_cmd(crate::input::get_listener_status(&state))
+}
+
+#[tauri::command]
+pub(crate) fn retry_input_listener(
+    app: AppHandle,
+    state: tauri::State<InputCaptureState>,
+) -> Result<ListenerStatus, String> {
+    to_cmd(crate::input::retry_key_listener(&app, &state))
+}
+
+#[tauri::command]
+pub(crate) fn get_hotkey_bindings(
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<HotkeyBinding>, String> {
+    to_cmd(crate::input::get_hotkey_bindings(&state))
+}
+
+#[tauri::command]
+pub(crate) fn set_hotkey_binding(
+    app: AppHandle,
+    action: HotkeyAction,
+    chord: Option<String>,
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<HotkeyBinding>, String> {
+    to_cmd(crate::input::set_hotkey_binding(
+        &app,
+        &state,
+        action,
+        chord.as_deref(),
+    ))
+}
+
+#[tauri::command]
+pub(crate) fn reset_hotkey_bindings(
+    app: AppHandle,
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<HotkeyBinding>, String> {
+    to_cmd(crate::input::reset_hotkey_bindings(&app, &state))
+}
+
+#[tauri::command]
+pub(crate) fn get_quick_chat_macros(
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<QuickChatMacro>, String> {
+    to_cmd(crate::input::get_quick_chat_macros(&state))
+}
+
+#[tauri::command]
+pub(crate) fn set_quick_chat_macros(
+    app: AppHandle,
+    macros: Vec<QuickChatMacro>,
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<QuickChatMacro>, String> {
+    to_cmd(crate::input::set_quick_chat_macros(&app, &state, &macros))
+}
+
+#[tauri::command]
+pub(crate) fn get_gamepad_chat_combo(
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<GamepadButton>, String> {
+    to_cmd(crate::input::get_gamepad_chat_combo(&state))
+}
+
+#[tauri::command]
+pub(crate) fn set_gamepad_chat_combo(
+    app: AppHandle,
+    buttons: Vec<GamepadButton>,
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<GamepadButton>, String> {
+    to_cmd(crate::input::set_gamepad_chat_combo(&app, &state, &buttons))
+}
+
+#[tauri::command]
+pub(crate) fn get_custom_latch_keys(
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<String>, String> {
+    to_cmd(crate::input::get_custom_latch_keys(&state))
+}
+
+#[tauri::command]
+pub(crate) fn set_custom_latch_keys(
+    app: AppHandle,
+    keys: Vec<String>,
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<String>, String> {
+    to_cmd(crate::input::set_custom_latch_keys(&app, &state, &keys))
+}
+
+#[tauri::command]
+pub(crate) fn get_passthrough_chords(
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<String>, String> {
+    to_cmd(crate::input::get_passthrough_chords(&state))
+}
+
+#[tauri::command]
+pub(crate) fn set_passthrough_chords(
+    app: AppHandle,
+    chords: Vec<String>,
+    state: tauri::State<InputCaptureState>,
+) -> Result<Vec<String>, String> {
+    to_cmd(crate::input::set_passthrough_chords(&app, &state, &chords))
+}
+
+#[tauri::command]
+pub(crate) fn test_chord_suppression(
+    chord: String,
+    state: tauri::State<InputCaptureState>,
+) -> Result<ChordSuppression, String> {
+    to_cmd(crate::input::test_chord_suppression(&state, &chord))
+}
+
+#[tauri::command]
+pub(crate) fn get_input_trace_enabled(state: tauri::State<InputCaptureState>) -> bool {
+    crate::input::get_input_trace_enabled(&state)
+}
+
+#[tauri::command]
+pub(crate) fn set_input_trace_enabled(
+    enabled: bool,
+    state: tauri::State<InputCaptureState>,
+) -> bool {
+    crate::input::set_input_trace_enabled(&state, enabled)
+}
+
+#[tauri::command]
+pub(crate) fn dump_input_trace(
+    include_text: Option<bool>,
+    state: tauri::State<InputCaptureState>,
+) -> Result<InputTraceDump, String> {
+    to_cmd(crate::input::dump_input_trace(
+        &state,
+        include_text.unwrap_or(false),
+    ))
+}
+
+#[tauri::command]
+pub(crate) fn clear_input_trace(state: tauri::State<InputCaptureState>) -> Result<(), String> {
+    to_cmd(crate::input::clear_input_trace(&state))
+}
+
+#[tauri::command]
+pub(crate) fn get_capture_idle_timeout(
+    state: tauri::State<InputCaptureState>,
+) -> Result<u64, String> {
+    to_cmd(crate::input::get_capture_idle_timeout(&state))
+}
+
+#[tauri::command]
+pub(crate) fn set_capture_idle_timeout(
+    app: AppHandle,
+    seconds: u64,
+    state: tauri::State<InputCaptureState>,
+) -> Result<u64, String> {
+    to_cmd(crate::input::set_capture_idle_timeout(
+        &app, &state, seconds,
+    ))
+}
+
+#[tauri::command]
+pub(crate) fn get_chat_scroll_modifier(
+    state: tauri::State<InputCaptureState>,
+) -> Result<ChatScrollModifier, String> {
+    to_cmd(crate::input::get_chat_scroll_modifier(&state))
+}
+
+#[tauri::command]
+pub(crate) fn set_chat_scroll_modifier(
+    app: AppHandle,
+    modifier: ChatScrollModifier,
+    state: tauri::State<InputCaptureState>,
+) -> Result<ChatScrollModifier, String> {
+    to_cmd(crate::input::set_chat_scroll_modifier(
+        &app, &state, modifier,
+    ))
+}
+
+#[tauri::command]
+pub(crate) fn get_capture_trigger(
+    state: tauri::State<InputCaptureState>,
+) -> Result<CaptureTrigger, String> {
+    to_cmd(crate::input::get_capture_trigger(&state))
+}
+
+#[tauri::command]
+pub(crate) fn set_capture_trigger(
+    app: AppHandle,
+    trigger: CaptureTrigger,
+    state: tauri::State<InputCaptureState>,
+) -> Result<CaptureTrigger, String> {
+    to_cmd(crate::input::set_capture_trigger(&app, &state, trigger))
 }
 
 #[tauri::command]
 pub(crate) fn read_clipboard_text() -> Result<String, String> {
-    to_cmd(
-        arboard::Clipboard::new()
-            .and_then(|mut clipboard| clipboard.get_text())
-            .map_err(anyhow::Error::from),
-    )
+    to_cmd(crate::clipboard::read_text())
 }
 
 #[tauri::command]
 pub(crate) fn write_clipboard_text(value: String) -> Result<(), String> {
-    to_cmd(
-        arboard::Clipboard::new()
-            .and_then(|mut clipboard| clipboard.set_text(value))
-            .map_err(anyhow::Error::from),
-    )
+    to_cmd(crate::clipboard::write_text(value))
 }
 
 #[tauri::command]
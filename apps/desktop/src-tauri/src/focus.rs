@@ -0,0 +1,184 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+#[cfg(windows)]
+use windows::Win32::Foundation::HWND;
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetForegroundWindow, IsIconic, IsWindow, SetForegroundWindow, ShowWindow, SW_RESTORE,
+};
+
+// The platform's id for a top-level window: an HWND on Windows, an X11 window id on Linux.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct WindowHandle(usize);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "restored", rename_all = "camelCase")]
+pub(crate) enum FocusRestore {
+    // The window that was in front when capture started.
+    Previous,
+    // That window could not take focus back, so the target rules picked one.
+    Target { reason: String },
+}
+
+// Hands focus back to the exact window chat capture took it from.
+#[derive(Default)]
+pub(crate) struct FocusHandoff {
+    previous: Mutex<Option<WindowHandle>>,
+}
+
+impl FocusHandoff {
+    pub(crate) fn record(&self, app: &AppHandle) -> Result<()> {
+        let window = foreground_window(app);
+        *self
+            .previous
+            .lock()
+            .map_err(|err| anyhow::anyhow!("lock focus handoff: {err}"))? = window;
+        Ok(())
+    }
+
+    pub(crate) fn restore(&self) -> Result<FocusRestore> {
+        let previous = self
+            .previous
+            .lock()
+            .map_err(|err| anyhow::anyhow!("lock focus handoff: {err}"))?
+            .take();
+        restore_focus(previous, activate_window, crate::roblox::focus_roblox)
+    }
+}
+
+fn restore_focus(
+    previous: Option<WindowHandle>,
+    activate: impl FnOnce(WindowHandle) -> Result<(), String>,
+    focus_target: impl FnOnce() -> bool,
+) -> Result<FocusRestore> {
+    let reason = match previous {
+        Some(window) => match activate(window) {
+            Ok(()) => return Ok(FocusRestore::Previous),
+            Err(reason) => reason,
+        },
+        None => "no other window was in front when capture started".to_string(),
+    };
+
+    if !focus_target() {
+        anyhow::bail!("{reason}, and no Roblox window was found to fall back to");
+    }
+    Ok(FocusRestore::Target { reason })
+}
+
+// None when nothing or one of our own windows is in front; the app is never what focus returns to.
+#[cfg(windows)]
+fn foreground_window(app: &AppHandle) -> Option<WindowHandle> {
+    let foreground = unsafe { GetForegroundWindow() };
+    if foreground.0.is_null() {
+        return None;
+    }
+
+    let own = app
+        .webview_windows()
+        .values()
+        .any(|window| window.hwnd().is_ok_and(|hwnd| hwnd.0 == foreground.0));
+    (!own).then_some(WindowHandle(foreground.0 as usize))
+}
+
+#[cfg(windows)]
+fn activate_window(window: WindowHandle) -> Result<(), String> {
+    let hwnd = HWND(window.0 as *mut _);
+    unsafe {
+        if !IsWindow(Some(hwnd)).as_bool() {
+            return Err("the previous window has closed".to_string());
+        }
+        if IsIconic(hwnd).as_bool() {
+            let _ = ShowWindow(hwnd, SW_RESTORE);
+        }
+        if !SetForegroundWindow(hwnd).as_bool() {
+            return Err("Windows refused to bring the previous window to the front".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn foreground_window(app: &AppHandle) -> Option<WindowHandle> {
+    // Tauri does not expose our X11 window ids, so ask whether one of our windows has focus instead.
+    if app
+        .webview_windows()
+        .values()
+        .any(|window| window.is_focused().unwrap_or(false))
+    {
+        return None;
+    }
+
+    crate::xdisplay::with_display(|display| unsafe { crate::xdisplay::active_window(display) })
+        .flatten()
+        .map(|window| WindowHandle(window as usize))
+}
+
+#[cfg(target_os = "linux")]
+fn activate_window(window: WindowHandle) -> Result<(), String> {
+    use crate::xdisplay::window_list_property;
+    use x11::xlib;
+
+    let window = window.0 as xlib::Window;
+    crate::xdisplay::with_display(|display| unsafe {
+        let root = xlib::XDefaultRootWindow(display);
+        if !window_list_property(display, root, c"_NET_CLIENT_LIST").contains(&window) {
+            return Err("the previous window has closed".to_string());
+        }
+        crate::xdisplay::activate_window(display, window);
+        Ok(())
+    })
+    .unwrap_or_else(|| Err("could not connect to the X display".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_recorded_window_is_restored_before_the_target_rules() {
+        let window = WindowHandle(42);
+        let restored = restore_focus(
+            Some(window),
+            |activated| {
+                assert_eq!(activated, window);
+                Ok(())
+            },
+            || panic!("the target rules are only a fallback"),
+        );
+        assert_eq!(restored.unwrap(), FocusRestore::Previous);
+    }
+
+    #[test]
+    fn falling_back_reports_why_the_previous_window_was_skipped() {
+        let restored = restore_focus(
+            Some(WindowHandle(42)),
+            |_| Err("the previous window has closed".to_string()),
+            || true,
+        );
+        assert_eq!(
+            restored.unwrap(),
+            FocusRestore::Target {
+                reason: "the previous window has closed".to_string()
+            }
+        );
+
+        let restored = restore_focus(None, |_| unreachable!(), || true).unwrap();
+        assert!(matches!(restored, FocusRestore::Target { .. }));
+    }
+
+    #[test]
+    fn failing_both_reports_each_reason() {
+        let err = restore_focus(
+            Some(WindowHandle(42)),
+            |_| Err("the previous window has closed".to_string()),
+            || false,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the previous window has closed, and no Roblox window was found to fall back to"
+        );
+    }
+}
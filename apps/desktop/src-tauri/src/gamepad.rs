@@ -0,0 +1,273 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod win32;
+
+#[cfg(target_os = "linux")]
+pub(crate) use linux::EvdevGamepadSource as PlatformGamepadSource;
+#[cfg(windows)]
+pub(crate) use win32::XInputGamepadSource as PlatformGamepadSource;
+
+const STORE_KEY: &str = "gamepadChatCombo";
+// Clicking both sticks together is rare in play, so it makes a safe default.
+const DEFAULT_CHAT_COMBO: [GamepadButton; 2] =
+    [GamepadButton::LeftThumb, GamepadButton::RightThumb];
+pub(crate) const GAMEPAD_POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    Back,
+    Start,
+    LeftThumb,
+    RightThumb,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+}
+
+impl GamepadButton {
+    const ALL: [GamepadButton; 14] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::Back,
+        GamepadButton::Start,
+        GamepadButton::LeftThumb,
+        GamepadButton::RightThumb,
+        GamepadButton::DpadUp,
+        GamepadButton::DpadDown,
+        GamepadButton::DpadLeft,
+        GamepadButton::DpadRight,
+    ];
+
+    fn navigation(self) -> Option<GamepadAction> {
+        match self {
+            GamepadButton::DpadUp => Some(GamepadAction::PreviousMessage),
+            GamepadButton::DpadDown => Some(GamepadAction::NextMessage),
+            GamepadButton::DpadLeft => Some(GamepadAction::OldestMessage),
+            GamepadButton::DpadRight => Some(GamepadAction::LatestMessage),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum GamepadAction {
+    OpenChat,
+    PreviousMessage,
+    NextMessage,
+    OldestMessage,
+    LatestMessage,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GamepadActionEvent {
+    pub(crate) action: GamepadAction,
+    pub(crate) timestamp_ms: i64,
+}
+
+pub(crate) trait GamepadSource {
+    // Buttons held across every connected controller; empty when none are connected.
+    fn poll(&mut self) -> HashSet<GamepadButton>;
+}
+
+pub(crate) struct GamepadTracker {
+    // Empty disables opening chat from a controller.
+    chat_combo: HashSet<GamepadButton>,
+    held: HashSet<GamepadButton>,
+}
+
+impl Default for GamepadTracker {
+    fn default() -> Self {
+        Self {
+            chat_combo: DEFAULT_CHAT_COMBO.into(),
+            held: HashSet::new(),
+        }
+    }
+}
+
+impl GamepadTracker {
+    pub(crate) fn chat_combo(&self) -> Vec<GamepadButton> {
+        GamepadButton::ALL
+            .into_iter()
+            .filter(|button| self.chat_combo.contains(button))
+            .collect()
+    }
+
+    pub(crate) fn set_chat_combo(&mut self, buttons: &[GamepadButton]) -> Result<()> {
+        if buttons.iter().any(|button| button.navigation().is_some()) {
+            anyhow::bail!("The D-pad navigates messages and cannot be part of the chat combo");
+        }
+
+        self.chat_combo = buttons.iter().copied().collect();
+        Ok(())
+    }
+
+    // Compares a poll against the previous one and returns the actions it newly triggers.
+    pub(crate) fn update(&mut self, held: HashSet<GamepadButton>) -> Vec<GamepadAction> {
+        let mut actions = Vec::new();
+        let combo_completed = !self.chat_combo.is_empty()
+            && self.chat_combo.is_subset(&held)
+            && !self.chat_combo.is_subset(&self.held);
+        if combo_completed {
+            actions.push(GamepadAction::OpenChat);
+        }
+
+        actions.extend(
+            GamepadButton::ALL
+                .into_iter()
+                .filter(|button| held.contains(button) && !self.held.contains(button))
+                .filter_map(GamepadButton::navigation),
+        );
+
+        self.held = held;
+        actions
+    }
+}
+
+pub(crate) fn load_chat_combo(app: &AppHandle, tracker: &mut GamepadTracker) -> Result<()> {
+    let store = app.store(crate::STORE_PATH)?;
+    let Some(value) = store.get(STORE_KEY) else {
+        return Ok(());
+    };
+
+    let buttons: Vec<GamepadButton> = match serde_json::from_value(value) {
+        Ok(buttons) => buttons,
+        Err(err) => {
+            eprintln!("Ignoring stored gamepad chat combo: {err}");
+            return Ok(());
+        }
+    };
+    if let Err(err) = tracker.set_chat_combo(&buttons) {
+        eprintln!("Ignoring stored gamepad chat combo: {err:#}");
+    }
+
+    Ok(())
+}
+
+pub(crate) fn save_chat_combo(app: &AppHandle, tracker: &GamepadTracker) -> Result<()> {
+    let store = app.store(crate::STORE_PATH)?;
+    store.set(STORE_KEY, serde_json::json!(tracker.chat_combo()));
+    store.save()?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    // Replays a fixed sequence of polls, then reports every controller as released.
+    #[derive(Default)]
+    pub(crate) struct ScriptedGamepad {
+        polls: VecDeque<HashSet<GamepadButton>>,
+    }
+
+    impl ScriptedGamepad {
+        pub(crate) fn new(polls: &[&[GamepadButton]]) -> Self {
+            Self {
+                polls: polls
+                    .iter()
+                    .map(|held| held.iter().copied().collect())
+                    .collect(),
+            }
+        }
+    }
+
+    impl GamepadSource for ScriptedGamepad {
+        fn poll(&mut self) -> HashSet<GamepadButton> {
+            self.polls.pop_front().unwrap_or_default()
+        }
+    }
+
+    fn drive(tracker: &mut GamepadTracker, source: &mut ScriptedGamepad) -> Vec<GamepadAction> {
+        (0..source.polls.len())
+            .flat_map(|_| tracker.update(source.poll()))
+            .collect()
+    }
+
+    #[test]
+    fn combo_opens_chat_once_per_press() {
+        use GamepadButton::{LeftThumb, RightThumb, South};
+
+        let mut tracker = GamepadTracker::default();
+        let mut source = ScriptedGamepad::new(&[
+            &[LeftThumb],
+            &[LeftThumb, RightThumb],
+            &[LeftThumb, RightThumb, South],
+            &[RightThumb],
+            &[LeftThumb, RightThumb],
+        ]);
+
+        assert_eq!(
+            drive(&mut tracker, &mut source),
+            vec![GamepadAction::OpenChat, GamepadAction::OpenChat]
+        );
+    }
+
+    #[test]
+    fn dpad_presses_navigate_messages() {
+        use GamepadButton::{DpadDown, DpadRight, DpadUp};
+
+        let mut tracker = GamepadTracker::default();
+        let mut source = ScriptedGamepad::new(&[&[DpadUp], &[DpadUp], &[], &[DpadDown, DpadRight]]);
+
+        assert_eq!(
+            drive(&mut tracker, &mut source),
+            vec![
+                GamepadAction::PreviousMessage,
+                GamepadAction::NextMessage,
+                GamepadAction::LatestMessage,
+            ]
+        );
+    }
+
+    #[test]
+    fn chat_combo_is_configurable() {
+        let mut tracker = GamepadTracker::default();
+        assert!(tracker
+            .set_chat_combo(&[GamepadButton::Back, GamepadButton::DpadUp])
+            .is_err());
+        assert_eq!(
+            tracker.chat_combo(),
+            vec![GamepadButton::LeftThumb, GamepadButton::RightThumb]
+        );
+
+        tracker
+            .set_chat_combo(&[GamepadButton::Start, GamepadButton::Back])
+            .unwrap();
+        assert_eq!(
+            tracker.chat_combo(),
+            vec![GamepadButton::Back, GamepadButton::Start]
+        );
+        let mut source = ScriptedGamepad::new(&[&[GamepadButton::Back, GamepadButton::Start]]);
+        assert_eq!(
+            drive(&mut tracker, &mut source),
+            vec![GamepadAction::OpenChat]
+        );
+
+        tracker.set_chat_combo(&[]).unwrap();
+        let mut source = ScriptedGamepad::new(&[&[], &[GamepadButton::Back]]);
+        assert!(drive(&mut tracker, &mut source).is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use super::{GamepadButton, GamepadSource};
+use evdev_rs::enums::{EventCode, EV_ABS, EV_KEY};
+use evdev_rs::{Device, ReadFlag};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// Hotplugged controllers show up as new event nodes, so look for them every so often.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+const BUTTONS: [(EV_KEY, GamepadButton); 14] = [
+    (EV_KEY::BTN_SOUTH, GamepadButton::South),
+    (EV_KEY::BTN_EAST, GamepadButton::East),
+    (EV_KEY::BTN_WEST, GamepadButton::West),
+    (EV_KEY::BTN_NORTH, GamepadButton::North),
+    (EV_KEY::BTN_TL, GamepadButton::LeftShoulder),
+    (EV_KEY::BTN_TR, GamepadButton::RightShoulder),
+    (EV_KEY::BTN_SELECT, GamepadButton::Back),
+    (EV_KEY::BTN_START, GamepadButton::Start),
+    (EV_KEY::BTN_THUMBL, GamepadButton::LeftThumb),
+    (EV_KEY::BTN_THUMBR, GamepadButton::RightThumb),
+    (EV_KEY::BTN_DPAD_UP, GamepadButton::DpadUp),
+    (EV_KEY::BTN_DPAD_DOWN, GamepadButton::DpadDown),
+    (EV_KEY::BTN_DPAD_LEFT, GamepadButton::DpadLeft),
+    (EV_KEY::BTN_DPAD_RIGHT, GamepadButton::DpadRight),
+];
+
+struct Gamepad {
+    path: PathBuf,
+    device: Device,
+}
+
+impl Gamepad {
+    fn open(path: PathBuf) -> Option<Self> {
+        let device = Device::new_from_fd(File::open(&path).ok()?).ok()?;
+        // The kernel gives every gamepad BTN_SOUTH (BTN_GAMEPAD); keyboards and mice never have it.
+        device
+            .has(&EventCode::EV_KEY(EV_KEY::BTN_SOUTH))
+            .then_some(Self { path, device })
+    }
+
+    // libevdev tracks button state as it reads, including the resync after a dropped buffer.
+    fn drain_events(&self) -> io::Result<()> {
+        while self.device.has_event_pending() {
+            match self.device.next_event(ReadFlag::NORMAL) {
+                Ok(_) => {}
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn held_buttons(&self) -> impl Iterator<Item = GamepadButton> + '_ {
+        let buttons = BUTTONS
+            .iter()
+            .filter(|(code, _)| self.value(EventCode::EV_KEY(code.clone())) != 0)
+            .map(|(_, button)| *button);
+
+        // Many pads report the D-pad as a hat axis rather than as buttons.
+        let hat_x = self.value(EventCode::EV_ABS(EV_ABS::ABS_HAT0X));
+        let hat_y = self.value(EventCode::EV_ABS(EV_ABS::ABS_HAT0Y));
+        let hat = [
+            (hat_y < 0, GamepadButton::DpadUp),
+            (hat_y > 0, GamepadButton::DpadDown),
+            (hat_x < 0, GamepadButton::DpadLeft),
+            (hat_x > 0, GamepadButton::DpadRight),
+        ]
+        .into_iter()
+        .filter_map(|(held, button)| held.then_some(button));
+
+        buttons.chain(hat)
+    }
+
+    fn value(&self, code: EventCode) -> i32 {
+        self.device.event_value(&code).unwrap_or(0)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct EvdevGamepadSource {
+    gamepads: Vec<Gamepad>,
+    last_scan: Option<Instant>,
+}
+
+impl EvdevGamepadSource {
+    fn rescan(&mut self) {
+        self.last_scan = Some(Instant::now());
+        self.gamepads.retain(|gamepad| gamepad.path.exists());
+
+        let Ok(entries) = std::fs::read_dir("/dev/input") else {
+            return;
+        };
+        let new_paths = entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+            .map(|entry| entry.path())
+            .filter(|path| !self.gamepads.iter().any(|gamepad| gamepad.path == *path))
+            .collect::<Vec<_>>();
+        self.gamepads
+            .extend(new_paths.into_iter().filter_map(Gamepad::open));
+    }
+}
+
+impl GamepadSource for EvdevGamepadSource {
+    fn poll(&mut self) -> HashSet<GamepadButton> {
+        if self
+            .last_scan
+            .is_none_or(|scanned| scanned.elapsed() >= RESCAN_INTERVAL)
+        {
+            self.rescan();
+        }
+
+        let mut held = HashSet::new();
+        self.gamepads
+            .retain(|gamepad| match gamepad.drain_events() {
+                Ok(()) => {
+                    held.extend(gamepad.held_buttons());
+                    true
+                }
+                Err(err) => {
+                    eprintln!("Dropping gamepad {}: {err}", gamepad.path.display());
+                    false
+                }
+            });
+        held
+    }
+}
@@ -0,0 +1,64 @@
+use super::{GamepadButton, GamepadSource};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::ERROR_SUCCESS;
+use windows::Win32::UI::Input::XboxController::{
+    XInputGetState, XINPUT_GAMEPAD_A, XINPUT_GAMEPAD_B, XINPUT_GAMEPAD_BACK,
+    XINPUT_GAMEPAD_BUTTON_FLAGS, XINPUT_GAMEPAD_DPAD_DOWN, XINPUT_GAMEPAD_DPAD_LEFT,
+    XINPUT_GAMEPAD_DPAD_RIGHT, XINPUT_GAMEPAD_DPAD_UP, XINPUT_GAMEPAD_LEFT_SHOULDER,
+    XINPUT_GAMEPAD_LEFT_THUMB, XINPUT_GAMEPAD_RIGHT_SHOULDER, XINPUT_GAMEPAD_RIGHT_THUMB,
+    XINPUT_GAMEPAD_START, XINPUT_GAMEPAD_X, XINPUT_GAMEPAD_Y, XINPUT_STATE, XUSER_MAX_COUNT,
+};
+
+// XInputGetState on an empty slot is slow, so unplugged slots are only rechecked occasionally.
+const EMPTY_SLOT_RETRY: Duration = Duration::from_secs(2);
+
+const BUTTONS: [(XINPUT_GAMEPAD_BUTTON_FLAGS, GamepadButton); 14] = [
+    (XINPUT_GAMEPAD_A, GamepadButton::South),
+    (XINPUT_GAMEPAD_B, GamepadButton::East),
+    (XINPUT_GAMEPAD_X, GamepadButton::West),
+    (XINPUT_GAMEPAD_Y, GamepadButton::North),
+    (XINPUT_GAMEPAD_LEFT_SHOULDER, GamepadButton::LeftShoulder),
+    (XINPUT_GAMEPAD_RIGHT_SHOULDER, GamepadButton::RightShoulder),
+    (XINPUT_GAMEPAD_BACK, GamepadButton::Back),
+    (XINPUT_GAMEPAD_START, GamepadButton::Start),
+    (XINPUT_GAMEPAD_LEFT_THUMB, GamepadButton::LeftThumb),
+    (XINPUT_GAMEPAD_RIGHT_THUMB, GamepadButton::RightThumb),
+    (XINPUT_GAMEPAD_DPAD_UP, GamepadButton::DpadUp),
+    (XINPUT_GAMEPAD_DPAD_DOWN, GamepadButton::DpadDown),
+    (XINPUT_GAMEPAD_DPAD_LEFT, GamepadButton::DpadLeft),
+    (XINPUT_GAMEPAD_DPAD_RIGHT, GamepadButton::DpadRight),
+];
+
+#[derive(Default)]
+pub(crate) struct XInputGamepadSource {
+    // When each slot last reported no controller.
+    empty_since: [Option<Instant>; XUSER_MAX_COUNT as usize],
+}
+
+impl GamepadSource for XInputGamepadSource {
+    fn poll(&mut self) -> HashSet<GamepadButton> {
+        let mut held = HashSet::new();
+        for (slot, empty_since) in self.empty_since.iter_mut().enumerate() {
+            if empty_since.is_some_and(|since| since.elapsed() < EMPTY_SLOT_RETRY) {
+                continue;
+            }
+
+            let mut state = XINPUT_STATE::default();
+            if unsafe { XInputGetState(slot as u32, &mut state) } != ERROR_SUCCESS.0 {
+                *empty_since = Some(Instant::now());
+                continue;
+            }
+            *empty_since = None;
+
+            let buttons = state.Gamepad.wButtons;
+            held.extend(
+                BUTTONS
+                    .iter()
+                    .filter(|(flag, _)| buttons.contains(*flag))
+                    .map(|(_, button)| *button),
+            );
+        }
+        held
+    }
+}
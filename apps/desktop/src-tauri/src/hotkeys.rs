@@ -0,0 +1,440 @@
+use anyhow::Result;
+use rdev::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+use crate::input::{key_from_code, key_to_code};
+
+const STORE_KEY: &str = "hotkeyBindings";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum HotkeyAction {
+    ToggleOverlay,
+    ToggleClickThrough,
+    ScrollChatUp,
+    ScrollChatDown,
+    ReplyToLastMessage,
+    MuteRoom,
+    FocusBloxchat,
+}
+
+impl HotkeyAction {
+    const ALL: [HotkeyAction; 7] = [
+        HotkeyAction::ToggleOverlay,
+        HotkeyAction::ToggleClickThrough,
+        HotkeyAction::ScrollChatUp,
+        HotkeyAction::ScrollChatDown,
+        HotkeyAction::ReplyToLastMessage,
+        HotkeyAction::MuteRoom,
+        HotkeyAction::FocusBloxchat,
+    ];
+
+    fn default_chord(self) -> &'static str {
+        match self {
+            HotkeyAction::ToggleOverlay => "Ctrl+Shift+KeyB",
+            HotkeyAction::ToggleClickThrough => "Ctrl+Shift+KeyL",
+            HotkeyAction::ScrollChatUp => "Ctrl+Shift+PageUp",
+            HotkeyAction::ScrollChatDown => "Ctrl+Shift+PageDown",
+            HotkeyAction::ReplyToLastMessage => "Ctrl+Shift+KeyR",
+            HotkeyAction::MuteRoom => "Ctrl+Shift+KeyM",
+            HotkeyAction::FocusBloxchat => "Ctrl+Shift+KeyF",
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            HotkeyAction::ToggleOverlay => "toggleOverlay",
+            HotkeyAction::ToggleClickThrough => "toggleClickThrough",
+            HotkeyAction::ScrollChatUp => "scrollChatUp",
+            HotkeyAction::ScrollChatDown => "scrollChatDown",
+            HotkeyAction::ReplyToLastMessage => "replyToLastMessage",
+            HotkeyAction::MuteRoom => "muteRoom",
+            HotkeyAction::FocusBloxchat => "focusBloxchat",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct Modifiers {
+    pub(crate) ctrl: bool,
+    pub(crate) shift: bool,
+    pub(crate) alt: bool,
+    pub(crate) meta: bool,
+}
+
+impl Modifiers {
+    pub(crate) fn from_down_keys(down_keys: &HashSet<Key>) -> Self {
+        Self {
+            ctrl: down_keys.contains(&Key::ControlLeft) || down_keys.contains(&Key::ControlRight),
+            shift: down_keys.contains(&Key::ShiftLeft) || down_keys.contains(&Key::ShiftRight),
+            alt: down_keys.contains(&Key::Alt) || down_keys.contains(&Key::AltGr),
+            meta: down_keys.contains(&Key::MetaLeft) || down_keys.contains(&Key::MetaRight),
+        }
+    }
+
+    fn is_empty(self) -> bool {
+        !(self.ctrl || self.shift || self.alt || self.meta)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Chord {
+    pub(crate) modifiers: Modifiers,
+    pub(crate) key: Key,
+}
+
+impl Chord {
+    pub(crate) fn parse(value: &str) -> Result<Self> {
+        let mut modifiers = Modifiers::default();
+        let mut key = None;
+
+        for part in value.split('+').map(str::trim) {
+            if part.is_empty() {
+                anyhow::bail!("Invalid chord \"{value}\": empty segment");
+            }
+
+            if key.is_some() {
+                anyhow::bail!("Invalid chord \"{value}\": the key must come last");
+            }
+
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                "meta" | "win" | "super" => modifiers.meta = true,
+                _ => {
+                    let parsed = key_from_code(part)
+                        .ok_or_else(|| anyhow::anyhow!("Unknown key \"{part}\" in \"{value}\""))?;
+                    if is_modifier_key(parsed) {
+                        anyhow::bail!(
+                            "Invalid chord \"{value}\": modifiers cannot be the main key"
+                        );
+                    }
+                    key = Some(parsed);
+                }
+            }
+        }
+
+        let key = key.ok_or_else(|| anyhow::anyhow!("Invalid chord \"{value}\": missing key"))?;
+        Ok(Self { modifiers, key })
+    }
+
+    pub(crate) fn matches(&self, key: Key, modifiers: Modifiers) -> bool {
+        self.key == key && self.modifiers == modifiers
+    }
+
+    pub(crate) fn is_reserved(&self) -> bool {
+        // A bare "/" opens chat and Enter/Escape close it, so they can't double as hotkeys.
+        self.modifiers.is_empty()
+            && matches!(
+                self.key,
+                Key::Slash | Key::Return | Key::KpReturn | Key::Escape
+            )
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.shift {
+            f.write_str("Shift+")?;
+        }
+        if self.modifiers.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.meta {
+            f.write_str("Meta+")?;
+        }
+        f.write_str(&key_to_code(self.key))
+    }
+}
+
+fn is_modifier_key(key: Key) -> bool {
+    matches!(
+        key,
+        Key::ControlLeft
+            | Key::ControlRight
+            | Key::ShiftLeft
+            | Key::ShiftRight
+            | Key::Alt
+            | Key::AltGr
+            | Key::MetaLeft
+            | Key::MetaRight
+    )
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HotkeyBinding {
+    action: HotkeyAction,
+    chord: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HotkeyActionEvent {
+    pub(crate) action: HotkeyAction,
+    pub(crate) chord: String,
+    pub(crate) timestamp_ms: i64,
+}
+
+#[derive(Clone, PartialEq)]
+pub(crate) struct HotkeyRegistry {
+    bindings: HashMap<HotkeyAction, Chord>,
+}
+
+impl Default for HotkeyRegistry {
+    fn default() -> Self {
+        let bindings = HotkeyAction::ALL
+            .iter()
+            .filter_map(|action| {
+                Chord::parse(action.default_chord())
+                    .ok()
+                    .map(|chord| (*action, chord))
+            })
+            .collect();
+
+        Self { bindings }
+    }
+}
+
+impl HotkeyRegistry {
+    pub(crate) fn bindings(&self) -> Vec<HotkeyBinding> {
+        HotkeyAction::ALL
+            .iter()
+            .map(|action| HotkeyBinding {
+                action: *action,
+                chord: self.bindings.get(action).map(Chord::to_string),
+            })
+            .collect()
+    }
+
+    pub(crate) fn set(&mut self, action: HotkeyAction, chord: Option<Chord>) -> Result<()> {
+        let Some(chord) = chord else {
+            self.bindings.remove(&action);
+            return Ok(());
+        };
+
+        if chord.is_reserved() {
+            anyhow::bail!("{chord} is reserved for chat and cannot be bound");
+        }
+
+        if let Some(existing) = self.conflicting_action(&chord, action) {
+            anyhow::bail!("{chord} is already bound to {}", existing.as_str());
+        }
+
+        self.bindings.insert(action, chord);
+        Ok(())
+    }
+
+    pub(crate) fn match_key(
+        &self,
+        key: Key,
+        down_keys: &HashSet<Key>,
+    ) -> Option<(HotkeyAction, Chord)> {
+        let modifiers = Modifiers::from_down_keys(down_keys);
+        self.bindings
+            .iter()
+            .find(|(_, chord)| chord.matches(key, modifiers))
+            .map(|(action, chord)| (*action, *chord))
+    }
+
+    pub(crate) fn bound_action(&self, chord: &Chord) -> Option<HotkeyAction> {
+        self.bindings
+            .iter()
+            .find(|(_, existing)| *existing == chord)
+            .map(|(action, _)| *action)
+    }
+
+    fn conflicting_action(&self, chord: &Chord, except: HotkeyAction) -> Option<HotkeyAction> {
+        self.bindings
+            .iter()
+            .find(|(action, existing)| **action != except && *existing == chord)
+            .map(|(action, _)| *action)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut map = serde_json::Map::new();
+        for action in HotkeyAction::ALL {
+            let value = match self.bindings.get(&action) {
+                Some(chord) => serde_json::Value::String(chord.to_string()),
+                None => serde_json::Value::Null,
+            };
+            map.insert(action.as_str().to_string(), value);
+        }
+        serde_json::Value::Object(map)
+    }
+
+    // The stored map is applied as a whole, so a saved swap does not trip over the defaults it replaced.
+    fn apply_json(&mut self, value: &serde_json::Value) {
+        let Some(map) = value.as_object() else {
+            return;
+        };
+
+        let mut registry = Self {
+            bindings: HashMap::new(),
+        };
+        let mut fallbacks = Vec::new();
+        for action in HotkeyAction::ALL {
+            let Some(entry) = map.get(action.as_str()) else {
+                fallbacks.push(action);
+                continue;
+            };
+
+            let chord = match entry.as_str().map(Chord::parse) {
+                Some(Ok(chord)) => Some(chord),
+                Some(Err(err)) => {
+                    eprintln!("Ignoring stored hotkey for {}: {err:#}", action.as_str());
+                    fallbacks.push(action);
+                    continue;
+                }
+                None => None,
+            };
+
+            if let Err(err) = registry.set(action, chord) {
+                eprintln!("Ignoring stored hotkey for {}: {err:#}", action.as_str());
+                fallbacks.push(action);
+            }
+        }
+
+        // Actions without a usable stored chord get their default, unless a stored binding now owns it.
+        for action in fallbacks {
+            let chord = Chord::parse(action.default_chord()).ok();
+            if let Err(err) = registry.set(action, chord) {
+                eprintln!("Leaving {} unbound: {err:#}", action.as_str());
+            }
+        }
+
+        *self = registry;
+    }
+}
+
+pub(crate) fn load_bindings(app: &AppHandle, registry: &mut HotkeyRegistry) -> Result<()> {
+    let store = app.store(crate::STORE_PATH)?;
+    if let Some(value) = store.get(STORE_KEY) {
+        registry.apply_json(&value);
+    }
+
+    Ok(())
+}
+
+pub(crate) fn save_bindings(app: &AppHandle, registry: &HotkeyRegistry) -> Result<()> {
+    let store = app.store(crate::STORE_PATH)?;
+    store.set(STORE_KEY, registry.to_json());
+    store.save()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chord_parsing_round_trips() {
+        let chord = Chord::parse("shift + ctrl + KeyM").unwrap();
+        assert!(chord.modifiers.ctrl && chord.modifiers.shift);
+        assert!(!chord.modifiers.alt && !chord.modifiers.meta);
+        assert_eq!(chord.key, Key::KeyM);
+        assert_eq!(chord.to_string(), "Ctrl+Shift+KeyM");
+
+        assert!(Chord::parse("Ctrl+").is_err());
+        assert!(Chord::parse("Ctrl+Shift").is_err());
+        assert!(Chord::parse("KeyA+Ctrl").is_err());
+        assert!(Chord::parse("Ctrl+NotAKey").is_err());
+        assert!(Chord::parse("F9").is_ok());
+    }
+
+    #[test]
+    fn matching_requires_exact_modifiers() {
+        let registry = HotkeyRegistry::default();
+        let down: HashSet<Key> = [Key::ControlLeft, Key::ShiftRight, Key::KeyM].into();
+        assert_eq!(
+            registry
+                .match_key(Key::KeyM, &down)
+                .map(|(action, _)| action),
+            Some(HotkeyAction::MuteRoom)
+        );
+
+        let with_alt: HashSet<Key> = [Key::ControlLeft, Key::ShiftLeft, Key::Alt, Key::KeyM].into();
+        assert!(registry.match_key(Key::KeyM, &with_alt).is_none());
+
+        let bare: HashSet<Key> = [Key::KeyM].into();
+        assert!(registry.match_key(Key::KeyM, &bare).is_none());
+    }
+
+    #[test]
+    fn conflicts_and_reserved_chords_are_rejected() {
+        let mut registry = HotkeyRegistry::default();
+        let taken = Chord::parse("Ctrl+Shift+KeyM").unwrap();
+        assert!(registry
+            .set(HotkeyAction::FocusBloxchat, Some(taken))
+            .is_err());
+        assert!(registry.set(HotkeyAction::MuteRoom, Some(taken)).is_ok());
+
+        let slash = Chord::parse("Slash").unwrap();
+        assert!(registry
+            .set(HotkeyAction::ToggleOverlay, Some(slash))
+            .is_err());
+
+        registry.set(HotkeyAction::MuteRoom, None).unwrap();
+        assert!(registry
+            .set(HotkeyAction::FocusBloxchat, Some(taken))
+            .is_ok());
+    }
+
+    #[test]
+    fn stored_bindings_skip_invalid_entries() {
+        let mut registry = HotkeyRegistry::default();
+        registry.apply_json(&serde_json::json!({
+            "toggleOverlay": "F8",
+            "muteRoom": null,
+            "scrollChatUp": "Ctrl+Bogus",
+            "focusBloxchat": "F8",
+        }));
+
+        let bindings = registry.to_json();
+        assert_eq!(bindings["toggleOverlay"], "F8");
+        assert!(bindings["muteRoom"].is_null());
+        assert_eq!(bindings["scrollChatUp"], "Ctrl+Shift+PageUp");
+        assert_eq!(bindings["focusBloxchat"], "Ctrl+Shift+KeyF");
+    }
+
+    #[test]
+    fn stored_swaps_and_reassignments_round_trip() {
+        let chord = |value| Some(Chord::parse(value).unwrap());
+        let mut registry = HotkeyRegistry::default();
+        registry
+            .set(HotkeyAction::ToggleClickThrough, None)
+            .unwrap();
+        registry
+            .set(HotkeyAction::ToggleOverlay, chord("Ctrl+Shift+KeyL"))
+            .unwrap();
+        registry
+            .set(HotkeyAction::ToggleClickThrough, chord("Ctrl+Shift+KeyB"))
+            .unwrap();
+        registry.set(HotkeyAction::FocusBloxchat, None).unwrap();
+        registry
+            .set(HotkeyAction::MuteRoom, chord("Ctrl+Shift+KeyF"))
+            .unwrap();
+
+        let mut loaded = HotkeyRegistry::default();
+        loaded.apply_json(&registry.to_json());
+        assert_eq!(loaded.to_json(), registry.to_json());
+
+        let mut loaded = HotkeyRegistry::default();
+        loaded.apply_json(&serde_json::json!({
+            "toggleOverlay": "Ctrl+Shift+KeyF",
+            "focusBloxchat": null,
+        }));
+        let bindings = loaded.to_json();
+        assert_eq!(bindings["toggleOverlay"], "Ctrl+Shift+KeyF");
+        assert!(bindings["focusBloxchat"].is_null());
+        assert_eq!(bindings["muteRoom"], "Ctrl+Shift+KeyM");
+    }
+}
@@ -1,73 +1,629 @@
+use crate::focus::{FocusHandoff, FocusRestore};
+use crate::gamepad::{
+    GamepadAction, GamepadActionEvent, GamepadButton, GamepadSource, GamepadTracker,
+    PlatformGamepadSource, GAMEPAD_POLL_INTERVAL,
+};
+use crate::hotkeys::{Chord, HotkeyAction, HotkeyBinding, HotkeyRegistry, Modifiers};
+use crate::quick_chat::{QuickChatMacro, QuickChatTable};
 use anyhow::Result;
-use rdev::{grab, listen, Event, EventType, Key};
-use serde::Serialize;
+use rdev::{listen, Button, Event, EventType, Key};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::collections::HashSet;
 use std::mem;
-use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter, Manager};
-use windows::Win32::UI::Input::KeyboardAndMouse::{
-    MapVirtualKeyW, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS,
-    KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, MAPVK_VK_TO_VSC_EX, VIRTUAL_KEY,
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_store::StoreExt;
+
+mod compose;
+mod hook;
+mod keys;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(test)]
+mod testing;
+mod trace;
+#[cfg(windows)]
+mod win32;
+
+pub(crate) use compose::ComposeState;
+use compose::{ClipboardRequest, ComposeBuffer};
+use hook::{handle_event, run_hook_worker, HookDecider, HookSnapshot, HookUpdate};
+pub(crate) use keys::{key_from_code, key_to_code};
+use trace::InputTrace;
+pub(crate) use trace::{
+    clear_input_trace, dump_input_trace, get_input_trace_enabled, set_input_trace_enabled,
+    InputTraceDump,
+};
+
+#[cfg(target_os = "linux")]
+use linux::{
+    self as platform, LinuxKeyInjector as PlatformKeyInjector,
+    XkbKeyboardLayout as PlatformKeyboardLayout,
+};
+#[cfg(windows)]
+use win32::{
+    self as platform, Win32KeyInjector as PlatformKeyInjector,
+    Win32KeyboardLayout as PlatformKeyboardLayout,
 };
-use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
 
-#[derive(Clone, Copy)]
-enum ChatKeyPersistenceMode {
+const CUSTOM_LATCH_KEYS_STORE_KEY: &str = "customLatchKeys";
+const CAPTURE_IDLE_TIMEOUT_STORE_KEY: &str = "captureIdleTimeoutSecs";
+const CHAT_SCROLL_MODIFIER_STORE_KEY: &str = "chatScrollModifier";
+const PASSTHROUGH_CHORDS_STORE_KEY: &str = "passthroughChords";
+const CAPTURE_TRIGGER_STORE_KEY: &str = "captureTrigger";
+const MODIFIER_KEYS: [Key; 8] = [
+    Key::ControlLeft,
+    Key::ControlRight,
+    Key::ShiftLeft,
+    Key::ShiftRight,
+    Key::Alt,
+    Key::AltGr,
+    Key::MetaLeft,
+    Key::MetaRight,
+];
+const DEFAULT_CAPTURE_IDLE_TIMEOUT_SECS: u64 = 30;
+const MAX_CAPTURE_IDLE_TIMEOUT_SECS: u64 = 3600;
+// Where the event source cannot say who sent an event (Linux, and rdev's passive listener on Windows),
+// echoes are matched against recent injections by key and direction. A real event for the same key
+// inside this window is then taken for the echo.
+const INJECTION_ECHO_WINDOW: Duration = Duration::from_millis(250);
+const KEY_STATE_RECONCILE_INTERVAL: Duration = Duration::from_millis(500);
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ChatKeyPersistenceMode {
+    #[default]
     Full,
     Wasd,
+    Custom,
     None,
 }
 
 impl ChatKeyPersistenceMode {
-    fn parse(value: &str) -> Self {
-        match value.trim().to_ascii_lowercase().as_str() {
-            "none" => Self::None,
-            "wasd" => Self::Wasd,
-            _ => Self::Full,
-        }
-    }
+    const ALL: [ChatKeyPersistenceMode; 4] = [
+        ChatKeyPersistenceMode::Full,
+        ChatKeyPersistenceMode::Wasd,
+        ChatKeyPersistenceMode::Custom,
+        ChatKeyPersistenceMode::None,
+    ];
 }
 
-impl Default for ChatKeyPersistenceMode {
-    fn default() -> Self {
-        Self::Full
-    }
-}
-
-#[derive(Clone, Copy)]
-enum ChatInputMode {
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ChatInputMode {
+    #[default]
     Focusless,
     Ime,
 }
 
 impl ChatInputMode {
-    fn parse(value: &str) -> Self {
-        match value.trim().to_ascii_lowercase().as_str() {
-            "ime" => Self::Ime,
-            _ => Self::Focusless,
+    const ALL: [ChatInputMode; 2] = [ChatInputMode::Focusless, ChatInputMode::Ime];
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CaptureTrigger {
+    // The chat key opens capture and Enter or Escape closes it.
+    #[default]
+    Toggle,
+    // Capture lasts while the chat key is held and sends on release, like push-to-talk.
+    Hold,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ChatScrollModifier {
+    Off,
+    #[default]
+    Alt,
+    Ctrl,
+    Shift,
+}
+
+impl ChatScrollModifier {
+    fn is_held(self, down_keys: &HashSet<Key>) -> bool {
+        match self {
+            ChatScrollModifier::Off => false,
+            ChatScrollModifier::Alt => is_alt_down(down_keys),
+            ChatScrollModifier::Ctrl => {
+                down_keys.contains(&Key::ControlLeft) || down_keys.contains(&Key::ControlRight)
+            }
+            ChatScrollModifier::Shift => {
+                down_keys.contains(&Key::ShiftLeft) || down_keys.contains(&Key::ShiftRight)
+            }
         }
     }
 }
 
-impl Default for ChatInputMode {
-    fn default() -> Self {
-        Self::Focusless
-    }
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CaptureCapabilities {
+    key_persistence_modes: Vec<ChatKeyPersistenceMode>,
+    input_modes: Vec<ChatInputMode>,
+    mouse_button_latching: bool,
+    grab_active: bool,
+    // False where capture cannot see a password field take focus, so it never steps aside for one.
+    password_field_detection: bool,
 }
 
 #[derive(Default)]
 struct InputCaptureInner {
     physical_down: HashSet<Key>,
-    active: bool,
+    capture: CaptureState,
+    // Transitions not yet emitted to the frontend.
+    capture_changes: Vec<CaptureState>,
     mode: ChatKeyPersistenceMode,
-    input_mode: ChatInputMode,
     latched_keys: HashSet<Key>,
     capture_started_down: HashSet<Key>,
+    buttons_down: HashSet<Button>,
+    latched_buttons: HashSet<Button>,
+    custom_latch_keys: Vec<Key>,
+    // User chords that reach other apps during capture, on top of the built-in system shortcuts.
+    passthrough_chords: Vec<Chord>,
+    hotkeys: HotkeyRegistry,
+    quick_chat: QuickChatTable,
+    gamepad: GamepadTracker,
+    compose: ComposeBuffer,
+    // None falls back to the text rdev attaches to each event.
+    layout: Option<Box<dyn KeyboardLayout>>,
+    // Presses we kept from the OS, so its key state cannot vouch for them.
+    withheld_keys: HashSet<Key>,
+    // Keys whose OS state disagreed on the last reconcile pass.
+    key_state_suspects: HashSet<Key>,
+    // None disables the idle watchdog.
+    capture_idle_timeout: Option<Duration>,
+    last_capture_activity: Option<Instant>,
+    chat_scroll_modifier: ChatScrollModifier,
+    capture_trigger: CaptureTrigger,
+    // The chat key holding a push-to-chat capture open; None while capture is a toggle.
+    hold_key: Option<Key>,
+    listener_status: ListenerStatus,
+    // None where the platform cannot stop a listener thread once it runs.
+    listener_thread_id: Option<u32>,
+    listener_running: bool,
+    // Bumped by every capture start, so the hook can tell a new capture from the one it ended.
+    capture_generation: u64,
+    hook_updates: Option<Sender<HookUpdate>>,
+    published_snapshot: Option<Arc<HookSnapshot>>,
 }
 
-#[derive(Clone, Default)]
+impl InputCaptureInner {
+    fn is_capturing(&self) -> bool {
+        matches!(self.capture, CaptureState::Capturing { .. })
+    }
+
+    fn capture_input_mode(&self) -> Option<ChatInputMode> {
+        match self.capture {
+            CaptureState::Capturing { mode } => Some(mode),
+            _ => None,
+        }
+    }
+
+    fn transition(&mut self, next: CaptureState) -> Result<()> {
+        if !self.capture.can_transition_to(next) {
+            anyhow::bail!(
+                "invalid chat capture transition from {:?} to {next:?}",
+                self.capture
+            );
+        }
+
+        self.capture = next;
+        self.capture_changes.push(next);
+        Ok(())
+    }
+
+    fn end_capture(&mut self, reason: CaptureStopReason) -> (Vec<Key>, Vec<Button>) {
+        if !matches!(self.capture, CaptureState::Idle) {
+            // Starting and Capturing can always stop, and Stopping always settles to Idle.
+            let _ = self.transition(CaptureState::Stopping { reason });
+            let _ = self.transition(CaptureState::Idle);
+        }
+        // The draft survives focus loss and idle stops so reopening chat picks it back up.
+        if matches!(
+            reason,
+            CaptureStopReason::Submit | CaptureStopReason::Cancel | CaptureStopReason::Release
+        ) {
+            self.compose.clear();
+        }
+        self.capture_started_down.clear();
+        self.hold_key = None;
+        let mut keys = mem::take(&mut self.latched_keys);
+        let mut buttons = mem::take(&mut self.latched_buttons);
+        // Push-to-chat ends mid-movement; whatever the player still holds stays down for the game.
+        if reason == CaptureStopReason::Release {
+            keys.retain(|key| !self.physical_down.contains(key));
+            buttons.retain(|button| !self.buttons_down.contains(button));
+        }
+        (keys.into_iter().collect(), buttons.into_iter().collect())
+    }
+
+    fn publish_hook_snapshot(&mut self) {
+        let Some(updates) = &self.hook_updates else {
+            return;
+        };
+        if self
+            .published_snapshot
+            .as_deref()
+            .is_some_and(|snapshot| snapshot.matches(self))
+        {
+            return;
+        }
+
+        let snapshot = Arc::new(HookSnapshot::from_inner(self));
+        if updates
+            .send(HookUpdate::Snapshot(snapshot.clone()))
+            .is_err()
+        {
+            // The hook is gone; the next listener attaches afresh.
+            self.hook_updates = None;
+        }
+        self.published_snapshot = Some(snapshot);
+    }
+
+    // Keys the OS says were released behind the hook's back.
+    fn forget_in_hook(&self, keys: Vec<Key>) {
+        if let Some(updates) = &self.hook_updates {
+            let _ = updates.send(HookUpdate::Forget(keys));
+        }
+    }
+}
+
+// Unlocking republishes the hook snapshot, so no writer can forget to.
+struct InputStateGuard<'a>(MutexGuard<'a, InputCaptureInner>);
+
+impl Deref for InputStateGuard<'_> {
+    type Target = InputCaptureInner;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for InputStateGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for InputStateGuard<'_> {
+    fn drop(&mut self) {
+        self.0.publish_hook_snapshot();
+    }
+}
+
+#[derive(Clone)]
 pub(crate) struct InputCaptureState {
     inner: Arc<Mutex<InputCaptureInner>>,
+    listener_generation: Arc<AtomicU64>,
+    injector: Arc<dyn KeyInjector>,
+    trace: Arc<InputTrace>,
+    focus: Arc<FocusHandoff>,
+    foreground: Arc<ForegroundCache>,
+}
+
+impl Default for InputCaptureState {
+    fn default() -> Self {
+        let state = Self::with_injector(Arc::new(PlatformKeyInjector::default()));
+        if let Ok(mut inner) = state.inner.lock() {
+            inner.layout = Some(Box::<PlatformKeyboardLayout>::default());
+        }
+        state
+    }
+}
+
+impl InputCaptureState {
+    fn with_injector(injector: Arc<dyn KeyInjector>) -> Self {
+        let inner = InputCaptureInner {
+            capture_idle_timeout: Some(Duration::from_secs(DEFAULT_CAPTURE_IDLE_TIMEOUT_SECS)),
+            ..Default::default()
+        };
+
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+            listener_generation: Arc::default(),
+            injector,
+            trace: Arc::default(),
+            focus: Arc::default(),
+            foreground: Arc::default(),
+        }
+    }
+
+    fn lock(&self) -> Result<InputStateGuard<'_>, PoisonError<InputStateGuard<'_>>> {
+        self.inner
+            .lock()
+            .map(InputStateGuard)
+            .map_err(|err| PoisonError::new(InputStateGuard(err.into_inner())))
+    }
+}
+
+pub(crate) trait KeyInjector: Send + Sync {
+    fn send_key(&self, key: Key, up: bool);
+    fn send_button(&self, button: Button, up: bool);
+    fn caps_lock_enabled(&self) -> bool;
+    // The OS's view of whether a key is held, or None when it cannot be queried.
+    fn is_key_down(&self, key: Key) -> Option<bool>;
+    // False when the platform gave us no way to synthesize input, e.g. no uinput and no XTest.
+    fn is_available(&self) -> bool;
+    // Consumes a pending echo when the event source reports input we sent ourselves.
+    fn take_echo(&self, input: InjectedInput) -> bool;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum KeyText {
+    Typed(String),
+    // Starts a dead-key sequence; the next press carries the composed character.
+    Dead,
+    Empty,
+}
+
+pub(crate) trait KeyboardLayout: Send {
+    // Picks up layout switches made since the last capture and drops any pending dead key.
+    fn refresh(&mut self);
+    // None when the layout cannot be read, so the event's own text is used instead.
+    fn key_text(&mut self, key: Key, down_keys: &HashSet<Key>, caps: bool) -> Option<KeyText>;
+}
+
+pub(crate) trait InputSource {
+    // Blocks while delivering events; the callback returns true to keep an event from the game.
+    fn grab(&mut self, callback: Box<dyn Fn(&Event) -> bool>) -> Result<(), String>;
+    fn listen(&mut self, callback: Box<dyn Fn(&Event)>) -> Result<(), String>;
+}
+
+// Receives each event plus whether suppression is possible; returns true to suppress it.
+type EventHandler = Rc<dyn Fn(&Event, bool) -> bool>;
+
+// Grabs through the platform, which on Windows is our own hook; passive listening is rdev's.
+struct SystemInputSource;
+
+impl InputSource for SystemInputSource {
+    fn grab(&mut self, callback: Box<dyn Fn(&Event) -> bool>) -> Result<(), String> {
+        platform::grab(callback)
+    }
+
+    fn listen(&mut self, callback: Box<dyn Fn(&Event)>) -> Result<(), String> {
+        listen(move |event: Event| callback(&event)).map_err(|err| format!("{err:?}"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum InjectedInput {
+    Key { key: Key, up: bool },
+    Button { button: Button, up: bool },
+}
+
+#[derive(Default)]
+struct InjectionLedger {
+    pending: Mutex<Vec<(InjectedInput, Instant)>>,
+}
+
+impl InjectionLedger {
+    fn record(&self, input: InjectedInput, count: usize, now: Instant) {
+        match self.pending.lock() {
+            Ok(mut pending) => pending.extend(std::iter::repeat_n(
+                (input, now + INJECTION_ECHO_WINDOW),
+                count,
+            )),
+            Err(err) => eprintln!("failed to lock injection ledger: {err}"),
+        }
+    }
+
+    fn consume(&self, input: InjectedInput, now: Instant) -> bool {
+        let Ok(mut pending) = self.pending.lock() else {
+            return false;
+        };
+
+        pending.retain(|(_, expires_at)| *expires_at > now);
+        match pending
+            .iter()
+            .position(|(pending_input, _)| *pending_input == input)
+        {
+            Some(index) => {
+                pending.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl InjectedInput {
+    fn from_event_type(event_type: &EventType) -> Option<Self> {
+        match *event_type {
+            EventType::KeyPress(key) => Some(Self::Key { key, up: false }),
+            EventType::KeyRelease(key) => Some(Self::Key { key, up: true }),
+            EventType::ButtonPress(button) => Some(Self::Button { button, up: false }),
+            EventType::ButtonRelease(button) => Some(Self::Button { button, up: true }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub(crate) enum ListenerStatus {
+    #[default]
+    Starting,
+    Grab,
+    Passive {
+        error: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CaptureStopReason {
+    Submit,
+    #[default]
+    Cancel,
+    FocusLost,
+    IdleTimeout,
+    // The push-to-chat key came up, which sends like Submit.
+    Release,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub(crate) enum CaptureState {
+    #[default]
+    Idle,
+    // Latched input is being pressed on the game's behalf.
+    Starting,
+    Capturing {
+        mode: ChatInputMode,
+    },
+    Stopping {
+        reason: CaptureStopReason,
+    },
+}
+
+impl CaptureState {
+    fn can_transition_to(self, next: CaptureState) -> bool {
+        matches!(
+            (self, next),
+            (CaptureState::Idle, CaptureState::Starting)
+                | (CaptureState::Starting, CaptureState::Capturing { .. })
+                | (CaptureState::Starting, CaptureState::Stopping { .. })
+                | (
+                    CaptureState::Capturing { .. },
+                    CaptureState::Stopping { .. }
+                )
+                | (CaptureState::Stopping { .. }, CaptureState::Idle)
+        ) || matches!(
+            (self, next),
+            (CaptureState::Capturing { mode: from }, CaptureState::Capturing { mode: to }) if from != to
+        )
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CaptureStoppedEvent {
+    reason: CaptureStopReason,
+    timestamp_ms: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum PassthroughSource {
+    BuiltIn,
+    Allowlist,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ChordSuppression {
+    chord: String,
+    suppressed: bool,
+    allowed_by: Option<PassthroughSource>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ChatScrollEvent {
+    // Wheel notches; positive scrolls toward older messages.
+    delta: i64,
+    timestamp_ms: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum ImeEvent {
+    // cursor counts UTF-16 units, matching JavaScript string offsets.
+    Preedit {
+        text: String,
+        cursor: usize,
+    },
+    Commit {
+        text: String,
+    },
+    // Screen coordinates in physical pixels.
+    CandidateWindow {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    End,
+}
+
+type ImeEventHandler = Box<dyn Fn(ImeEvent) + Send + Sync>;
+
+// The caret in physical pixels relative to the BloxChat window's client area.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ImeCaret {
+    x: i32,
+    y: i32,
+    height: i32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CaptureSession {
+    // True when composition arrives as ime-composition events instead of through a focused input.
+    ime_bridge: bool,
+    // True when a stop arrived before capture finished starting, so it never began.
+    cancelled: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KeyStateCorrection {
+    // Keys the OS reports as up although we never saw their release.
+    cleared_keys: Vec<String>,
+    // Latches the OS dropped mid-capture, pressed again so the game keeps them held.
+    repressed_latches: Vec<String>,
+    timestamp_ms: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum ForegroundContext {
+    Roblox,
+    App,
+    Other,
+    // A password field has keyboard focus.
+    Secure,
+}
+
+// Written by the foreground watcher and read by the hook, which must never ask the OS itself.
+#[derive(Default)]
+struct ForegroundCache(AtomicU8);
+
+impl ForegroundContext {
+    // Keys typed into other apps are none of our business, so the trace never records them.
+    fn is_traced(self) -> bool {
+        matches!(self, ForegroundContext::Roblox | ForegroundContext::App)
+    }
+}
+
+impl ForegroundCache {
+    fn get(&self) -> ForegroundContext {
+        match self.0.load(Ordering::Relaxed) {
+            1 => ForegroundContext::Roblox,
+            2 => ForegroundContext::App,
+            3 => ForegroundContext::Secure,
+            _ => ForegroundContext::Other,
+        }
+    }
+
+    fn set(&self, context: ForegroundContext) {
+        let value = match context {
+            ForegroundContext::Other => 0,
+            ForegroundContext::Roblox => 1,
+            ForegroundContext::App => 2,
+            ForegroundContext::Secure => 3,
+        };
+        self.0.store(value, Ordering::Relaxed);
+    }
 }
 
 #[derive(Clone, Copy, Serialize)]
@@ -88,232 +644,1070 @@ pub(crate) struct GlobalKeyEvent {
     pub(crate) alt: bool,
     pub(crate) meta: bool,
     pub(crate) repeat: bool,
+    // The press started a dead-key sequence, so it types nothing yet.
+    pub(crate) dead_key: bool,
     pub(crate) timestamp_ms: i64,
 }
 
-pub(crate) fn start_key_listener(app: AppHandle, state: InputCaptureState) {
-    std::thread::spawn(move || {
-        let grab_app = app.clone();
-        let grab_state = state.clone();
-        let grab_callback = move |event: Event| -> Option<Event> {
-            let should_suppress = handle_event(&grab_app, &grab_state, &event, true);
-            if should_suppress { None } else { Some(event) }
-        };
+pub(crate) fn start_key_listener(app: AppHandle, state: InputCaptureState) {
+    let generation = state.listener_generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+    std::thread::spawn(move || {
+        match state.lock() {
+            Ok(mut inner) => {
+                inner.listener_thread_id = platform::current_listener_thread();
+                inner.listener_running = true;
+            }
+            Err(err) => eprintln!("failed to lock input state for listener thread: {err}"),
+        }
+
+        let hook = match HookDecider::attach(&state) {
+            Ok(hook) => RefCell::new(hook),
+            Err(err) => {
+                eprintln!("Failed to attach the input hook: {err:#}");
+                publish_listener_status(
+                    &app,
+                    &state,
+                    ListenerStatus::Failed {
+                        error: format!("{err:#}"),
+                    },
+                );
+                return;
+            }
+        };
+        let (worker, messages) = mpsc::channel();
+        let worker_app = app.clone();
+        let worker_state = state.clone();
+        std::thread::spawn(move || run_hook_worker(&worker_app, &worker_state, messages));
+
+        let handler_state = state.clone();
+        let handler = Rc::new(move |event: &Event, suppression_enabled: bool| {
+            // A retried listener replaces this one; stay out of the way until the hook is gone.
+            if handler_state.listener_generation.load(Ordering::SeqCst) != generation {
+                return false;
+            }
+            handle_event(
+                &handler_state,
+                &mut hook.borrow_mut(),
+                &worker,
+                event,
+                suppression_enabled,
+            )
+        });
+
+        run_input_source(&mut SystemInputSource, handler, &|status| {
+            publish_listener_status(&app, &state, status)
+        });
+
+        // A stopped listener ending late must not mark its replacement as gone.
+        if state.listener_generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        match state.lock() {
+            Ok(mut inner) => inner.listener_running = false,
+            Err(err) => eprintln!("failed to lock input state for listener exit: {err}"),
+        }
+    });
+}
+
+fn run_input_source(
+    source: &mut dyn InputSource,
+    handler: EventHandler,
+    publish: &dyn Fn(ListenerStatus),
+) {
+    // rdev only returns from grab when installing the hook fails.
+    publish(ListenerStatus::Grab);
+    let grab_handler = handler.clone();
+    let Err(err) = source.grab(Box::new(move |event| grab_handler(event, true))) else {
+        return;
+    };
+
+    eprintln!("Error in global input grab listener ({err}), falling back to passive listening");
+    publish(ListenerStatus::Passive { error: err });
+
+    if let Err(err) = source.listen(Box::new(move |event| {
+        let _ = handler(event, false);
+    })) {
+        eprintln!("Error in global input listener: {err}");
+        publish(ListenerStatus::Failed { error: err });
+    }
+}
+
+pub(crate) fn retry_key_listener(
+    app: &AppHandle,
+    state: &InputCaptureState,
+) -> Result<ListenerStatus> {
+    let previous_thread = {
+        let mut inner = state
+            .lock()
+            .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+        if inner.listener_status == ListenerStatus::Grab {
+            return Ok(inner.listener_status.clone());
+        }
+        // A second listener next to one we cannot stop would leak it, and a live grab would keep
+        // the devices from the new one.
+        if inner.listener_running && inner.listener_thread_id.is_none() {
+            anyhow::bail!(
+                "the running input listener cannot be stopped on this platform; restart BloxChat to try again"
+            );
+        }
+        inner.listener_status = ListenerStatus::Starting;
+        inner.listener_thread_id.take()
+    };
+
+    if let Some(thread_id) = previous_thread {
+        platform::stop_listener_thread(thread_id);
+    }
+
+    let _ = app.emit("input-listener-status", ListenerStatus::Starting);
+    start_key_listener(app.clone(), state.clone());
+    Ok(ListenerStatus::Starting)
+}
+
+pub(crate) fn get_listener_status(state: &InputCaptureState) -> Result<ListenerStatus> {
+    Ok(state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?
+        .listener_status
+        .clone())
+}
+
+fn publish_listener_status(app: &AppHandle, state: &InputCaptureState, status: ListenerStatus) {
+    match state.lock() {
+        Ok(mut inner) => inner.listener_status = status.clone(),
+        Err(err) => eprintln!("failed to lock input state for listener status: {err}"),
+    }
+
+    let _ = app.emit("input-listener-status", status);
+}
+
+pub(crate) fn start_chat_capture(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    mode: ChatKeyPersistenceMode,
+    input_mode: ChatInputMode,
+) -> Result<CaptureSession> {
+    // Remember where the player was before IME capture pulls focus to us; a mode switch keeps the first window.
+    if get_capture_state(state)? == CaptureState::Idle {
+        state.focus.record(app)?;
+    }
+    let started = begin_chat_capture(state, mode, input_mode);
+    publish_capture_changes(app, state);
+    if !started? {
+        return Ok(CaptureSession {
+            ime_bridge: false,
+            cancelled: true,
+        });
+    }
+
+    let ime_bridge = input_mode == ChatInputMode::Ime && attach_ime_bridge(app);
+    if !ime_bridge {
+        platform::detach_ime(app);
+    }
+    Ok(CaptureSession {
+        ime_bridge,
+        cancelled: false,
+    })
+}
+
+fn attach_ime_bridge(app: &AppHandle) -> bool {
+    let event_app = app.clone();
+    let on_event: ImeEventHandler = Box::new(move |event| {
+        let _ = event_app.emit("ime-composition", event);
+    });
+    match platform::attach_ime(app, on_event) {
+        Ok(attached) => attached,
+        Err(err) => {
+            eprintln!("Failed to attach the IME bridge: {err}");
+            false
+        }
+    }
+}
+
+pub(crate) fn set_ime_caret(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    caret: ImeCaret,
+) -> Result<()> {
+    if !is_ime_capture(state)? {
+        return Ok(());
+    }
+
+    platform::place_ime_candidates(app, caret).map_err(anyhow::Error::msg)
+}
+
+pub(crate) fn focus_ime_bridge(app: &AppHandle, state: &InputCaptureState) -> Result<()> {
+    if !is_ime_capture(state)? {
+        return Ok(());
+    }
+
+    platform::focus_ime(app).map_err(anyhow::Error::msg)
+}
+
+pub(crate) fn restore_capture_focus(state: &InputCaptureState) -> Result<FocusRestore> {
+    state.focus.restore()
+}
+
+fn is_ime_capture(state: &InputCaptureState) -> Result<bool> {
+    let inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    Ok(inner.capture_input_mode() == Some(ChatInputMode::Ime))
+}
+
+// False when a stop landed while the latch presses went out, so capture never started.
+fn begin_chat_capture(
+    state: &InputCaptureState,
+    mode: ChatKeyPersistenceMode,
+    input_mode: ChatInputMode,
+) -> Result<bool> {
+    let (generation, latched) = {
+        let mut inner = state
+            .lock()
+            .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+
+        // Switching input mode mid-capture keeps the latches already held.
+        if inner.is_capturing() {
+            inner.transition(CaptureState::Capturing { mode: input_mode })?;
+            inner.last_capture_activity = Some(Instant::now());
+            return Ok(true);
+        }
+
+        inner.transition(CaptureState::Starting)?;
+        inner.capture_generation += 1;
+        if let Some(layout) = inner.layout.as_mut() {
+            layout.refresh();
+        }
+        inner.latched_keys =
+            select_latched_keys(&inner.physical_down, mode, &inner.custom_latch_keys);
+        inner.latched_buttons = select_latched_buttons(&inner.buttons_down, mode);
+        inner.mode = mode;
+        inner.capture_started_down = inner.physical_down.clone();
+        // A capture opened without the chat key held (a click, the gamepad, a quick tap) stays a toggle.
+        inner.hold_key = (inner.capture_trigger == CaptureTrigger::Hold
+            && inner.physical_down.contains(&Key::Slash))
+        .then_some(Key::Slash);
+        if let Some(hold_key) = inner.hold_key {
+            inner.latched_keys.remove(&hold_key);
+        }
+        (
+            inner.capture_generation,
+            (
+                inner.latched_keys.iter().copied().collect::<Vec<_>>(),
+                inner.latched_buttons.iter().copied().collect::<Vec<_>>(),
+            ),
+        )
+    };
+
+    // Create a synthetic press for persisted keys so stop can reliably end it with a synthetic release.
+    let (latched_keys, latched_buttons) = latched;
+    for key in latched_keys {
+        state.injector.send_key(key, false);
+    }
+    for button in latched_buttons {
+        state.injector.send_button(button, false);
+    }
+
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    // A stop that raced the latch presses has already scheduled their releases, and may have let a
+    // newer capture start since.
+    if inner.capture_generation != generation || inner.capture != CaptureState::Starting {
+        return Ok(false);
+    }
+    inner.transition(CaptureState::Capturing { mode: input_mode })?;
+    inner.last_capture_activity = Some(Instant::now());
+
+    Ok(true)
+}
+
+pub(crate) fn stop_chat_capture(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    reason: CaptureStopReason,
+) -> Result<()> {
+    let stopped = end_chat_capture(state, reason)?;
+    notify_capture_stopped(app, state, stopped);
+    Ok(())
+}
+
+pub(crate) fn get_capture_state(state: &InputCaptureState) -> Result<CaptureState> {
+    Ok(state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?
+        .capture)
+}
+
+fn publish_capture_changes(app: &AppHandle, state: &InputCaptureState) {
+    let changes = match state.lock() {
+        Ok(mut inner) => mem::take(&mut inner.capture_changes),
+        Err(err) => {
+            eprintln!("failed to lock input state for capture state: {err}");
+            return;
+        }
+    };
+    emit_capture_changes(app, changes);
+}
+
+fn emit_capture_changes(app: &AppHandle, changes: Vec<CaptureState>) {
+    for change in changes {
+        let _ = app.emit("capture-state", change);
+    }
+}
+
+// Returns the event to announce, or None when capture was not running.
+fn end_chat_capture(
+    state: &InputCaptureState,
+    reason: CaptureStopReason,
+) -> Result<Option<CaptureStoppedEvent>> {
+    let (was_active, released) = {
+        let mut inner = state
+            .lock()
+            .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+        let was_active = inner.capture != CaptureState::Idle;
+        (was_active, inner.end_capture(reason))
+    };
+
+    Ok(release_after_stop(state, was_active, released, reason))
+}
+
+fn release_after_stop(
+    state: &InputCaptureState,
+    was_active: bool,
+    (keys, buttons): (Vec<Key>, Vec<Button>),
+    reason: CaptureStopReason,
+) -> Option<CaptureStoppedEvent> {
+    if !keys.is_empty() || !buttons.is_empty() {
+        eprintln!(
+            "Chat capture stopped ({reason:?}), released {}",
+            describe_released(&keys, &buttons)
+        );
+    }
+    schedule_latched_key_release(state.injector.clone(), keys);
+    schedule_latched_button_release(state.injector.clone(), buttons);
+
+    was_active.then(|| CaptureStoppedEvent {
+        reason,
+        timestamp_ms: event_timestamp_ms(),
+    })
+}
+
+fn notify_capture_stopped(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    stopped: Option<CaptureStoppedEvent>,
+) {
+    publish_capture_changes(app, state);
+    if let Some(stopped) = stopped {
+        platform::detach_ime(app);
+        let _ = app.emit("capture-stopped", stopped);
+    }
+}
+
+pub(crate) fn start_gamepad_listener(app: AppHandle, state: InputCaptureState) {
+    std::thread::spawn(move || {
+        // libevdev handles cannot leave the thread that opened them, so the source is built here.
+        let mut source = PlatformGamepadSource::default();
+        loop {
+            match poll_gamepad(&state, &mut source, || foreground_context(&app)) {
+                Ok(actions) => {
+                    for action in actions {
+                        let _ = app.emit(
+                            "gamepad-action",
+                            GamepadActionEvent {
+                                action,
+                                timestamp_ms: event_timestamp_ms(),
+                            },
+                        );
+                    }
+                }
+                Err(err) => eprintln!("Failed to process gamepad input: {err:#}"),
+            }
+            std::thread::sleep(GAMEPAD_POLL_INTERVAL);
+        }
+    });
+}
+
+// The foreground lookup only runs once a poll has actually triggered something.
+fn poll_gamepad(
+    state: &InputCaptureState,
+    source: &mut dyn GamepadSource,
+    context: impl FnOnce() -> ForegroundContext,
+) -> Result<Vec<GamepadAction>> {
+    let held = source.poll();
+    let actions = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?
+        .gamepad
+        .update(held);
+    if actions.is_empty() {
+        return Ok(actions);
+    }
+
+    let context = context();
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    let capturing = inner.is_capturing();
+    if capturing {
+        inner.last_capture_activity = Some(Instant::now());
+    }
+
+    Ok(actions
+        .into_iter()
+        .filter(|action| match action {
+            // Same rule as the Slash key: chat only opens over Roblox and only once.
+            GamepadAction::OpenChat => context == ForegroundContext::Roblox && !capturing,
+            _ => matches!(context, ForegroundContext::Roblox | ForegroundContext::App),
+        })
+        .collect())
+}
+
+pub(crate) fn start_foreground_watcher(app: AppHandle, state: InputCaptureState) {
+    refresh_foreground(&app, &state);
+    let watcher_app = app.clone();
+    platform::watch_foreground(Box::new(move || {
+        refresh_foreground(&watcher_app, &state);
+        match stop_capture_if_focus_lost(&state, state.foreground.get()) {
+            Ok(stopped) => notify_capture_stopped(&watcher_app, &state, stopped),
+            Err(err) => eprintln!("Failed to stop chat capture after focus change: {err:#}"),
+        }
+    }));
+}
+
+// Also called when one of our windows gains or loses focus, which Tauri reports after the OS does.
+pub(crate) fn refresh_foreground(app: &AppHandle, state: &InputCaptureState) {
+    state.foreground.set(foreground_context(app));
+}
+
+// Alt+Tab and the Windows key pass through capture; once the player is elsewhere, hand the keys back.
+fn stop_capture_if_focus_lost(
+    state: &InputCaptureState,
+    context: ForegroundContext,
+) -> Result<Option<CaptureStoppedEvent>> {
+    if matches!(context, ForegroundContext::Roblox | ForegroundContext::App) {
+        return Ok(None);
+    }
+
+    let released = {
+        let mut inner = state
+            .lock()
+            .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+        if !inner.is_capturing() {
+            return Ok(None);
+        }
+        inner.end_capture(CaptureStopReason::FocusLost)
+    };
+
+    Ok(release_after_stop(
+        state,
+        true,
+        released,
+        CaptureStopReason::FocusLost,
+    ))
+}
+
+pub(crate) fn start_input_watchdog(app: AppHandle, state: InputCaptureState) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(KEY_STATE_RECONCILE_INTERVAL);
+        match reconcile_key_state(&state) {
+            Ok(Some(correction)) => {
+                eprintln!("Corrected key state: {correction:?}");
+                let _ = app.emit("key-state-corrected", correction);
+            }
+            Ok(None) => {}
+            Err(err) => eprintln!("Failed to reconcile key state: {err:#}"),
+        }
+
+        match expire_idle_capture(&state, Instant::now()) {
+            Ok(stopped) => notify_capture_stopped(&app, &state, stopped),
+            Err(err) => eprintln!("Failed to check chat capture idle timeout: {err:#}"),
+        }
+    });
+}
+
+// A missed release (hook timeout, UAC prompt, lost hook) would otherwise leave a key "down" forever.
+fn reconcile_key_state(state: &InputCaptureState) -> Result<Option<KeyStateCorrection>> {
+    let (cleared_keys, repressed_latches) = {
+        let mut inner = state
+            .lock()
+            .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+
+        // Latched keys read as held because we injected them, and withheld presses never reached the OS.
+        let unlatched = inner
+            .physical_down
+            .iter()
+            .copied()
+            .filter(|key| !inner.latched_keys.contains(key) && !inner.withheld_keys.contains(key))
+            .filter(|key| state.injector.is_key_down(*key) == Some(false));
+        let dropped_latches =
+            inner.latched_keys.iter().copied().filter(|key| {
+                inner.is_capturing() && state.injector.is_key_down(*key) == Some(false)
+            });
+        let disagreeing = unlatched.chain(dropped_latches).collect::<HashSet<_>>();
+
+        // Only act on keys that disagreed twice in a row, so a press the OS has not applied yet is left alone.
+        let confirmed = disagreeing
+            .intersection(&inner.key_state_suspects)
+            .copied()
+            .collect::<HashSet<_>>();
+        inner.key_state_suspects = &disagreeing - &confirmed;
+
+        let mut cleared_keys = Vec::new();
+        let mut repressed_latches = Vec::new();
+        for key in confirmed {
+            if inner.latched_keys.contains(&key) {
+                repressed_latches.push(key);
+            } else {
+                inner.physical_down.remove(&key);
+                inner.capture_started_down.remove(&key);
+                cleared_keys.push(key);
+            }
+        }
+        if !cleared_keys.is_empty() {
+            inner.forget_in_hook(cleared_keys.clone());
+        }
+
+        (cleared_keys, repressed_latches)
+    };
+
+    if cleared_keys.is_empty() && repressed_latches.is_empty() {
+        return Ok(None);
+    }
+
+    for key in &repressed_latches {
+        state.injector.send_key(*key, false);
+    }
+    let correction = KeyStateCorrection {
+        cleared_keys: cleared_keys.into_iter().map(key_to_code).collect(),
+        repressed_latches: repressed_latches.into_iter().map(key_to_code).collect(),
+        timestamp_ms: event_timestamp_ms(),
+    };
+
+    Ok(Some(correction))
+}
+
+// A webview reload or a lost stop call would otherwise leave latched keys held indefinitely.
+fn expire_idle_capture(
+    state: &InputCaptureState,
+    now: Instant,
+) -> Result<Option<CaptureStoppedEvent>> {
+    let released = {
+        let mut inner = state
+            .lock()
+            .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+        let (Some(timeout), Some(last_activity)) =
+            (inner.capture_idle_timeout, inner.last_capture_activity)
+        else {
+            return Ok(None);
+        };
+        if !inner.is_capturing() || now.duration_since(last_activity) < timeout {
+            return Ok(None);
+        }
+        inner.end_capture(CaptureStopReason::IdleTimeout)
+    };
+
+    Ok(release_after_stop(
+        state,
+        true,
+        released,
+        CaptureStopReason::IdleTimeout,
+    ))
+}
+
+pub(crate) fn install_panic_release(state: InputCaptureState) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // The panicking thread may hold the lock itself, and unwinding has not released it yet.
+        let released = match state.inner.try_lock() {
+            Ok(mut inner) => inner.end_capture(CaptureStopReason::Cancel),
+            Err(TryLockError::Poisoned(poisoned)) => {
+                poisoned.into_inner().end_capture(CaptureStopReason::Cancel)
+            }
+            Err(TryLockError::WouldBlock) => {
+                eprintln!("Input state is locked during panic, cannot release latched input");
+                previous(info);
+                return;
+            }
+        };
+        release_now(state.injector.as_ref(), released, "panic");
+        previous(info);
+    }));
+}
+
+pub(crate) fn release_on_exit(state: &InputCaptureState, trigger: &str) {
+    let released = match state.lock() {
+        Ok(mut inner) => inner.end_capture(CaptureStopReason::Cancel),
+        Err(poisoned) => poisoned.into_inner().end_capture(CaptureStopReason::Cancel),
+    };
+    release_now(state.injector.as_ref(), released, trigger);
+}
+
+// Shutdown paths cannot wait for the retrying release threads, so release synchronously.
+fn release_now(
+    injector: &dyn KeyInjector,
+    (keys, buttons): (Vec<Key>, Vec<Button>),
+    trigger: &str,
+) {
+    if keys.is_empty() && buttons.is_empty() {
+        return;
+    }
+
+    release_latched_keys(injector, &keys);
+    for button in &buttons {
+        injector.send_button(*button, true);
+    }
+    eprintln!(
+        "Released {} on {trigger}",
+        describe_released(&keys, &buttons)
+    );
+}
+
+fn describe_released(keys: &[Key], buttons: &[Button]) -> String {
+    let keys = keys.iter().copied().map(key_to_code).collect::<Vec<_>>();
+    format!("keys {keys:?} and buttons {buttons:?}")
+}
+
+pub(crate) fn get_capture_idle_timeout(state: &InputCaptureState) -> Result<u64> {
+    let inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    Ok(inner
+        .capture_idle_timeout
+        .map_or(0, |timeout| timeout.as_secs()))
+}
+
+pub(crate) fn set_capture_idle_timeout(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    seconds: u64,
+) -> Result<u64> {
+    let timeout = parse_capture_idle_timeout(seconds)?;
+
+    let store = app.store(crate::STORE_PATH)?;
+    store.set(CAPTURE_IDLE_TIMEOUT_STORE_KEY, serde_json::json!(seconds));
+    store.save()?;
+
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    inner.capture_idle_timeout = timeout;
+    Ok(seconds)
+}
+
+// Zero turns the idle watchdog off.
+fn parse_capture_idle_timeout(seconds: u64) -> Result<Option<Duration>> {
+    if seconds > MAX_CAPTURE_IDLE_TIMEOUT_SECS {
+        anyhow::bail!("Idle timeout must be at most {MAX_CAPTURE_IDLE_TIMEOUT_SECS} seconds");
+    }
+
+    Ok((seconds > 0).then(|| Duration::from_secs(seconds)))
+}
+
+pub(crate) fn get_chat_scroll_modifier(state: &InputCaptureState) -> Result<ChatScrollModifier> {
+    let inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    Ok(inner.chat_scroll_modifier)
+}
+
+pub(crate) fn set_chat_scroll_modifier(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    modifier: ChatScrollModifier,
+) -> Result<ChatScrollModifier> {
+    let store = app.store(crate::STORE_PATH)?;
+    store.set(CHAT_SCROLL_MODIFIER_STORE_KEY, serde_json::json!(modifier));
+    store.save()?;
+
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    inner.chat_scroll_modifier = modifier;
+    Ok(modifier)
+}
+
+pub(crate) fn get_capture_trigger(state: &InputCaptureState) -> Result<CaptureTrigger> {
+    let inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    Ok(inner.capture_trigger)
+}
+
+pub(crate) fn set_capture_trigger(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    trigger: CaptureTrigger,
+) -> Result<CaptureTrigger> {
+    let store = app.store(crate::STORE_PATH)?;
+    store.set(CAPTURE_TRIGGER_STORE_KEY, serde_json::json!(trigger));
+    store.save()?;
+
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    inner.capture_trigger = trigger;
+    Ok(trigger)
+}
+
+pub(crate) fn get_passthrough_chords(state: &InputCaptureState) -> Result<Vec<String>> {
+    let inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    Ok(inner
+        .passthrough_chords
+        .iter()
+        .map(Chord::to_string)
+        .collect())
+}
+
+pub(crate) fn set_passthrough_chords(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    values: &[String],
+) -> Result<Vec<String>> {
+    let chords = parse_passthrough_chords(values)?;
+    let values = chords.iter().map(Chord::to_string).collect::<Vec<_>>();
+
+    let store = app.store(crate::STORE_PATH)?;
+    store.set(PASSTHROUGH_CHORDS_STORE_KEY, serde_json::json!(values));
+    store.save()?;
+
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    inner.passthrough_chords = chords;
+    Ok(values)
+}
+
+// Answers for a focusless capture, the only mode that withholds keys from other apps.
+pub(crate) fn test_chord_suppression(
+    state: &InputCaptureState,
+    value: &str,
+) -> Result<ChordSuppression> {
+    let chord = Chord::parse(value.trim())?;
+    let inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+
+    let mut down_keys = HashSet::from([chord.key]);
+    for (held, key) in [
+        (chord.modifiers.ctrl, Key::ControlLeft),
+        (chord.modifiers.shift, Key::ShiftLeft),
+        (chord.modifiers.alt, Key::Alt),
+        (chord.modifiers.meta, Key::MetaLeft),
+    ] {
+        if held {
+            down_keys.insert(key);
+        }
+    }
+
+    let allowed_by = passthrough_source(chord.key, &down_keys, &inner.passthrough_chords);
+    Ok(ChordSuppression {
+        chord: chord.to_string(),
+        suppressed: allowed_by.is_none(),
+        allowed_by,
+    })
+}
+
+pub(crate) fn get_capture_capabilities(state: &InputCaptureState) -> Result<CaptureCapabilities> {
+    let grab_active = get_listener_status(state)? == ListenerStatus::Grab;
+
+    // Latching and focusless typing rely on synthetic input and a suppressing hook.
+    let injection_supported = state.injector.is_available();
+    let key_persistence_modes = ChatKeyPersistenceMode::ALL
+        .into_iter()
+        .filter(|mode| injection_supported || *mode == ChatKeyPersistenceMode::None)
+        .collect();
+    let input_modes = ChatInputMode::ALL
+        .into_iter()
+        .filter(|mode| injection_supported || *mode == ChatInputMode::Ime)
+        .collect();
+
+    Ok(CaptureCapabilities {
+        key_persistence_modes,
+        input_modes,
+        mouse_button_latching: injection_supported,
+        grab_active,
+        password_field_detection: platform::DETECTS_PASSWORD_FIELDS,
+    })
+}
+
+pub(crate) fn load_persisted_settings(app: &AppHandle, state: &InputCaptureState) -> Result<()> {
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    crate::hotkeys::load_bindings(app, &mut inner.hotkeys)?;
+    let inner = &mut *inner;
+    crate::quick_chat::load_macros(app, &mut inner.quick_chat, &inner.hotkeys)?;
+    crate::gamepad::load_chat_combo(app, &mut inner.gamepad)?;
+
+    let store = app.store(crate::STORE_PATH)?;
+    if let Some(value) = store.get(CUSTOM_LATCH_KEYS_STORE_KEY) {
+        let codes: Vec<String> = serde_json::from_value(value).unwrap_or_default();
+        match parse_custom_latch_keys(&codes) {
+            Ok(keys) => inner.custom_latch_keys = keys,
+            Err(err) => eprintln!("Ignoring stored custom latch keys: {err:#}"),
+        }
+    }
+    if let Some(value) = store.get(CAPTURE_IDLE_TIMEOUT_STORE_KEY) {
+        let seconds = serde_json::from_value(value).unwrap_or(DEFAULT_CAPTURE_IDLE_TIMEOUT_SECS);
+        match parse_capture_idle_timeout(seconds) {
+            Ok(timeout) => inner.capture_idle_timeout = timeout,
+            Err(err) => eprintln!("Ignoring stored capture idle timeout: {err:#}"),
+        }
+    }
+    if let Some(value) = store.get(CHAT_SCROLL_MODIFIER_STORE_KEY) {
+        match serde_json::from_value(value) {
+            Ok(modifier) => inner.chat_scroll_modifier = modifier,
+            Err(err) => eprintln!("Ignoring stored chat scroll modifier: {err}"),
+        }
+    }
+    if let Some(value) = store.get(CAPTURE_TRIGGER_STORE_KEY) {
+        match serde_json::from_value(value) {
+            Ok(trigger) => inner.capture_trigger = trigger,
+            Err(err) => eprintln!("Ignoring stored capture trigger: {err}"),
+        }
+    }
+    if let Some(value) = store.get(PASSTHROUGH_CHORDS_STORE_KEY) {
+        let chords: Vec<String> = serde_json::from_value(value).unwrap_or_default();
+        match parse_passthrough_chords(&chords) {
+            Ok(chords) => inner.passthrough_chords = chords,
+            Err(err) => eprintln!("Ignoring stored passthrough chords: {err:#}"),
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn get_custom_latch_keys(state: &InputCaptureState) -> Result<Vec<String>> {
+    let inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    Ok(inner
+        .custom_latch_keys
+        .iter()
+        .copied()
+        .map(key_to_code)
+        .collect())
+}
 
-        if let Err(err) = grab(grab_callback) {
-            eprintln!(
-                "Error in global input grab listener ({err:?}), falling back to passive listening",
-            );
+pub(crate) fn set_custom_latch_keys(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    codes: &[String],
+) -> Result<Vec<String>> {
+    let keys = parse_custom_latch_keys(codes)?;
+    let codes = keys.iter().copied().map(key_to_code).collect::<Vec<_>>();
 
-            let callback = move |event: Event| {
-                let _ = handle_event(&app, &state, &event, false);
-            };
+    let store = app.store(crate::STORE_PATH)?;
+    store.set(CUSTOM_LATCH_KEYS_STORE_KEY, serde_json::json!(codes));
+    store.save()?;
 
-            if let Err(err) = listen(callback) {
-                eprintln!("Error in global input listener: {:?}", err);
-            }
-        }
-    });
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    inner.custom_latch_keys = keys;
+    Ok(codes)
 }
 
-pub(crate) fn start_chat_capture(
-    state: &InputCaptureState,
-    mode: &str,
-    input_mode: &str,
-) -> Result<()> {
-    let mode = ChatKeyPersistenceMode::parse(mode);
-    let input_mode = ChatInputMode::parse(input_mode);
-    let latched = {
-        let mut inner = state
-            .inner
-            .lock()
-            .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+pub(crate) fn get_hotkey_bindings(state: &InputCaptureState) -> Result<Vec<HotkeyBinding>> {
+    let inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    Ok(inner.hotkeys.bindings())
+}
 
-        inner.mode = mode;
-        inner.input_mode = input_mode;
-        inner.capture_started_down = inner.physical_down.clone();
-        inner.latched_keys = select_latched_keys(&inner.physical_down, mode);
-        inner.latched_keys.iter().copied().collect::<Vec<_>>()
-    };
+pub(crate) fn set_hotkey_binding(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    action: HotkeyAction,
+    chord: Option<&str>,
+) -> Result<Vec<HotkeyBinding>> {
+    let chord = chord
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(Chord::parse)
+        .transpose()?;
 
-    // Create a synthetic press for persisted keys so stop can reliably end it with a synthetic release.
-    for key in latched {
-        inject_key_event(key, false);
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    if let Some(chord) = chord.filter(|chord| inner.quick_chat.uses_chord(chord)) {
+        anyhow::bail!("{chord} is already used by a quick chat macro");
     }
+    inner.hotkeys.set(action, chord)?;
+    crate::hotkeys::save_bindings(app, &inner.hotkeys)?;
+    Ok(inner.hotkeys.bindings())
+}
 
+pub(crate) fn reset_hotkey_bindings(
+    app: &AppHandle,
+    state: &InputCaptureState,
+) -> Result<Vec<HotkeyBinding>> {
     let mut inner = state
-        .inner
         .lock()
         .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
-    inner.active = true;
-
-    Ok(())
+    inner.hotkeys = HotkeyRegistry::default();
+    crate::hotkeys::save_bindings(app, &inner.hotkeys)?;
+    Ok(inner.hotkeys.bindings())
 }
 
-pub(crate) fn stop_chat_capture(state: &InputCaptureState) -> Result<()> {
-    let keys_to_release = {
-        let mut inner = state
-            .inner
-            .lock()
-            .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
-        inner.active = false;
-        inner.capture_started_down.clear();
-        mem::take(&mut inner.latched_keys)
-    };
+pub(crate) fn get_quick_chat_macros(state: &InputCaptureState) -> Result<Vec<QuickChatMacro>> {
+    let inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    Ok(inner.quick_chat.macros())
+}
 
-    schedule_latched_key_release(keys_to_release.into_iter().collect());
+pub(crate) fn set_quick_chat_macros(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    macros: &[QuickChatMacro],
+) -> Result<Vec<QuickChatMacro>> {
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    let inner = &mut *inner;
+    inner.quick_chat.set_all(macros, &inner.hotkeys)?;
+    crate::quick_chat::save_macros(app, &inner.quick_chat)?;
+    Ok(inner.quick_chat.macros())
+}
 
-    Ok(())
+pub(crate) fn get_gamepad_chat_combo(state: &InputCaptureState) -> Result<Vec<GamepadButton>> {
+    let inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    Ok(inner.gamepad.chat_combo())
 }
 
-fn handle_event(
+pub(crate) fn set_gamepad_chat_combo(
     app: &AppHandle,
     state: &InputCaptureState,
-    event: &Event,
-    suppression_enabled: bool,
-) -> bool {
-    let can_suppress = suppression_enabled && should_intercept_for_roblox(app);
-
-    match event.event_type {
-        EventType::KeyPress(key) => {
-            let (payload, suppress_event, keys_to_release) = {
-                let mut inner = match state.inner.lock() {
-                    Ok(guard) => guard,
-                    Err(err) => {
-                        eprintln!("failed to lock input state on key press: {err}");
-                        return false;
-                    }
-                };
-
-                let repeat = !inner.physical_down.insert(key);
-                let should_backend_stop = inner.active
-                    && matches!(inner.input_mode, ChatInputMode::Focusless)
-                    && matches!(key, Key::Return | Key::KpReturn | Key::Escape);
-                let suppress_event = if should_backend_stop {
-                    can_suppress
-                } else {
-                    can_suppress
-                        && should_suppress_key_event(&inner, key, KeyPhase::Down, false)
-                };
-                let keys_to_release = if should_backend_stop {
-                    inner.active = false;
-                    inner.capture_started_down.clear();
-                    mem::take(&mut inner.latched_keys).into_iter().collect()
-                } else {
-                    Vec::new()
-                };
-                let payload = build_global_key_event(
-                    key,
-                    KeyPhase::Down,
-                    &inner.physical_down,
-                    repeat,
-                    event.name.as_deref(),
-                );
-                (payload, suppress_event, keys_to_release)
-            };
-
-            schedule_latched_key_release(keys_to_release);
+    buttons: &[GamepadButton],
+) -> Result<Vec<GamepadButton>> {
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    inner.gamepad.set_chat_combo(buttons)?;
+    crate::gamepad::save_chat_combo(app, &inner.gamepad)?;
+    Ok(inner.gamepad.chat_combo())
+}
 
-            let _ = app.emit("global-key", payload);
-            suppress_event
+// The clipboard can block on its owner, so it never runs on the hook thread.
+fn run_clipboard_request(app: AppHandle, state: InputCaptureState, request: ClipboardRequest) {
+    std::thread::spawn(move || match request {
+        ClipboardRequest::Write(text) => {
+            if let Err(err) = crate::clipboard::write_text(text) {
+                eprintln!("Failed to copy chat text: {err:#}");
+            }
         }
-        EventType::KeyRelease(key) => {
-            let (payload, should_reinject, suppress_event) = {
-                let mut inner = match state.inner.lock() {
-                    Ok(guard) => guard,
-                    Err(err) => {
-                        eprintln!("failed to lock input state on key release: {err}");
-                        return false;
-                    }
-                };
-
-                let was_down_at_capture = inner.capture_started_down.remove(&key);
-                let suppress_event =
-                    can_suppress
-                        && should_suppress_key_event(
-                            &inner,
-                            key,
-                            KeyPhase::Up,
-                            was_down_at_capture,
-                        );
-                inner.physical_down.remove(&key);
-                let should_reinject =
-                    inner.active && inner.latched_keys.contains(&key) && !suppress_event;
-                let payload =
-                    build_global_key_event(key, KeyPhase::Up, &inner.physical_down, false, None);
-                (payload, should_reinject, suppress_event)
-            };
-
-            if should_reinject {
-                inject_key_event(key, false);
+        ClipboardRequest::Read => {
+            let pasted =
+                crate::clipboard::read_text().and_then(|text| paste_into_compose(&state, &text));
+            match pasted {
+                Ok(Some(compose)) => {
+                    let _ = app.emit("compose-state", compose);
+                }
+                Ok(None) => {}
+                Err(err) => eprintln!("Failed to paste into chat: {err:#}"),
             }
-
-            let _ = app.emit("global-key", payload);
-            suppress_event
         }
-        _ => false,
-    }
+    });
 }
 
-fn should_intercept_for_roblox(app: &AppHandle) -> bool {
-    if is_app_window_foreground(app) {
-        return false;
+fn paste_into_compose(state: &InputCaptureState, text: &str) -> Result<Option<ComposeState>> {
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    // Capture may have ended while the clipboard was being read.
+    if inner.capture_input_mode() != Some(ChatInputMode::Focusless) {
+        return Ok(None);
     }
 
-    crate::roblox::should_steal_focus(app.clone())
+    Ok(inner.compose.paste(text).then(|| inner.compose.state()))
 }
 
-fn is_app_window_foreground(app: &AppHandle) -> bool {
-    unsafe {
-        let foreground = GetForegroundWindow();
-        if foreground.0 == std::ptr::null_mut() {
-            return false;
-        }
+pub(crate) fn set_compose_state(
+    state: &InputCaptureState,
+    text: &str,
+    cursor: Option<usize>,
+) -> Result<ComposeState> {
+    let mut inner = state
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+    inner.compose.set(text, cursor);
+    Ok(inner.compose.state())
+}
 
-        for window in app.webview_windows().values() {
-            let Ok(hwnd) = window.hwnd() else {
-                continue;
-            };
+fn foreground_context(app: &AppHandle) -> ForegroundContext {
+    if platform::is_app_window_foreground(app) {
+        return ForegroundContext::App;
+    }
 
-            if hwnd.0 == foreground.0 {
-                return true;
-            }
-        }
+    if platform::is_password_field_focused() {
+        return ForegroundContext::Secure;
+    }
 
-        false
+    if crate::roblox::should_steal_focus(app.clone()) {
+        ForegroundContext::Roblox
+    } else {
+        ForegroundContext::Other
     }
 }
 
-fn should_suppress_key_event(
-    inner: &InputCaptureInner,
+// Privacy mode: the webview only hears keys while chat capture owns the keyboard,
+// plus the slash that opens chat in Roblox. Everything else stays in the hook.
+fn should_forward_key_event(
+    context: ForegroundContext,
+    capture_active: bool,
     key: Key,
     phase: KeyPhase,
-    was_down_at_capture: bool,
+    repeat: bool,
 ) -> bool {
-    if !inner.active {
-        return matches!(key, Key::Slash);
-    }
-
-    if !matches!(inner.input_mode, ChatInputMode::Focusless) {
-        return false;
+    match context {
+        ForegroundContext::Secure | ForegroundContext::Other => false,
+        ForegroundContext::App => capture_active,
+        ForegroundContext::Roblox => {
+            capture_active || (matches!(phase, KeyPhase::Down) && !repeat && key == Key::Slash)
+        }
     }
+}
 
-    if should_allow_system_shortcut_during_capture(key, &inner.physical_down) {
-        return false;
-    }
+fn should_allow_system_shortcut_during_capture(
+    key: Key,
+    down_keys: &HashSet<Key>,
+    passthrough_chords: &[Chord],
+) -> bool {
+    passthrough_source(key, down_keys, passthrough_chords).is_some()
+}
 
-    if inner.latched_keys.contains(&key) {
-        return true;
+fn passthrough_source(
+    key: Key,
+    down_keys: &HashSet<Key>,
+    passthrough_chords: &[Chord],
+) -> Option<PassthroughSource> {
+    if is_builtin_system_shortcut(key, down_keys) {
+        return Some(PassthroughSource::BuiltIn);
     }
 
-    if matches!(phase, KeyPhase::Up) && was_down_at_capture {
-        return false;
-    }
+    matches_passthrough_chord(passthrough_chords, key, down_keys)
+        .then_some(PassthroughSource::Allowlist)
+}
 
-    true
+fn matches_passthrough_chord(
+    passthrough_chords: &[Chord],
+    key: Key,
+    down_keys: &HashSet<Key>,
+) -> bool {
+    let modifiers = Modifiers::from_down_keys(down_keys);
+    passthrough_chords
+        .iter()
+        .any(|chord| chord.matches(key, modifiers))
 }
 
-fn should_allow_system_shortcut_during_capture(key: Key, down_keys: &HashSet<Key>) -> bool {
+fn is_builtin_system_shortcut(key: Key, down_keys: &HashSet<Key>) -> bool {
     if matches!(key, Key::Alt | Key::AltGr | Key::MetaLeft | Key::MetaRight) {
         return true;
     }
@@ -344,12 +1738,13 @@ fn build_global_key_event(
     key: Key,
     phase: KeyPhase,
     down_keys: &HashSet<Key>,
+    caps: bool,
     repeat: bool,
-    text: Option<&str>,
+    text: Option<String>,
+    dead_key: bool,
 ) -> GlobalKeyEvent {
     let ctrl = down_keys.contains(&Key::ControlLeft) || down_keys.contains(&Key::ControlRight);
     let shift = down_keys.contains(&Key::ShiftLeft) || down_keys.contains(&Key::ShiftRight);
-    let caps = is_caps_lock_enabled();
     let alt = down_keys.contains(&Key::Alt) || down_keys.contains(&Key::AltGr);
     let meta = down_keys.contains(&Key::MetaLeft) || down_keys.contains(&Key::MetaRight);
 
@@ -357,7 +1752,7 @@ fn build_global_key_event(
 
     GlobalKeyEvent {
         code: key_to_code(key),
-        text: sanitize_event_text(text),
+        text,
         phase,
         ctrl,
         shift,
@@ -365,13 +1760,37 @@ fn build_global_key_event(
         alt,
         meta,
         repeat,
+        dead_key,
         timestamp_ms,
     }
 }
 
-fn is_caps_lock_enabled() -> bool {
-    // 0x14 = VK_CAPITAL. The low-order bit of GetKeyState indicates toggle state.
-    unsafe { windows::Win32::UI::Input::KeyboardAndMouse::GetKeyState(0x14) & 1 != 0 }
+fn derive_key_text(
+    inner: &mut InputCaptureInner,
+    key: Key,
+    caps: bool,
+    fallback: Option<&str>,
+) -> (Option<String>, bool) {
+    let down_keys = &inner.physical_down;
+    let ctrl = down_keys.contains(&Key::ControlLeft) || down_keys.contains(&Key::ControlRight);
+    let alt = down_keys.contains(&Key::Alt);
+    let meta = down_keys.contains(&Key::MetaLeft) || down_keys.contains(&Key::MetaRight);
+    // Windows layouts report AltGr as Ctrl+Alt, so that pair still types text.
+    let altgr = down_keys.contains(&Key::AltGr) || (ctrl && alt);
+    if meta || ((ctrl || alt) && !altgr) {
+        return (None, false);
+    }
+
+    let text = inner
+        .layout
+        .as_mut()
+        .and_then(|layout| layout.key_text(key, &inner.physical_down, caps));
+    match text {
+        Some(KeyText::Typed(text)) => (sanitize_event_text(Some(&text)), false),
+        Some(KeyText::Dead) => (None, true),
+        Some(KeyText::Empty) => (None, false),
+        None => (sanitize_event_text(fallback), false),
+    }
 }
 
 fn sanitize_event_text(value: Option<&str>) -> Option<String> {
@@ -392,7 +1811,7 @@ fn sanitize_event_text(value: Option<&str>) -> Option<String> {
     Some(normalized)
 }
 
-fn schedule_latched_key_release(keys: Vec<Key>) {
+fn schedule_latched_key_release(injector: Arc<dyn KeyInjector>, keys: Vec<Key>) {
     if keys.is_empty() {
         return;
     }
@@ -405,19 +1824,38 @@ fn schedule_latched_key_release(keys: Vec<Key>) {
             if attempt > 0 {
                 std::thread::sleep(std::time::Duration::from_millis(14));
             }
-            release_latched_keys(keys.clone());
+            release_latched_keys(injector.as_ref(), &keys);
+        }
+    });
+}
+
+fn schedule_latched_button_release(injector: Arc<dyn KeyInjector>, buttons: Vec<Button>) {
+    if buttons.is_empty() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(8));
+
+        for attempt in 0..4 {
+            if attempt > 0 {
+                std::thread::sleep(std::time::Duration::from_millis(14));
+            }
+            for button in &buttons {
+                injector.send_button(*button, true);
+            }
         }
     });
 }
 
-fn release_latched_keys(keys: Vec<Key>) {
+fn release_latched_keys(injector: &dyn KeyInjector, keys: &[Key]) {
     for key in keys {
         // Cleanup should only release keys that we explicitly latched.
-        inject_key_event(key, true);
+        injector.send_key(*key, true);
     }
 }
 
-fn event_timestamp_ms() -> i64 {
+pub(crate) fn event_timestamp_ms() -> i64 {
     use std::time::{SystemTime, UNIX_EPOCH};
 
     let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
@@ -428,7 +1866,11 @@ fn event_timestamp_ms() -> i64 {
     now.as_millis() as i64
 }
 
-fn select_latched_keys(physical_down: &HashSet<Key>, mode: ChatKeyPersistenceMode) -> HashSet<Key> {
+fn select_latched_keys(
+    physical_down: &HashSet<Key>,
+    mode: ChatKeyPersistenceMode,
+    custom_keys: &[Key],
+) -> HashSet<Key> {
     match mode {
         ChatKeyPersistenceMode::None => HashSet::new(),
         ChatKeyPersistenceMode::Wasd => physical_down
@@ -436,6 +1878,11 @@ fn select_latched_keys(physical_down: &HashSet<Key>, mode: ChatKeyPersistenceMod
             .copied()
             .filter(|key| matches!(key, Key::KeyW | Key::KeyA | Key::KeyS | Key::KeyD))
             .collect(),
+        ChatKeyPersistenceMode::Custom => physical_down
+            .iter()
+            .copied()
+            .filter(|key| custom_keys.contains(key))
+            .collect(),
         ChatKeyPersistenceMode::Full => physical_down
             .iter()
             .copied()
@@ -444,279 +1891,945 @@ fn select_latched_keys(physical_down: &HashSet<Key>, mode: ChatKeyPersistenceMod
     }
 }
 
+fn select_latched_buttons(
+    buttons_down: &HashSet<Button>,
+    mode: ChatKeyPersistenceMode,
+) -> HashSet<Button> {
+    if matches!(mode, ChatKeyPersistenceMode::None) {
+        return HashSet::new();
+    }
+
+    buttons_down
+        .iter()
+        .copied()
+        .filter(|button| is_latchable_button(*button))
+        .collect()
+}
+
+fn is_latchable_button(button: Button) -> bool {
+    // rdev reports the side buttons as Unknown(XBUTTON1 | XBUTTON2).
+    matches!(
+        button,
+        Button::Left | Button::Right | Button::Middle | Button::Unknown(1 | 2)
+    )
+}
+
+fn parse_custom_latch_keys(codes: &[String]) -> Result<Vec<Key>> {
+    let mut keys = Vec::new();
+    for code in codes
+        .iter()
+        .map(|code| code.trim())
+        .filter(|code| !code.is_empty())
+    {
+        let key = key_from_code(code).ok_or_else(|| anyhow::anyhow!("Unknown key \"{code}\""))?;
+        if !is_full_latch_eligible(key) {
+            anyhow::bail!("{code} cannot be kept held while chatting");
+        }
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    Ok(keys)
+}
+
+fn parse_passthrough_chords(values: &[String]) -> Result<Vec<Chord>> {
+    let mut chords = Vec::new();
+    for value in values
+        .iter()
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+    {
+        let chord = Chord::parse(value)?;
+        if chord.is_reserved() {
+            anyhow::bail!("{chord} is reserved for chat and cannot pass through");
+        }
+        if !chords.contains(&chord) {
+            chords.push(chord);
+        }
+    }
+
+    Ok(chords)
+}
+
 fn is_full_latch_eligible(key: Key) -> bool {
     if matches!(key, Key::Slash | Key::Escape | Key::Return) {
         return false;
     }
 
-    key_to_virtual_key(key).is_some()
-}
-
-fn key_to_code(key: Key) -> String {
-    let code = match key {
-        Key::KeyA => "KeyA",
-        Key::KeyB => "KeyB",
-        Key::KeyC => "KeyC",
-        Key::KeyD => "KeyD",
-        Key::KeyE => "KeyE",
-        Key::KeyF => "KeyF",
-        Key::KeyG => "KeyG",
-        Key::KeyH => "KeyH",
-        Key::KeyI => "KeyI",
-        Key::KeyJ => "KeyJ",
-        Key::KeyK => "KeyK",
-        Key::KeyL => "KeyL",
-        Key::KeyM => "KeyM",
-        Key::KeyN => "KeyN",
-        Key::KeyO => "KeyO",
-        Key::KeyP => "KeyP",
-        Key::KeyQ => "KeyQ",
-        Key::KeyR => "KeyR",
-        Key::KeyS => "KeyS",
-        Key::KeyT => "KeyT",
-        Key::KeyU => "KeyU",
-        Key::KeyV => "KeyV",
-        Key::KeyW => "KeyW",
-        Key::KeyX => "KeyX",
-        Key::KeyY => "KeyY",
-        Key::KeyZ => "KeyZ",
-        Key::Num1 => "Digit1",
-        Key::Num2 => "Digit2",
-        Key::Num3 => "Digit3",
-        Key::Num4 => "Digit4",
-        Key::Num5 => "Digit5",
-        Key::Num6 => "Digit6",
-        Key::Num7 => "Digit7",
-        Key::Num8 => "Digit8",
-        Key::Num9 => "Digit9",
-        Key::Num0 => "Digit0",
-        Key::Escape => "Escape",
-        Key::F1 => "F1",
-        Key::F2 => "F2",
-        Key::F3 => "F3",
-        Key::F4 => "F4",
-        Key::F5 => "F5",
-        Key::F6 => "F6",
-        Key::F7 => "F7",
-        Key::F8 => "F8",
-        Key::F9 => "F9",
-        Key::F10 => "F10",
-        Key::F11 => "F11",
-        Key::F12 => "F12",
-        Key::BackQuote => "Backquote",
-        Key::Minus => "Minus",
-        Key::Equal => "Equal",
-        Key::Backspace => "Backspace",
-        Key::Tab => "Tab",
-        Key::LeftBracket => "BracketLeft",
-        Key::RightBracket => "BracketRight",
-        Key::BackSlash => "Backslash",
-        Key::CapsLock => "CapsLock",
-        Key::SemiColon => "Semicolon",
-        Key::Quote => "Quote",
-        Key::Return => "Enter",
-        Key::ShiftLeft => "ShiftLeft",
-        Key::ShiftRight => "ShiftRight",
-        Key::ControlLeft => "ControlLeft",
-        Key::ControlRight => "ControlRight",
-        Key::Alt => "AltLeft",
-        Key::AltGr => "AltRight",
-        Key::MetaLeft => "MetaLeft",
-        Key::MetaRight => "MetaRight",
-        Key::Space => "Space",
-        Key::PrintScreen => "PrintScreen",
-        Key::ScrollLock => "ScrollLock",
-        Key::Pause => "Pause",
-        Key::Insert => "Insert",
-        Key::Home => "Home",
-        Key::PageUp => "PageUp",
-        Key::Delete => "Delete",
-        Key::End => "End",
-        Key::PageDown => "PageDown",
-        Key::RightArrow => "ArrowRight",
-        Key::LeftArrow => "ArrowLeft",
-        Key::DownArrow => "ArrowDown",
-        Key::UpArrow => "ArrowUp",
-        Key::NumLock => "NumLock",
-        Key::Kp0 => "Numpad0",
-        Key::Kp1 => "Numpad1",
-        Key::Kp2 => "Numpad2",
-        Key::Kp3 => "Numpad3",
-        Key::Kp4 => "Numpad4",
-        Key::Kp5 => "Numpad5",
-        Key::Kp6 => "Numpad6",
-        Key::Kp7 => "Numpad7",
-        Key::Kp8 => "Numpad8",
-        Key::Kp9 => "Numpad9",
-        Key::KpMultiply => "NumpadMultiply",
-        Key::KpPlus => "NumpadAdd",
-        Key::KpMinus => "NumpadSubtract",
-        Key::KpDelete => "NumpadDecimal",
-        Key::KpDivide => "NumpadDivide",
-        Key::KpReturn => "NumpadEnter",
-        Key::Slash => "Slash",
-        Key::Dot => "Period",
-        Key::Comma => "Comma",
-        _ => return format!("{key:?}"),
-    };
+    platform::can_inject_key(key)
+}
 
-    code.to_string()
-}
-
-fn key_to_virtual_key(key: Key) -> Option<VIRTUAL_KEY> {
-    let vk = match key {
-        Key::KeyA => 0x41,
-        Key::KeyB => 0x42,
-        Key::KeyC => 0x43,
-        Key::KeyD => 0x44,
-        Key::KeyE => 0x45,
-        Key::KeyF => 0x46,
-        Key::KeyG => 0x47,
-        Key::KeyH => 0x48,
-        Key::KeyI => 0x49,
-        Key::KeyJ => 0x4A,
-        Key::KeyK => 0x4B,
-        Key::KeyL => 0x4C,
-        Key::KeyM => 0x4D,
-        Key::KeyN => 0x4E,
-        Key::KeyO => 0x4F,
-        Key::KeyP => 0x50,
-        Key::KeyQ => 0x51,
-        Key::KeyR => 0x52,
-        Key::KeyS => 0x53,
-        Key::KeyT => 0x54,
-        Key::KeyU => 0x55,
-        Key::KeyV => 0x56,
-        Key::KeyW => 0x57,
-        Key::KeyX => 0x58,
-        Key::KeyY => 0x59,
-        Key::KeyZ => 0x5A,
-        Key::Num1 => 0x31,
-        Key::Num2 => 0x32,
-        Key::Num3 => 0x33,
-        Key::Num4 => 0x34,
-        Key::Num5 => 0x35,
-        Key::Num6 => 0x36,
-        Key::Num7 => 0x37,
-        Key::Num8 => 0x38,
-        Key::Num9 => 0x39,
-        Key::Num0 => 0x30,
-        Key::Escape => 0x1B,
-        Key::BackQuote => 0xC0,
-        Key::Minus => 0xBD,
-        Key::Equal => 0xBB,
-        Key::Backspace => 0x08,
-        Key::Tab => 0x09,
-        Key::LeftBracket => 0xDB,
-        Key::RightBracket => 0xDD,
-        Key::BackSlash => 0xDC,
-        Key::CapsLock => 0x14,
-        Key::SemiColon => 0xBA,
-        Key::Quote => 0xDE,
-        Key::Return => 0x0D,
-        Key::ShiftLeft => 0xA0,
-        Key::ShiftRight => 0xA1,
-        Key::ControlLeft => 0xA2,
-        Key::ControlRight => 0xA3,
-        Key::Alt => 0xA4,
-        Key::AltGr => 0xA5,
-        Key::MetaLeft => 0x5B,
-        Key::MetaRight => 0x5C,
-        Key::Space => 0x20,
-        Key::PrintScreen => 0x2C,
-        Key::ScrollLock => 0x91,
-        Key::Pause => 0x13,
-        Key::Insert => 0x2D,
-        Key::Home => 0x24,
-        Key::PageUp => 0x21,
-        Key::Delete => 0x2E,
-        Key::End => 0x23,
-        Key::PageDown => 0x22,
-        Key::RightArrow => 0x27,
-        Key::LeftArrow => 0x25,
-        Key::DownArrow => 0x28,
-        Key::UpArrow => 0x26,
-        Key::NumLock => 0x90,
-        Key::Kp0 => 0x60,
-        Key::Kp1 => 0x61,
-        Key::Kp2 => 0x62,
-        Key::Kp3 => 0x63,
-        Key::Kp4 => 0x64,
-        Key::Kp5 => 0x65,
-        Key::Kp6 => 0x66,
-        Key::Kp7 => 0x67,
-        Key::Kp8 => 0x68,
-        Key::Kp9 => 0x69,
-        Key::KpMultiply => 0x6A,
-        Key::KpPlus => 0x6B,
-        Key::KpMinus => 0x6D,
-        Key::KpDelete => 0x6E,
-        Key::KpDivide => 0x6F,
-        Key::KpReturn => 0x0D,
-        Key::Slash => 0xBF,
-        Key::Dot => 0xBE,
-        Key::Comma => 0xBC,
-        _ => return None,
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::{event, key_input, process_event, Harness, TableLayout};
+
+    #[test]
+    fn custom_latch_keys_reject_uninjectable_keys() {
+        let codes = ["ArrowUp", "ShiftLeft", " KeyC ", "ArrowUp"].map(String::from);
+        assert_eq!(
+            parse_custom_latch_keys(&codes).unwrap(),
+            vec![Key::UpArrow, Key::ShiftLeft, Key::KeyC]
+        );
+
+        assert!(parse_custom_latch_keys(&["Slash".to_string()]).is_err());
+        assert!(parse_custom_latch_keys(&["Function".to_string()]).is_err());
+        assert!(parse_custom_latch_keys(&["NotAKey".to_string()]).is_err());
+    }
 
-    Some(VIRTUAL_KEY(vk))
-}
+    #[test]
+    fn custom_mode_only_latches_listed_keys() {
+        let down: HashSet<Key> = [Key::UpArrow, Key::KeyW, Key::ControlLeft].into();
+        let custom = [Key::UpArrow, Key::ControlLeft];
+        assert_eq!(
+            select_latched_keys(&down, ChatKeyPersistenceMode::Custom, &custom),
+            [Key::UpArrow, Key::ControlLeft].into()
+        );
+        assert_eq!(
+            select_latched_keys(&down, ChatKeyPersistenceMode::Wasd, &custom),
+            [Key::KeyW].into()
+        );
+    }
 
-fn inject_key_event(key: Key, key_up: bool) {
-    let Some(vk) = key_to_virtual_key(key) else {
-        return;
-    };
+    #[test]
+    fn held_mouse_buttons_latch_unless_persistence_is_off() {
+        let down: HashSet<Button> = [Button::Right, Button::Unknown(2), Button::Unknown(7)].into();
+        assert_eq!(
+            select_latched_buttons(&down, ChatKeyPersistenceMode::Wasd),
+            [Button::Right, Button::Unknown(2)].into()
+        );
+        assert!(select_latched_buttons(&down, ChatKeyPersistenceMode::None).is_empty());
+    }
+
+    #[test]
+    fn capture_modes_reject_unknown_values() {
+        let mode: ChatKeyPersistenceMode =
+            serde_json::from_value(serde_json::json!("wasd")).unwrap();
+        assert_eq!(mode, ChatKeyPersistenceMode::Wasd);
+
+        let err = serde_json::from_value::<ChatKeyPersistenceMode>(serde_json::json!("wsad"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown variant `wsad`"), "{err}");
+        assert!(err.contains("`full`, `wasd`, `custom`, `none`"), "{err}");
+
+        assert!(serde_json::from_value::<ChatInputMode>(serde_json::json!("IME")).is_err());
+    }
 
-    let mapped = unsafe { MapVirtualKeyW(vk.0 as u32, MAPVK_VK_TO_VSC_EX) };
-    if mapped != 0 {
-        let scan_code = (mapped & 0xFF) as u16;
-        let extended_prefix = mapped & 0xFF00;
+    #[test]
+    fn idle_keys_are_withheld_except_the_chat_trigger() {
+        let roblox = ForegroundContext::Roblox;
+        assert!(should_forward_key_event(
+            roblox,
+            false,
+            Key::Slash,
+            KeyPhase::Down,
+            false
+        ));
+        assert!(!should_forward_key_event(
+            roblox,
+            false,
+            Key::Slash,
+            KeyPhase::Down,
+            true
+        ));
+        assert!(!should_forward_key_event(
+            roblox,
+            false,
+            Key::Slash,
+            KeyPhase::Up,
+            false
+        ));
+        assert!(!should_forward_key_event(
+            roblox,
+            false,
+            Key::KeyW,
+            KeyPhase::Down,
+            false
+        ));
+        assert!(!should_forward_key_event(
+            ForegroundContext::App,
+            false,
+            Key::KeyA,
+            KeyPhase::Down,
+            false
+        ));
+        assert!(!should_forward_key_event(
+            ForegroundContext::Other,
+            false,
+            Key::Slash,
+            KeyPhase::Down,
+            false
+        ));
+    }
 
-        let mut flags = KEYEVENTF_SCANCODE;
-        if extended_prefix == 0xE000 || extended_prefix == 0xE100 {
-            flags |= KEYEVENTF_EXTENDEDKEY;
+    #[test]
+    fn capture_forwards_only_in_roblox_or_bloxchat() {
+        for phase in [KeyPhase::Down, KeyPhase::Up] {
+            assert!(should_forward_key_event(
+                ForegroundContext::Roblox,
+                true,
+                Key::KeyH,
+                phase,
+                false
+            ));
+            assert!(should_forward_key_event(
+                ForegroundContext::App,
+                true,
+                Key::Escape,
+                phase,
+                false
+            ));
+            assert!(!should_forward_key_event(
+                ForegroundContext::Other,
+                true,
+                Key::KeyH,
+                phase,
+                false
+            ));
         }
-        if key_up {
-            flags |= KEYEVENTF_KEYUP;
+    }
+
+    #[test]
+    fn password_fields_are_never_forwarded() {
+        for active in [false, true] {
+            for key in [Key::Slash, Key::KeyP, Key::Return] {
+                assert!(!should_forward_key_event(
+                    ForegroundContext::Secure,
+                    active,
+                    key,
+                    KeyPhase::Down,
+                    false
+                ));
+                assert!(!should_forward_key_event(
+                    ForegroundContext::Secure,
+                    active,
+                    key,
+                    KeyPhase::Up,
+                    false
+                ));
+            }
         }
+    }
 
-        let input = INPUT {
-            r#type: INPUT_KEYBOARD,
-            Anonymous: INPUT_0 {
-                ki: KEYBDINPUT {
-                    wVk: VIRTUAL_KEY(0),
-                    wScan: scan_code,
-                    dwFlags: flags,
-                    time: 0,
-                    dwExtraInfo: 0,
-                },
+    #[test]
+    fn opening_chat_while_holding_w_keeps_w_held() {
+        let harness = Harness::new();
+        assert!(!harness.press(Key::KeyW).suppress);
+        let slash = harness.press(Key::Slash);
+        assert!(slash.suppress && slash.key_event.is_some());
+        assert!(harness.release(Key::Slash).suppress);
+
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        assert_eq!(harness.injector.sent(), vec![key_input(Key::KeyW, false)]);
+        harness.deliver_echoes();
+
+        // Autorepeat from the held key must not type into the game or drop the latch.
+        let repeat = harness.press(Key::KeyW);
+        assert!(repeat.suppress);
+        assert!(repeat.key_event.is_some_and(|payload| payload.repeat));
+        let typed = harness.press(Key::KeyH);
+        assert!(typed.suppress && typed.key_event.is_some());
+        assert!(harness.release(Key::KeyH).suppress);
+        assert_eq!(harness.injector.sent().len(), 1);
+    }
+
+    #[test]
+    fn releasing_w_mid_capture_defers_the_release_until_stop() {
+        let harness = Harness::new();
+        harness.press(Key::KeyW);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Wasd,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        harness.deliver_echoes();
+
+        assert!(harness.release(Key::KeyW).suppress);
+        assert!(harness.physical_down().is_empty());
+        assert_eq!(harness.sent_count(key_input(Key::KeyW, true)), 0);
+
+        end_chat_capture(&harness.state, CaptureStopReason::Cancel).unwrap();
+        harness.wait_for_sent(key_input(Key::KeyW, true), 4);
+        harness.deliver_echoes();
+        assert!(harness.physical_down().is_empty());
+    }
+
+    #[test]
+    fn enter_stops_capture_and_releases_latches() {
+        let harness = Harness::new();
+        harness.press(Key::KeyA);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        harness.deliver_echoes();
+
+        let enter = harness.press(Key::Return);
+        assert!(enter.suppress);
+        assert!(enter.key_event.is_some());
+        assert!(!harness.is_active());
+        harness.wait_for_sent(key_input(Key::KeyA, true), 4);
+
+        // With capture over, keys flow to the game untouched again.
+        assert!(!harness.release(Key::Return).suppress);
+        assert!(!harness.press(Key::KeyH).suppress);
+    }
+
+    #[test]
+    fn releasing_the_held_chat_key_sends_and_keeps_held_movement() {
+        let harness = Harness::new();
+        harness.state.lock().unwrap().capture_trigger = CaptureTrigger::Hold;
+        harness.press(Key::KeyW);
+        harness.press(Key::KeyA);
+        assert!(harness.press(Key::Slash).suppress);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Wasd,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        harness.deliver_echoes();
+
+        assert!(harness.press(Key::KeyH).suppress);
+        assert!(harness.release(Key::KeyH).suppress);
+        // Letting go of A mid-message is deferred as usual; W is still held when chat closes.
+        assert!(harness.release(Key::KeyA).suppress);
+
+        let release = harness.release(Key::Slash);
+        assert!(release.suppress);
+        assert_eq!(
+            release.stopped.map(|stopped| stopped.reason),
+            Some(CaptureStopReason::Release)
+        );
+        assert!(!harness.is_active());
+        harness.wait_for_sent(key_input(Key::KeyA, true), 4);
+        assert_eq!(harness.sent_count(key_input(Key::KeyW, true)), 0);
+
+        harness.deliver_echoes();
+        assert!(!harness.release(Key::KeyW).suppress);
+        assert!(!harness.press(Key::KeyH).suppress);
+    }
+
+    #[test]
+    fn hold_trigger_keeps_a_capture_opened_by_a_tap_as_a_toggle() {
+        let harness = Harness::new();
+        harness.state.lock().unwrap().capture_trigger = CaptureTrigger::Hold;
+        harness.press(Key::Slash);
+        harness.release(Key::Slash);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+
+        let slash = harness.press(Key::Slash);
+        assert!(slash.suppress && slash.key_event.is_some());
+        let release = harness.release(Key::Slash);
+        assert!(release.suppress && release.stopped.is_none());
+        assert!(harness.is_active());
+
+        assert!(harness.press(Key::Return).suppress);
+        assert!(!harness.is_active());
+    }
+
+    struct ScriptedSource {
+        grab_error: Option<String>,
+        events: Vec<EventType>,
+        suppressed: Vec<bool>,
+    }
+
+    impl InputSource for ScriptedSource {
+        fn grab(&mut self, callback: Box<dyn Fn(&Event) -> bool>) -> Result<(), String> {
+            if let Some(err) = self.grab_error.clone() {
+                return Err(err);
+            }
+            for event_type in &self.events {
+                self.suppressed.push(callback(&event(*event_type)));
+            }
+            Ok(())
+        }
+
+        fn listen(&mut self, callback: Box<dyn Fn(&Event)>) -> Result<(), String> {
+            for event_type in &self.events {
+                callback(&event(*event_type));
+            }
+            Ok(())
+        }
+    }
+
+    fn run_scripted(harness: &Harness, source: &mut ScriptedSource) -> Vec<ListenerStatus> {
+        let state = harness.state.clone();
+        let hook = harness.hook.clone();
+        let statuses = RefCell::new(Vec::new());
+        run_input_source(
+            source,
+            Rc::new(move |event: &Event, suppression_enabled: bool| {
+                process_event(
+                    &state,
+                    &mut hook.borrow_mut(),
+                    event,
+                    ForegroundContext::Roblox,
+                    suppression_enabled,
+                )
+                .suppress
+            }),
+            &|status| statuses.borrow_mut().push(status),
+        );
+        statuses.into_inner()
+    }
+
+    #[test]
+    fn grabbing_source_applies_suppression() {
+        let harness = Harness::new();
+        let mut source = ScriptedSource {
+            grab_error: None,
+            events: vec![
+                EventType::KeyPress(Key::Slash),
+                EventType::KeyPress(Key::KeyW),
+            ],
+            suppressed: Vec::new(),
+        };
+        assert_eq!(
+            run_scripted(&harness, &mut source),
+            vec![ListenerStatus::Grab]
+        );
+        assert_eq!(source.suppressed, vec![true, false]);
+    }
+
+    #[test]
+    fn passive_fallback_reports_status_and_still_tracks_keys() {
+        let harness = Harness::new();
+        let mut source = ScriptedSource {
+            grab_error: Some("KeyHookError(5)".to_string()),
+            events: vec![EventType::KeyPress(Key::KeyW)],
+            suppressed: Vec::new(),
+        };
+        assert_eq!(
+            run_scripted(&harness, &mut source),
+            vec![
+                ListenerStatus::Grab,
+                ListenerStatus::Passive {
+                    error: "KeyHookError(5)".to_string()
+                }
+            ]
+        );
+        assert_eq!(harness.physical_down(), [Key::KeyW].into());
+    }
+
+    #[test]
+    fn stale_injections_expire() {
+        let ledger = InjectionLedger::default();
+        let input = InjectedInput::Key {
+            key: Key::KeyA,
+            up: true,
+        };
+        let now = Instant::now();
+        ledger.record(input, 2, now);
+        assert!(!ledger.consume(
+            InjectedInput::Key {
+                key: Key::KeyA,
+                up: false
             },
+            now
+        ));
+        assert!(ledger.consume(input, now));
+        assert!(!ledger.consume(input, now + INJECTION_ECHO_WINDOW));
+    }
+
+    #[test]
+    fn a_real_event_inside_the_echo_window_is_taken_for_the_echo() {
+        // The limit of matching by key and direction: the real press arrives first and is consumed
+        // as the echo, so the echo that follows is the one that counts as real.
+        let ledger = InjectionLedger::default();
+        let input = InjectedInput::Key {
+            key: Key::KeyW,
+            up: false,
         };
+        let now = Instant::now();
+        ledger.record(input, 1, now);
+        assert!(ledger.consume(input, now + Duration::from_millis(5)));
+        assert!(!ledger.consume(input, now + Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn missed_release_is_cleared_once_the_os_disagrees_twice() {
+        let harness = Harness::new();
+        harness.press(Key::KeyW);
+        harness.press(Key::ShiftLeft);
+        harness
+            .injector
+            .released_by_os
+            .lock()
+            .unwrap()
+            .insert(Key::KeyW);
+
+        assert_eq!(reconcile_key_state(&harness.state).unwrap(), None);
+        let correction = reconcile_key_state(&harness.state).unwrap().unwrap();
+        assert_eq!(correction.cleared_keys, vec!["KeyW".to_string()]);
+        assert!(correction.repressed_latches.is_empty());
+        assert_eq!(harness.physical_down(), [Key::ShiftLeft].into());
+        assert_eq!(reconcile_key_state(&harness.state).unwrap(), None);
+    }
+
+    #[test]
+    fn keys_typed_into_chat_survive_reconciliation() {
+        let harness = Harness::new();
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        assert!(harness.press(Key::ControlLeft).suppress);
+        harness
+            .injector
+            .released_by_os
+            .lock()
+            .unwrap()
+            .insert(Key::ControlLeft);
+
+        assert_eq!(reconcile_key_state(&harness.state).unwrap(), None);
+        assert_eq!(reconcile_key_state(&harness.state).unwrap(), None);
+        assert_eq!(harness.physical_down(), [Key::ControlLeft].into());
+    }
+
+    #[test]
+    fn latch_dropped_by_the_os_is_pressed_again() {
+        let harness = Harness::new();
+        harness.press(Key::KeyW);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        harness.deliver_echoes();
+        harness
+            .injector
+            .released_by_os
+            .lock()
+            .unwrap()
+            .insert(Key::KeyW);
+
+        assert_eq!(reconcile_key_state(&harness.state).unwrap(), None);
+        let correction = reconcile_key_state(&harness.state).unwrap().unwrap();
+        assert_eq!(correction.repressed_latches, vec!["KeyW".to_string()]);
+        assert!(correction.cleared_keys.is_empty());
+        assert_eq!(harness.sent_count(key_input(Key::KeyW, false)), 2);
+        assert!(harness.physical_down().contains(&Key::KeyW));
+    }
 
-        unsafe {
-            let _ = SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+    #[test]
+    fn idle_capture_expires_and_hands_back_latches() {
+        let harness = Harness::new();
+        harness.press(Key::KeyW);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        harness.deliver_echoes();
+        assert!(expire_idle_capture(&harness.state, Instant::now())
+            .unwrap()
+            .is_none());
+
+        harness.press(Key::KeyH);
+        let typed_at = Instant::now();
+        assert!(
+            expire_idle_capture(&harness.state, typed_at + Duration::from_secs(29))
+                .unwrap()
+                .is_none()
+        );
+        let stopped =
+            expire_idle_capture(&harness.state, typed_at + Duration::from_secs(31)).unwrap();
+        assert_eq!(
+            stopped.map(|stopped| stopped.reason),
+            Some(CaptureStopReason::IdleTimeout)
+        );
+        assert!(!harness.is_active());
+        harness.wait_for_sent(key_input(Key::KeyW, true), 1);
+    }
+
+    #[test]
+    fn losing_focus_stops_capture_and_releases_latches() {
+        let harness = Harness::new();
+        harness.press(Key::KeyW);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        harness.deliver_echoes();
+
+        for context in [ForegroundContext::Roblox, ForegroundContext::App] {
+            assert!(stop_capture_if_focus_lost(&harness.state, context)
+                .unwrap()
+                .is_none());
+            assert!(harness.is_active());
         }
+
+        let stopped = stop_capture_if_focus_lost(&harness.state, ForegroundContext::Other).unwrap();
+        assert_eq!(
+            stopped.map(|stopped| stopped.reason),
+            Some(CaptureStopReason::FocusLost)
+        );
+        assert!(!harness.is_active());
+        harness.wait_for_sent(key_input(Key::KeyW, true), 1);
+
+        assert!(
+            stop_capture_if_focus_lost(&harness.state, ForegroundContext::Secure)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn forwarded_text_comes_from_the_keyboard_layout() {
+        let harness = Harness::new();
+        harness.state.lock().unwrap().layout = Some(Box::new(TableLayout(vec![
+            (Key::KeyQ, KeyText::Typed("a".to_string())),
+            (Key::KeyE, KeyText::Typed("€".to_string())),
+            (Key::LeftBracket, KeyText::Dead),
+        ])));
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::None,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+
+        let typed = |key| {
+            let event = harness.press(key).key_event.unwrap();
+            harness.release(key);
+            (event.text, event.dead_key)
+        };
+        assert_eq!(typed(Key::KeyQ), (Some("a".to_string()), false));
+        assert_eq!(typed(Key::LeftBracket), (None, true));
+        // Keys the layout does not know keep rdev's text, which these synthetic events lack.
+        assert_eq!(typed(Key::KeyZ), (None, false));
+
+        // Ctrl+Alt is how Windows reports AltGr, so it still types; Ctrl alone is a shortcut.
+        harness.press(Key::ControlLeft);
+        assert_eq!(typed(Key::KeyQ), (None, false));
+        harness.press(Key::Alt);
+        assert_eq!(typed(Key::KeyE), (Some("€".to_string()), false));
+        harness.release(Key::ControlLeft);
+        harness.release(Key::Alt);
+
+        harness.press(Key::MetaLeft);
+        assert_eq!(typed(Key::KeyQ), (None, false));
     }
 
-    let mut flags = KEYBD_EVENT_FLAGS(0);
-    if key_up {
-        flags |= KEYEVENTF_KEYUP;
+    #[test]
+    fn capture_state_rejects_illegal_transitions() {
+        let capturing = CaptureState::Capturing {
+            mode: ChatInputMode::Focusless,
+        };
+        let stopping = CaptureState::Stopping {
+            reason: CaptureStopReason::Cancel,
+        };
+
+        assert!(!CaptureState::Idle.can_transition_to(capturing));
+        assert!(!CaptureState::Idle.can_transition_to(stopping));
+        assert!(!CaptureState::Starting.can_transition_to(CaptureState::Starting));
+        assert!(!capturing.can_transition_to(CaptureState::Starting));
+        assert!(!capturing.can_transition_to(CaptureState::Idle));
+        assert!(!stopping.can_transition_to(capturing));
+
+        let mut inner = InputCaptureInner::default();
+        assert!(inner.transition(capturing).is_err());
+        assert_eq!(inner.capture, CaptureState::Idle);
+        assert!(inner.capture_changes.is_empty());
     }
 
-    let fallback = INPUT {
-        r#type: INPUT_KEYBOARD,
-        Anonymous: INPUT_0 {
-            ki: KEYBDINPUT {
-                wVk: vk,
-                wScan: 0,
-                dwFlags: flags,
-                time: 0,
-                dwExtraInfo: 0,
+    #[test]
+    fn a_stop_while_latches_go_out_cancels_the_start() {
+        let harness = Harness::new();
+        harness.press(Key::KeyW);
+        let state = harness.state.clone();
+        *harness.injector.before_next_send.lock().unwrap() = Some(Box::new(move || {
+            end_chat_capture(&state, CaptureStopReason::Cancel).unwrap();
+        }));
+
+        let started = begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Wasd,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        assert!(!started);
+        {
+            let inner = harness.state.lock().unwrap();
+            assert_eq!(inner.capture, CaptureState::Idle);
+            assert!(inner.latched_keys.is_empty());
+        }
+        // The stop owns the latch it raced, so its release still goes out.
+        harness.wait_for_sent(
+            InjectedInput::Key {
+                key: Key::KeyW,
+                up: true,
             },
-        },
-    };
+            1,
+        );
+
+        assert!(begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Wasd,
+            ChatInputMode::Focusless,
+        )
+        .unwrap());
+        assert!(harness.is_active());
+    }
+
+    #[test]
+    fn capture_lifecycle_records_each_transition() {
+        let harness = Harness::new();
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Ime,
+        )
+        .unwrap();
+        assert!(begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Ime,
+        )
+        .is_err());
+        end_chat_capture(&harness.state, CaptureStopReason::Submit).unwrap();
+
+        let inner = harness.state.lock().unwrap();
+        assert_eq!(inner.capture, CaptureState::Idle);
+        assert_eq!(
+            inner.capture_changes,
+            vec![
+                CaptureState::Starting,
+                CaptureState::Capturing {
+                    mode: ChatInputMode::Ime
+                },
+                CaptureState::Stopping {
+                    reason: CaptureStopReason::Submit
+                },
+                CaptureState::Idle,
+            ]
+        );
+    }
+
+    #[test]
+    fn switching_input_mode_keeps_capture_and_latches() {
+        let harness = Harness::new();
+        harness.press(Key::KeyW);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Wasd,
+            ChatInputMode::Ime,
+        )
+        .unwrap();
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Wasd,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+
+        let inner = harness.state.lock().unwrap();
+        assert_eq!(
+            inner.capture,
+            CaptureState::Capturing {
+                mode: ChatInputMode::Focusless
+            }
+        );
+        assert!(inner.latched_keys.contains(&Key::KeyW));
+        drop(inner);
+        assert_eq!(
+            harness.injector.sent(),
+            vec![InjectedInput::Key {
+                key: Key::KeyW,
+                up: false
+            }]
+        );
+    }
+
+    #[test]
+    fn gamepad_opens_chat_over_roblox_and_navigates_while_capturing() {
+        use crate::gamepad::tests::ScriptedGamepad;
+        use GamepadButton::{DpadUp, LeftThumb, RightThumb};
+
+        let harness = Harness::new();
+        let mut source = ScriptedGamepad::new(&[&[LeftThumb, RightThumb], &[]]);
+        let poll = |source: &mut ScriptedGamepad, context| {
+            poll_gamepad(&harness.state, source, || context).unwrap()
+        };
+        assert!(poll(&mut source, ForegroundContext::Other).is_empty());
+        assert!(poll(&mut source, ForegroundContext::Roblox).is_empty());
+
+        let mut source = ScriptedGamepad::new(&[&[LeftThumb, RightThumb]]);
+        assert_eq!(
+            poll(&mut source, ForegroundContext::Roblox),
+            vec![GamepadAction::OpenChat]
+        );
+
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::None,
+            ChatInputMode::Ime,
+        )
+        .unwrap();
+        let mut source = ScriptedGamepad::new(&[&[], &[LeftThumb, RightThumb, DpadUp]]);
+        poll(&mut source, ForegroundContext::App);
+        assert_eq!(
+            poll(&mut source, ForegroundContext::App),
+            vec![GamepadAction::PreviousMessage]
+        );
+    }
+
+    #[test]
+    fn chord_suppression_reports_why_a_chord_passes() {
+        let harness = Harness::new();
+        harness.state.lock().unwrap().passthrough_chords =
+            parse_passthrough_chords(&["Ctrl+Shift+KeyM".to_string()]).unwrap();
+
+        let check = |chord: &str| test_chord_suppression(&harness.state, chord).unwrap();
+        assert_eq!(
+            check("Alt+Tab").allowed_by,
+            Some(PassthroughSource::BuiltIn)
+        );
+        assert_eq!(
+            check("Meta+KeyD").allowed_by,
+            Some(PassthroughSource::BuiltIn)
+        );
+        assert_eq!(
+            check("shift+ctrl+KeyM"),
+            ChordSuppression {
+                chord: "Ctrl+Shift+KeyM".to_string(),
+                suppressed: false,
+                allowed_by: Some(PassthroughSource::Allowlist),
+            }
+        );
+        assert!(check("Ctrl+KeyM").suppressed);
+        assert!(test_chord_suppression(&harness.state, "Ctrl+Nope").is_err());
+
+        assert!(parse_passthrough_chords(&["Escape".to_string()]).is_err());
+        assert_eq!(
+            parse_passthrough_chords(&[" F9 ".to_string(), String::new(), "F9".to_string()])
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn capture_state_serializes_for_the_frontend() {
+        assert_eq!(
+            serde_json::to_value(CaptureState::Capturing {
+                mode: ChatInputMode::Focusless
+            })
+            .unwrap(),
+            serde_json::json!({ "state": "capturing", "mode": "focusless" })
+        );
+        assert_eq!(
+            serde_json::to_value(CaptureStopReason::FocusLost).unwrap(),
+            serde_json::json!("focus-lost")
+        );
+        assert_eq!(
+            serde_json::from_value::<CaptureStopReason>(serde_json::json!("submit")).unwrap(),
+            CaptureStopReason::Submit
+        );
+    }
+
+    #[test]
+    fn zero_idle_timeout_disables_the_watchdog() {
+        assert_eq!(parse_capture_idle_timeout(0).unwrap(), None);
+        assert_eq!(
+            parse_capture_idle_timeout(45).unwrap(),
+            Some(Duration::from_secs(45))
+        );
+        assert!(parse_capture_idle_timeout(MAX_CAPTURE_IDLE_TIMEOUT_SECS + 1).is_err());
+    }
+
+    #[test]
+    fn focusless_capture_edits_the_compose_buffer() {
+        let harness = Harness::new();
+        harness.state.lock().unwrap().layout = Some(Box::new(TableLayout(vec![
+            (Key::KeyH, KeyText::Typed("h".to_string())),
+            (Key::KeyI, KeyText::Typed("i".to_string())),
+        ])));
+        set_compose_state(&harness.state, "oh ", None).unwrap();
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::None,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+
+        harness.press(Key::KeyH);
+        harness.release(Key::KeyH);
+        let outcome = harness.press(Key::KeyI);
+        assert_eq!(
+            outcome.compose,
+            Some(ComposeState {
+                text: "oh hi".to_string(),
+                cursor: 5,
+                selection: None,
+            })
+        );
+
+        harness.press(Key::ControlLeft);
+        assert_eq!(
+            harness.press(Key::KeyV).clipboard,
+            Some(ClipboardRequest::Read)
+        );
+        harness.release(Key::KeyV);
+        harness.release(Key::ControlLeft);
+        assert_eq!(
+            paste_into_compose(&harness.state, "!")
+                .unwrap()
+                .unwrap()
+                .text,
+            "oh hi!"
+        );
+
+        // A submitted draft is gone, but one interrupted by focus loss waits for the next capture.
+        stop_capture_if_focus_lost(&harness.state, ForegroundContext::Other).unwrap();
+        assert_eq!(paste_into_compose(&harness.state, "?").unwrap(), None);
+        assert_eq!(harness.state.lock().unwrap().compose.state().text, "oh hi!");
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::None,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        end_chat_capture(&harness.state, CaptureStopReason::Submit).unwrap();
+        assert_eq!(harness.state.lock().unwrap().compose.state().text, "");
+    }
 
-    unsafe {
-        let _ = SendInput(&[fallback], std::mem::size_of::<INPUT>() as i32);
+    #[test]
+    fn ime_capture_leaves_the_compose_buffer_alone() {
+        let harness = Harness::new();
+        harness.state.lock().unwrap().layout = Some(Box::new(TableLayout(vec![(
+            Key::KeyH,
+            KeyText::Typed("h".to_string()),
+        )])));
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::None,
+            ChatInputMode::Ime,
+        )
+        .unwrap();
+
+        assert_eq!(harness.press(Key::KeyH).compose, None);
     }
 }
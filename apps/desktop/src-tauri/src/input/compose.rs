@@ -0,0 +1,676 @@
+use crate::hotkeys::Modifiers;
+use rdev::Key;
+use serde::Serialize;
+
+const COMPOSE_UNDO_LIMIT: usize = 100;
+
+// Ctrl+Alt is AltGr on Windows layouts and types text instead of running shortcuts.
+fn is_editing_shortcut(modifiers: Modifiers) -> bool {
+    modifiers.ctrl && !modifiers.alt && !modifiers.meta
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum ClipboardRequest {
+    Write(String),
+    Read,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct ComposeEffect {
+    pub(super) changed: bool,
+    pub(super) clipboard: Option<ClipboardRequest>,
+}
+
+impl ComposeEffect {
+    fn changed(changed: bool) -> Self {
+        Self {
+            changed,
+            clipboard: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ComposeSelection {
+    start: usize,
+    end: usize,
+}
+
+// Offsets count UTF-16 units, matching JavaScript string indices.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ComposeState {
+    pub(super) text: String,
+    pub(super) cursor: usize,
+    pub(super) selection: Option<ComposeSelection>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct ComposeSnapshot {
+    text: Vec<char>,
+    anchor: usize,
+    cursor: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CharClass {
+    Space,
+    Word,
+    Punctuation,
+}
+
+fn char_class(ch: char) -> CharClass {
+    if ch.is_whitespace() {
+        CharClass::Space
+    } else if ch.is_alphanumeric() || ch == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+// The focusless chat box: key presses edit it here so the webview only has to draw it.
+#[derive(Debug, Default)]
+pub(super) struct ComposeBuffer {
+    text: Vec<char>,
+    // The selection runs from anchor to cursor and is empty when they match.
+    anchor: usize,
+    cursor: usize,
+    undo: Vec<ComposeSnapshot>,
+    redo: Vec<ComposeSnapshot>,
+    // Consecutive typed characters undo as one step.
+    typing_run: bool,
+}
+
+impl ComposeBuffer {
+    pub(super) fn apply_key(
+        &mut self,
+        key: Key,
+        modifiers: Modifiers,
+        text: Option<&str>,
+    ) -> ComposeEffect {
+        if modifiers.meta {
+            return ComposeEffect::default();
+        }
+
+        let word = is_editing_shortcut(modifiers);
+        let extend = modifiers.shift;
+        match key {
+            Key::Backspace => return ComposeEffect::changed(self.delete_backward(word)),
+            Key::Delete => return ComposeEffect::changed(self.delete_forward(word)),
+            Key::LeftArrow => return ComposeEffect::changed(self.move_left(word, extend)),
+            Key::RightArrow => return ComposeEffect::changed(self.move_right(word, extend)),
+            Key::Home => return ComposeEffect::changed(self.move_to(0, extend)),
+            Key::End => return ComposeEffect::changed(self.move_to(self.text.len(), extend)),
+            _ => {}
+        }
+
+        if is_editing_shortcut(modifiers) {
+            return match key {
+                Key::KeyA => ComposeEffect::changed(self.select_all()),
+                Key::KeyC => ComposeEffect {
+                    changed: false,
+                    clipboard: self.selected_text().map(ClipboardRequest::Write),
+                },
+                Key::KeyX => {
+                    let copied = self.selected_text();
+                    ComposeEffect {
+                        changed: copied.is_some() && self.replace_selection("", false),
+                        clipboard: copied.map(ClipboardRequest::Write),
+                    }
+                }
+                Key::KeyV => ComposeEffect {
+                    changed: false,
+                    clipboard: Some(ClipboardRequest::Read),
+                },
+                Key::KeyZ if modifiers.shift => ComposeEffect::changed(self.redo()),
+                Key::KeyZ => ComposeEffect::changed(self.undo()),
+                Key::KeyY => ComposeEffect::changed(self.redo()),
+                _ => ComposeEffect::default(),
+            };
+        }
+
+        match text {
+            Some(text) => ComposeEffect::changed(self.replace_selection(text, true)),
+            None => ComposeEffect::default(),
+        }
+    }
+
+    pub(super) fn state(&self) -> ComposeState {
+        let (start, end) = self.selection();
+        ComposeState {
+            text: self.text.iter().collect(),
+            cursor: self.utf16_offset(self.cursor),
+            selection: (start != end).then(|| ComposeSelection {
+                start: self.utf16_offset(start),
+                end: self.utf16_offset(end),
+            }),
+        }
+    }
+
+    // Replaces the whole buffer, e.g. after the frontend inserted a mention; cursor is in UTF-16 units.
+    pub(super) fn set(&mut self, text: &str, cursor: Option<usize>) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        let cursor = match cursor {
+            Some(units) => char_index_at_utf16(&text, units),
+            None => text.len(),
+        };
+        if text == self.text && cursor == self.cursor && self.anchor == self.cursor {
+            return false;
+        }
+
+        self.checkpoint();
+        self.text = text;
+        self.anchor = cursor;
+        self.cursor = cursor;
+        true
+    }
+
+    pub(super) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub(super) fn paste(&mut self, text: &str) -> bool {
+        // Chat is a single line, so line breaks become spaces and other control characters go.
+        let cleaned: String = text
+            .replace("\r\n", " ")
+            .chars()
+            .filter_map(|ch| match ch {
+                '\n' | '\r' | '\t' => Some(' '),
+                ch if ch.is_control() => None,
+                ch => Some(ch),
+            })
+            .collect();
+        if cleaned.is_empty() {
+            return false;
+        }
+
+        self.replace_selection(&cleaned, false)
+    }
+
+    fn selection(&self) -> (usize, usize) {
+        (self.anchor.min(self.cursor), self.anchor.max(self.cursor))
+    }
+
+    fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection();
+        (start != end).then(|| self.text[start..end].iter().collect())
+    }
+
+    fn replace_selection(&mut self, insert: &str, typed: bool) -> bool {
+        let (start, end) = self.selection();
+        self.replace_range(start, end, insert, typed)
+    }
+
+    fn replace_range(&mut self, start: usize, end: usize, insert: &str, typed: bool) -> bool {
+        if insert.is_empty() && start == end {
+            return false;
+        }
+
+        // A space after a word closes the typing run, so undo works a word at a time.
+        let breaks_run = insert.starts_with(char::is_whitespace)
+            && start > 0
+            && !self.text[start - 1].is_whitespace();
+        if !typed || !self.typing_run || start != end || breaks_run {
+            self.checkpoint();
+        }
+
+        let inserted: Vec<char> = insert.chars().collect();
+        let caret = start + inserted.len();
+        self.text.splice(start..end, inserted);
+        self.anchor = caret;
+        self.cursor = caret;
+        self.typing_run = typed;
+        true
+    }
+
+    fn delete_backward(&mut self, word: bool) -> bool {
+        if self.anchor != self.cursor || self.cursor == 0 {
+            return self.replace_selection("", false);
+        }
+
+        let start = if word {
+            self.previous_word_boundary(self.cursor)
+        } else {
+            self.cursor - 1
+        };
+        self.replace_range(start, self.cursor, "", false)
+    }
+
+    fn delete_forward(&mut self, word: bool) -> bool {
+        if self.anchor != self.cursor || self.cursor == self.text.len() {
+            return self.replace_selection("", false);
+        }
+
+        let end = if word {
+            self.next_word_boundary(self.cursor)
+        } else {
+            self.cursor + 1
+        };
+        self.replace_range(self.cursor, end, "", false)
+    }
+
+    fn move_left(&mut self, word: bool, extend: bool) -> bool {
+        let (start, end) = self.selection();
+        let target = if word {
+            self.previous_word_boundary(self.cursor)
+        } else if start != end && !extend {
+            start
+        } else {
+            self.cursor.saturating_sub(1)
+        };
+        self.move_to(target, extend)
+    }
+
+    fn move_right(&mut self, word: bool, extend: bool) -> bool {
+        let (start, end) = self.selection();
+        let target = if word {
+            self.next_word_boundary(self.cursor)
+        } else if start != end && !extend {
+            end
+        } else {
+            (self.cursor + 1).min(self.text.len())
+        };
+        self.move_to(target, extend)
+    }
+
+    fn move_to(&mut self, target: usize, extend: bool) -> bool {
+        let anchor = if extend { self.anchor } else { target };
+        if (anchor, target) == (self.anchor, self.cursor) {
+            return false;
+        }
+
+        self.anchor = anchor;
+        self.cursor = target;
+        self.typing_run = false;
+        true
+    }
+
+    fn select_all(&mut self) -> bool {
+        if (self.anchor, self.cursor) == (0, self.text.len()) {
+            return false;
+        }
+
+        self.anchor = 0;
+        self.cursor = self.text.len();
+        self.typing_run = false;
+        true
+    }
+
+    // Skips any spaces, then the run of word or punctuation characters before them.
+    fn previous_word_boundary(&self, from: usize) -> usize {
+        let mut index = from;
+        while index > 0 && char_class(self.text[index - 1]) == CharClass::Space {
+            index -= 1;
+        }
+        if let Some(&ch) = index.checked_sub(1).and_then(|prev| self.text.get(prev)) {
+            let class = char_class(ch);
+            while index > 0 && char_class(self.text[index - 1]) == class {
+                index -= 1;
+            }
+        }
+        index
+    }
+
+    // Lands on the start of the next word, as Ctrl+Right does on Windows.
+    fn next_word_boundary(&self, from: usize) -> usize {
+        let mut index = from;
+        if let Some(&ch) = self.text.get(index) {
+            let class = char_class(ch);
+            if class != CharClass::Space {
+                while index < self.text.len() && char_class(self.text[index]) == class {
+                    index += 1;
+                }
+            }
+        }
+        while index < self.text.len() && char_class(self.text[index]) == CharClass::Space {
+            index += 1;
+        }
+        index
+    }
+
+    fn checkpoint(&mut self) {
+        self.undo.push(self.snapshot());
+        if self.undo.len() > COMPOSE_UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    fn undo(&mut self) -> bool {
+        let Some(previous) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(self.snapshot());
+        self.restore(previous);
+        true
+    }
+
+    fn redo(&mut self) -> bool {
+        let Some(next) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(self.snapshot());
+        self.restore(next);
+        true
+    }
+
+    fn snapshot(&self) -> ComposeSnapshot {
+        ComposeSnapshot {
+            text: self.text.clone(),
+            anchor: self.anchor,
+            cursor: self.cursor,
+        }
+    }
+
+    fn restore(&mut self, snapshot: ComposeSnapshot) {
+        self.text = snapshot.text;
+        self.anchor = snapshot.anchor;
+        self.cursor = snapshot.cursor;
+        self.typing_run = false;
+    }
+
+    fn utf16_offset(&self, index: usize) -> usize {
+        self.text[..index].iter().map(|ch| ch.len_utf16()).sum()
+    }
+}
+
+fn char_index_at_utf16(text: &[char], units: usize) -> usize {
+    let mut seen = 0;
+    for (index, ch) in text.iter().enumerate() {
+        if seen >= units {
+            return index;
+        }
+        seen += ch.len_utf16();
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: Modifiers = Modifiers {
+        ctrl: false,
+        shift: false,
+        alt: false,
+        meta: false,
+    };
+
+    const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ..PLAIN
+    };
+
+    const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        ..PLAIN
+    };
+
+    const CTRL_SHIFT: Modifiers = Modifiers {
+        ctrl: true,
+        shift: true,
+        ..PLAIN
+    };
+
+    fn compose(text: &str) -> ComposeBuffer {
+        let mut buffer = ComposeBuffer::default();
+        buffer.set(text, None);
+        buffer
+    }
+
+    fn type_text(buffer: &mut ComposeBuffer, text: &str) {
+        for ch in text.chars() {
+            buffer.apply_key(Key::KeyQ, PLAIN, Some(&ch.to_string()));
+        }
+    }
+
+    fn tap(buffer: &mut ComposeBuffer, key: Key, modifiers: Modifiers) -> ComposeEffect {
+        buffer.apply_key(key, modifiers, None)
+    }
+
+    fn text_of(buffer: &ComposeBuffer) -> String {
+        buffer.text.iter().collect()
+    }
+
+    #[test]
+    fn typing_inserts_at_the_cursor() {
+        let mut buffer = compose("held");
+        tap(&mut buffer, Key::LeftArrow, PLAIN);
+        tap(&mut buffer, Key::LeftArrow, PLAIN);
+        type_text(&mut buffer, "l");
+
+        assert_eq!(text_of(&buffer), "helld");
+        assert_eq!((buffer.anchor, buffer.cursor), (3, 3));
+    }
+
+    #[test]
+    fn backspace_and_delete_remove_one_character() {
+        let mut buffer = compose("abc");
+        tap(&mut buffer, Key::Home, PLAIN);
+        assert!(!tap(&mut buffer, Key::Backspace, PLAIN).changed);
+        assert!(tap(&mut buffer, Key::Delete, PLAIN).changed);
+        assert_eq!(text_of(&buffer), "bc");
+
+        tap(&mut buffer, Key::End, PLAIN);
+        assert!(!tap(&mut buffer, Key::Delete, PLAIN).changed);
+        assert!(tap(&mut buffer, Key::Backspace, PLAIN).changed);
+        assert_eq!(text_of(&buffer), "b");
+    }
+
+    #[test]
+    fn ctrl_backspace_deletes_the_previous_word() {
+        let cases = [
+            ("hello world", "hello "),
+            ("hello world   ", "hello "),
+            ("wait...", "wait"),
+            ("go_team", ""),
+            ("  ", ""),
+        ];
+        for (before, after) in cases {
+            let mut buffer = compose(before);
+            tap(&mut buffer, Key::Backspace, CTRL);
+            assert_eq!(text_of(&buffer), after, "from {before:?}");
+        }
+    }
+
+    #[test]
+    fn ctrl_delete_deletes_up_to_the_next_word() {
+        let mut buffer = compose("hello big world");
+        tap(&mut buffer, Key::Home, PLAIN);
+        tap(&mut buffer, Key::Delete, CTRL);
+        assert_eq!(text_of(&buffer), "big world");
+
+        tap(&mut buffer, Key::End, PLAIN);
+        assert!(!tap(&mut buffer, Key::Delete, CTRL).changed);
+    }
+
+    #[test]
+    fn ctrl_arrows_jump_between_words() {
+        let mut buffer = compose("one two, three");
+        tap(&mut buffer, Key::LeftArrow, CTRL);
+        assert_eq!(buffer.cursor, 9);
+        tap(&mut buffer, Key::LeftArrow, CTRL);
+        assert_eq!(buffer.cursor, 7);
+        tap(&mut buffer, Key::LeftArrow, CTRL);
+        assert_eq!(buffer.cursor, 4);
+
+        tap(&mut buffer, Key::RightArrow, CTRL);
+        assert_eq!(buffer.cursor, 7);
+        tap(&mut buffer, Key::RightArrow, CTRL);
+        assert_eq!(buffer.cursor, 9);
+        tap(&mut buffer, Key::RightArrow, CTRL);
+        assert_eq!(buffer.cursor, 14);
+    }
+
+    #[test]
+    fn shift_arrows_extend_the_selection_and_typing_replaces_it() {
+        let mut buffer = compose("hello world");
+        tap(&mut buffer, Key::LeftArrow, SHIFT);
+        tap(&mut buffer, Key::LeftArrow, CTRL_SHIFT);
+        assert_eq!(buffer.selected_text().as_deref(), Some("world"));
+
+        type_text(&mut buffer, "there");
+        assert_eq!(text_of(&buffer), "hello there");
+        assert_eq!(buffer.selected_text(), None);
+    }
+
+    #[test]
+    fn plain_arrows_collapse_the_selection_to_its_edge() {
+        let mut buffer = compose("abcdef");
+        tap(&mut buffer, Key::LeftArrow, SHIFT);
+        tap(&mut buffer, Key::LeftArrow, SHIFT);
+        tap(&mut buffer, Key::LeftArrow, PLAIN);
+        assert_eq!((buffer.anchor, buffer.cursor), (4, 4));
+
+        tap(&mut buffer, Key::Home, SHIFT);
+        tap(&mut buffer, Key::RightArrow, PLAIN);
+        assert_eq!((buffer.anchor, buffer.cursor), (4, 4));
+    }
+
+    #[test]
+    fn home_and_end_move_or_select_to_the_line_edges() {
+        let mut buffer = compose("abc");
+        tap(&mut buffer, Key::LeftArrow, PLAIN);
+        tap(&mut buffer, Key::Home, SHIFT);
+        assert_eq!(buffer.selected_text().as_deref(), Some("ab"));
+
+        tap(&mut buffer, Key::End, PLAIN);
+        assert_eq!((buffer.anchor, buffer.cursor), (3, 3));
+        assert!(!tap(&mut buffer, Key::End, PLAIN).changed);
+    }
+
+    #[test]
+    fn ctrl_a_selects_everything() {
+        let mut buffer = compose("select me");
+        assert!(tap(&mut buffer, Key::KeyA, CTRL).changed);
+        assert_eq!(buffer.selected_text().as_deref(), Some("select me"));
+        assert!(!tap(&mut buffer, Key::KeyA, CTRL).changed);
+
+        tap(&mut buffer, Key::Backspace, PLAIN);
+        assert_eq!(text_of(&buffer), "");
+    }
+
+    #[test]
+    fn copy_and_cut_ask_for_a_clipboard_write() {
+        let mut buffer = compose("copy this");
+        assert_eq!(tap(&mut buffer, Key::KeyC, CTRL), ComposeEffect::default());
+
+        tap(&mut buffer, Key::LeftArrow, CTRL_SHIFT);
+        assert_eq!(
+            tap(&mut buffer, Key::KeyC, CTRL),
+            ComposeEffect {
+                changed: false,
+                clipboard: Some(ClipboardRequest::Write("this".to_string())),
+            }
+        );
+        assert_eq!(
+            tap(&mut buffer, Key::KeyX, CTRL),
+            ComposeEffect {
+                changed: true,
+                clipboard: Some(ClipboardRequest::Write("this".to_string())),
+            }
+        );
+        assert_eq!(text_of(&buffer), "copy ");
+    }
+
+    #[test]
+    fn paste_replaces_the_selection_and_flattens_line_breaks() {
+        let mut buffer = compose("say hi");
+        assert_eq!(
+            tap(&mut buffer, Key::KeyV, CTRL).clipboard,
+            Some(ClipboardRequest::Read)
+        );
+
+        tap(&mut buffer, Key::LeftArrow, CTRL_SHIFT);
+        assert!(buffer.paste("hello\r\nthere\tfriend\u{7}"));
+        assert_eq!(text_of(&buffer), "say hello there friend");
+        assert_eq!(buffer.cursor, buffer.text.len());
+        assert!(!buffer.paste("\u{7}"));
+    }
+
+    #[test]
+    fn undo_steps_back_a_word_at_a_time_and_redo_restores() {
+        let mut buffer = ComposeBuffer::default();
+        type_text(&mut buffer, "good game");
+        assert!(tap(&mut buffer, Key::KeyZ, CTRL).changed);
+        assert_eq!(text_of(&buffer), "good");
+        tap(&mut buffer, Key::KeyZ, CTRL);
+        assert_eq!(text_of(&buffer), "");
+        assert!(!tap(&mut buffer, Key::KeyZ, CTRL).changed);
+
+        tap(&mut buffer, Key::KeyY, CTRL);
+        assert_eq!(text_of(&buffer), "good");
+        tap(&mut buffer, Key::KeyZ, CTRL_SHIFT);
+        assert_eq!(text_of(&buffer), "good game");
+        assert_eq!(buffer.cursor, 9);
+    }
+
+    #[test]
+    fn editing_after_undo_drops_the_redo_history() {
+        let mut buffer = ComposeBuffer::default();
+        type_text(&mut buffer, "gg");
+        tap(&mut buffer, Key::Backspace, CTRL);
+        tap(&mut buffer, Key::KeyZ, CTRL);
+        assert_eq!(text_of(&buffer), "gg");
+
+        type_text(&mut buffer, "!");
+        assert!(!tap(&mut buffer, Key::KeyY, CTRL).changed);
+        assert_eq!(text_of(&buffer), "gg!");
+    }
+
+    #[test]
+    fn undo_history_is_bounded() {
+        let mut buffer = ComposeBuffer::default();
+        for _ in 0..COMPOSE_UNDO_LIMIT + 20 {
+            type_text(&mut buffer, "x");
+            tap(&mut buffer, Key::Home, PLAIN);
+        }
+        assert_eq!(buffer.undo.len(), COMPOSE_UNDO_LIMIT);
+    }
+
+    #[test]
+    fn altgr_text_is_typed_rather_than_read_as_a_shortcut() {
+        let mut buffer = ComposeBuffer::default();
+        let altgr = Modifiers {
+            ctrl: true,
+            alt: true,
+            ..PLAIN
+        };
+        assert!(buffer.apply_key(Key::KeyQ, altgr, Some("@")).changed);
+        assert!(
+            !buffer
+                .apply_key(
+                    Key::KeyV,
+                    Modifiers {
+                        meta: true,
+                        ..PLAIN
+                    },
+                    Some("v")
+                )
+                .changed
+        );
+        assert_eq!(text_of(&buffer), "@");
+    }
+
+    #[test]
+    fn compose_state_counts_utf16_units() {
+        let mut buffer = compose("a😀b");
+        tap(&mut buffer, Key::LeftArrow, SHIFT);
+        tap(&mut buffer, Key::LeftArrow, SHIFT);
+
+        assert_eq!(
+            buffer.state(),
+            ComposeState {
+                text: "a😀b".to_string(),
+                cursor: 1,
+                selection: Some(ComposeSelection { start: 1, end: 4 }),
+            }
+        );
+
+        assert!(buffer.set("a😀b", Some(3)));
+        assert_eq!(buffer.cursor, 2);
+        assert_eq!(buffer.state().selection, None);
+    }
+}
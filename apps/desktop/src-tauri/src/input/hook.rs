@@ -0,0 +1,997 @@
+use super::compose::{ClipboardRequest, ComposeState};
+use super::trace::{record_trace, trace_entry, InputTraceEntry};
+use super::{
+    build_global_key_event, derive_key_text, emit_capture_changes, event_timestamp_ms,
+    matches_passthrough_chord, platform, run_clipboard_request, schedule_latched_button_release,
+    schedule_latched_key_release, should_allow_system_shortcut_during_capture,
+    should_forward_key_event, CaptureState, CaptureStopReason, CaptureStoppedEvent, ChatInputMode,
+    ChatScrollEvent, ChatScrollModifier, ForegroundContext, GlobalKeyEvent, InjectedInput,
+    InputCaptureInner, InputCaptureState, KeyPhase, MODIFIER_KEYS,
+};
+use crate::hotkeys::{Chord, HotkeyAction, HotkeyActionEvent, HotkeyRegistry, Modifiers};
+use crate::quick_chat::QuickChatTable;
+use anyhow::Result;
+use rdev::{Button, Event, EventType, Key};
+use std::collections::HashSet;
+use std::mem;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+// Everything the hook decides from, copied out of the state whenever one of these changes.
+#[derive(Clone, Default, PartialEq)]
+pub(super) struct HookSnapshot {
+    capture: CaptureState,
+    capture_generation: u64,
+    latched_keys: HashSet<Key>,
+    latched_buttons: HashSet<Button>,
+    capture_started_down: HashSet<Key>,
+    hold_key: Option<Key>,
+    passthrough_chords: Vec<Chord>,
+    hotkeys: HotkeyRegistry,
+    quick_chat: QuickChatTable,
+    chat_scroll_modifier: ChatScrollModifier,
+}
+
+impl HookSnapshot {
+    pub(super) fn from_inner(inner: &InputCaptureInner) -> Self {
+        Self {
+            capture: inner.capture,
+            capture_generation: inner.capture_generation,
+            latched_keys: inner.latched_keys.clone(),
+            latched_buttons: inner.latched_buttons.clone(),
+            capture_started_down: inner.capture_started_down.clone(),
+            hold_key: inner.hold_key,
+            passthrough_chords: inner.passthrough_chords.clone(),
+            hotkeys: inner.hotkeys.clone(),
+            quick_chat: inner.quick_chat.clone(),
+            chat_scroll_modifier: inner.chat_scroll_modifier,
+        }
+    }
+
+    pub(super) fn matches(&self, inner: &InputCaptureInner) -> bool {
+        self.capture == inner.capture
+            && self.capture_generation == inner.capture_generation
+            && self.latched_keys == inner.latched_keys
+            && self.latched_buttons == inner.latched_buttons
+            && self.capture_started_down == inner.capture_started_down
+            && self.hold_key == inner.hold_key
+            && self.passthrough_chords == inner.passthrough_chords
+            && self.hotkeys == inner.hotkeys
+            && self.quick_chat == inner.quick_chat
+            && self.chat_scroll_modifier == inner.chat_scroll_modifier
+    }
+}
+
+pub(super) enum HookUpdate {
+    Snapshot(Arc<HookSnapshot>),
+    Forget(Vec<Key>),
+}
+
+#[derive(Default)]
+pub(super) struct EventOutcome {
+    pub(super) suppress: bool,
+    pub(super) key_event: Option<GlobalKeyEvent>,
+    pub(super) hotkey: Option<(HotkeyAction, Chord)>,
+    pub(super) quick_chat: Option<(Chord, String)>,
+    pub(super) keys_to_release: Vec<Key>,
+    pub(super) buttons_to_release: Vec<Button>,
+    pub(super) reinject_key: Option<Key>,
+    pub(super) reinject_button: Option<Button>,
+    pub(super) passthrough_replay: Vec<Key>,
+    pub(super) capture_changes: Vec<CaptureState>,
+    // Set when the event itself ended capture and the frontend has to finish the message.
+    pub(super) stopped: Option<CaptureStoppedEvent>,
+    pub(super) compose: Option<ComposeState>,
+    pub(super) clipboard: Option<ClipboardRequest>,
+    pub(super) trace: Option<InputTraceEntry>,
+}
+
+// Runs inside the OS hook, so it only decides; everything else happens on the worker.
+pub(super) fn handle_event(
+    state: &InputCaptureState,
+    hook: &mut HookDecider,
+    worker: &Sender<HookMessage>,
+    event: &Event,
+    suppression_enabled: bool,
+) -> bool {
+    if let EventType::Wheel { delta_y, .. } = event.event_type {
+        if !hook.is_chat_scroll_modifier_held() {
+            return false;
+        }
+        let Some(scroll) = chat_scroll_event(hook, state.foreground.get(), delta_y) else {
+            return false;
+        };
+        let _ = worker.send(HookMessage::Scroll(scroll));
+        return suppression_enabled;
+    }
+
+    // Mouse moves never change capture state.
+    if InjectedInput::from_event_type(&event.event_type).is_none() {
+        return false;
+    }
+
+    let started = Instant::now();
+    let context = state.foreground.get();
+    let decision = decide_event(state, hook, event, context, suppression_enabled);
+    let suppress = decision.suppress;
+    let _ = worker.send(HookMessage::Input(HookInput {
+        event: event.clone(),
+        context,
+        decision,
+        hook_latency: started.elapsed(),
+    }));
+    suppress
+}
+
+pub(super) enum HookMessage {
+    Input(HookInput),
+    Scroll(ChatScrollEvent),
+}
+
+pub(super) struct HookInput {
+    event: Event,
+    context: ForegroundContext,
+    decision: HookDecision,
+    hook_latency: Duration,
+}
+
+// Applies the hook's decisions to the shared state in order and does the slow work: locking, emits, injection.
+pub(super) fn run_hook_worker(
+    app: &AppHandle,
+    state: &InputCaptureState,
+    messages: Receiver<HookMessage>,
+) {
+    for message in messages {
+        let input = match message {
+            HookMessage::Scroll(scroll) => {
+                let _ = app.emit("chat-scroll", scroll);
+                continue;
+            }
+            HookMessage::Input(input) => input,
+        };
+
+        let mut outcome = apply_decision(state, &input.event, input.context, input.decision);
+        if let Some((action, chord)) = outcome.hotkey {
+            let _ = app.emit(
+                "hotkey-action",
+                HotkeyActionEvent {
+                    action,
+                    chord: chord.to_string(),
+                    timestamp_ms: event_timestamp_ms(),
+                },
+            );
+        }
+        if let Some((chord, template)) = outcome.quick_chat {
+            crate::quick_chat::send(app.clone(), chord, template);
+        }
+
+        if let Some(payload) = outcome.key_event {
+            let _ = app.emit("global-key", payload);
+        }
+        if let Some(compose) = outcome.compose {
+            let _ = app.emit("compose-state", compose);
+        }
+        if let Some(request) = outcome.clipboard {
+            run_clipboard_request(app.clone(), state.clone(), request);
+        }
+        emit_capture_changes(app, outcome.capture_changes);
+        if let Some(stopped) = outcome.stopped {
+            platform::detach_ime(app);
+            let _ = app.emit("capture-stopped", stopped);
+        }
+
+        record_trace(state, outcome.trace.take(), input.hook_latency);
+    }
+}
+
+// Some when the wheel should scroll chat instead of reaching the game.
+fn chat_scroll_event(
+    hook: &mut HookDecider,
+    context: ForegroundContext,
+    delta: i64,
+) -> Option<ChatScrollEvent> {
+    let scrolls_chat =
+        delta != 0 && context == ForegroundContext::Roblox && hook.is_chat_scroll_modifier_held();
+    scrolls_chat.then(|| ChatScrollEvent {
+        delta,
+        timestamp_ms: event_timestamp_ms(),
+    })
+}
+
+// The hook's verdict on one event, plus what the worker needs to replay its reasoning.
+#[derive(Default)]
+pub(super) struct HookDecision {
+    suppress: bool,
+    echo: bool,
+    can_suppress: bool,
+    capture: CaptureState,
+    repeat: bool,
+    was_down_at_capture: bool,
+    passthrough: bool,
+    passthrough_replay: Vec<Key>,
+    stop: Option<CaptureStopReason>,
+    hotkey: Option<(HotkeyAction, Chord)>,
+    quick_chat: Option<(Chord, String)>,
+}
+
+// Owned by the hook thread. It never takes the state lock: it decides from the last published
+// snapshot plus the key state it tracks itself, since the worker's copy lags behind the hook.
+pub(super) struct HookDecider {
+    updates: Receiver<HookUpdate>,
+    snapshot: Arc<HookSnapshot>,
+    physical_down: HashSet<Key>,
+    withheld_keys: HashSet<Key>,
+    hotkey_keys: HashSet<Key>,
+    // Keys let through by a passthrough chord, so their releases must follow.
+    passthrough_keys: HashSet<Key>,
+    // Keys held at capture start whose release the worker may not have applied yet.
+    released_since_capture: HashSet<Key>,
+    // A capture the hook stopped itself before the worker could publish the stop.
+    ended_capture: Option<u64>,
+}
+
+impl HookDecider {
+    // Takes over from any earlier hook: only the newest one hears about state changes.
+    pub(super) fn attach(state: &InputCaptureState) -> Result<Self> {
+        let (sender, updates) = mpsc::channel();
+        let mut inner = state
+            .lock()
+            .map_err(|err| anyhow::anyhow!("lock input state: {err}"))?;
+        let snapshot = Arc::new(HookSnapshot::from_inner(&inner));
+        inner.hook_updates = Some(sender);
+        inner.published_snapshot = Some(snapshot.clone());
+
+        Ok(Self {
+            updates,
+            snapshot,
+            physical_down: inner.physical_down.clone(),
+            withheld_keys: inner.withheld_keys.clone(),
+            hotkey_keys: HashSet::new(),
+            passthrough_keys: HashSet::new(),
+            released_since_capture: HashSet::new(),
+            ended_capture: None,
+        })
+    }
+
+    fn refresh(&mut self) {
+        while let Ok(update) = self.updates.try_recv() {
+            match update {
+                HookUpdate::Snapshot(snapshot) => {
+                    if snapshot.capture_generation != self.snapshot.capture_generation {
+                        self.released_since_capture.clear();
+                    }
+                    self.snapshot = snapshot;
+                }
+                HookUpdate::Forget(keys) => {
+                    for key in keys {
+                        self.physical_down.remove(&key);
+                        self.withheld_keys.remove(&key);
+                        self.hotkey_keys.remove(&key);
+                        self.passthrough_keys.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    fn has_ended_capture(&self) -> bool {
+        self.ended_capture == Some(self.snapshot.capture_generation)
+    }
+
+    fn capture(&self) -> CaptureState {
+        if self.has_ended_capture() {
+            CaptureState::Idle
+        } else {
+            self.snapshot.capture
+        }
+    }
+
+    fn is_latched(&self, key: Key) -> bool {
+        !self.has_ended_capture() && self.snapshot.latched_keys.contains(&key)
+    }
+
+    fn is_button_latched(&self, button: Button) -> bool {
+        !self.has_ended_capture() && self.snapshot.latched_buttons.contains(&button)
+    }
+
+    fn is_chat_scroll_modifier_held(&mut self) -> bool {
+        self.refresh();
+        self.snapshot
+            .chat_scroll_modifier
+            .is_held(&self.physical_down)
+    }
+
+    fn decide(
+        &mut self,
+        event_type: &EventType,
+        context: ForegroundContext,
+        suppression_enabled: bool,
+    ) -> HookDecision {
+        self.refresh();
+
+        let roblox_foreground = context == ForegroundContext::Roblox;
+        let can_suppress = suppression_enabled && roblox_foreground;
+        let capture = self.capture();
+        let capturing = matches!(capture, CaptureState::Capturing { .. });
+        let mut decision = HookDecision {
+            can_suppress,
+            capture,
+            ..Default::default()
+        };
+
+        match *event_type {
+            EventType::KeyPress(key) => {
+                decision.repeat = !self.physical_down.insert(key);
+                if roblox_foreground && !capturing && !decision.repeat {
+                    decision.hotkey = self.snapshot.hotkeys.match_key(key, &self.physical_down);
+                    if decision.hotkey.is_none() {
+                        decision.quick_chat =
+                            self.snapshot.quick_chat.match_key(key, &self.physical_down);
+                    }
+                }
+                if decision.hotkey.is_some() || decision.quick_chat.is_some() {
+                    self.hotkey_keys.insert(key);
+                }
+                let should_backend_stop =
+                    matches!(
+                        capture,
+                        CaptureState::Capturing {
+                            mode: ChatInputMode::Focusless
+                        }
+                    ) && matches!(key, Key::Return | Key::KpReturn | Key::Escape);
+                decision.suppress = if should_backend_stop || self.hotkey_keys.contains(&key) {
+                    can_suppress
+                } else {
+                    can_suppress && self.should_suppress_key(key, KeyPhase::Down, false)
+                };
+                if decision.suppress {
+                    self.withheld_keys.insert(key);
+                } else {
+                    self.withheld_keys.remove(&key);
+                }
+                decision.passthrough = capturing
+                    && !should_backend_stop
+                    && matches_passthrough_chord(
+                        &self.snapshot.passthrough_chords,
+                        key,
+                        &self.physical_down,
+                    );
+                if decision.passthrough {
+                    let withheld_modifiers = MODIFIER_KEYS
+                        .into_iter()
+                        .filter(|modifier| self.withheld_keys.contains(modifier))
+                        .collect::<Vec<_>>();
+                    // The other app has to see the chord's modifiers first, so replay them ahead of the key.
+                    if can_suppress && !withheld_modifiers.is_empty() && !decision.repeat {
+                        decision.suppress = true;
+                        decision.passthrough_replay = withheld_modifiers;
+                        decision.passthrough_replay.push(key);
+                    }
+                    for replayed in &decision.passthrough_replay {
+                        self.withheld_keys.remove(replayed);
+                        self.passthrough_keys.insert(*replayed);
+                    }
+                    self.passthrough_keys.insert(key);
+                }
+                if should_backend_stop {
+                    decision.stop = Some(if key == Key::Escape {
+                        CaptureStopReason::Cancel
+                    } else {
+                        CaptureStopReason::Submit
+                    });
+                    self.ended_capture = Some(self.snapshot.capture_generation);
+                }
+            }
+            EventType::KeyRelease(key) => {
+                decision.was_down_at_capture = !self.has_ended_capture()
+                    && self.snapshot.capture_started_down.contains(&key)
+                    && self.released_since_capture.insert(key);
+                let hold_released = capturing && self.snapshot.hold_key == Some(key);
+                // The game never saw the hotkey press, so it must not see the release either.
+                decision.suppress = if hold_released {
+                    decision.stop = Some(CaptureStopReason::Release);
+                    self.ended_capture = Some(self.snapshot.capture_generation);
+                    can_suppress
+                } else if self.hotkey_keys.remove(&key) {
+                    can_suppress
+                } else if self.passthrough_keys.remove(&key) {
+                    false
+                } else {
+                    can_suppress
+                        && self.should_suppress_key(key, KeyPhase::Up, decision.was_down_at_capture)
+                };
+                self.physical_down.remove(&key);
+                self.withheld_keys.remove(&key);
+            }
+            EventType::ButtonPress(button) => {
+                // The game already sees a latched button as held, so a second press is noise.
+                decision.suppress = can_suppress && capturing && self.is_button_latched(button);
+            }
+            EventType::ButtonRelease(button) => {
+                // An unsuppressed release reaches the game, so the worker holds the button again.
+                decision.suppress = can_suppress && capturing && self.is_button_latched(button);
+            }
+            _ => {}
+        }
+
+        decision
+    }
+
+    fn should_suppress_key(&self, key: Key, phase: KeyPhase, was_down_at_capture: bool) -> bool {
+        let CaptureState::Capturing { mode } = self.capture() else {
+            return matches!(key, Key::Slash);
+        };
+
+        if mode != ChatInputMode::Focusless {
+            return false;
+        }
+
+        if should_allow_system_shortcut_during_capture(
+            key,
+            &self.physical_down,
+            &self.snapshot.passthrough_chords,
+        ) {
+            return false;
+        }
+
+        if self.is_latched(key) {
+            return true;
+        }
+
+        if matches!(phase, KeyPhase::Up) && was_down_at_capture {
+            return false;
+        }
+
+        true
+    }
+}
+
+pub(super) fn decide_event(
+    state: &InputCaptureState,
+    hook: &mut HookDecider,
+    event: &Event,
+    context: ForegroundContext,
+    suppression_enabled: bool,
+) -> HookDecision {
+    // Our own injected echoes go straight to the game without touching tracked state.
+    if let Some(input) = InjectedInput::from_event_type(&event.event_type) {
+        if state.injector.take_echo(input) {
+            return HookDecision {
+                echo: true,
+                ..Default::default()
+            };
+        }
+    }
+
+    hook.decide(&event.event_type, context, suppression_enabled)
+}
+
+pub(super) fn apply_decision(
+    state: &InputCaptureState,
+    event: &Event,
+    context: ForegroundContext,
+    decision: HookDecision,
+) -> EventOutcome {
+    let mut outcome = settle_event(state, event, context, decision);
+
+    schedule_latched_key_release(
+        state.injector.clone(),
+        mem::take(&mut outcome.keys_to_release),
+    );
+    schedule_latched_button_release(
+        state.injector.clone(),
+        mem::take(&mut outcome.buttons_to_release),
+    );
+
+    if let Some(key) = outcome.reinject_key {
+        state.injector.send_key(key, false);
+    }
+    for key in &outcome.passthrough_replay {
+        state.injector.send_key(*key, false);
+    }
+    if let Some(button) = outcome.reinject_button {
+        state.injector.send_button(button, false);
+    }
+
+    outcome
+}
+
+// Brings the shared state in line with what the hook decided and works out what to tell the frontend.
+fn settle_event(
+    state: &InputCaptureState,
+    event: &Event,
+    context: ForegroundContext,
+    decision: HookDecision,
+) -> EventOutcome {
+    let mut outcome = EventOutcome {
+        suppress: decision.suppress,
+        hotkey: decision.hotkey,
+        quick_chat: decision.quick_chat,
+        passthrough_replay: decision.passthrough_replay,
+        ..Default::default()
+    };
+
+    if decision.echo {
+        if state.trace.is_enabled() && context.is_traced() {
+            outcome.trace = trace_entry(&event.event_type, &outcome).map(|entry| InputTraceEntry {
+                context,
+                echo: true,
+                ..entry
+            });
+        }
+        return outcome;
+    }
+
+    let mut inner = match state.lock() {
+        Ok(guard) => guard,
+        Err(err) => {
+            eprintln!("failed to lock input state on input event: {err}");
+            return outcome;
+        }
+    };
+    if inner.is_capturing() {
+        inner.last_capture_activity = Some(Instant::now());
+    }
+
+    match event.event_type {
+        EventType::KeyPress(key) => {
+            inner.physical_down.insert(key);
+            if outcome.suppress {
+                inner.withheld_keys.insert(key);
+            } else {
+                inner.withheld_keys.remove(&key);
+            }
+            for replayed in &outcome.passthrough_replay {
+                inner.withheld_keys.remove(replayed);
+            }
+            let forward = should_forward_key_event(
+                context,
+                inner.is_capturing(),
+                key,
+                KeyPhase::Down,
+                decision.repeat,
+            );
+            if let Some(reason) = decision.stop {
+                (outcome.keys_to_release, outcome.buttons_to_release) = inner.end_capture(reason);
+                outcome.capture_changes = mem::take(&mut inner.capture_changes);
+            }
+            if forward {
+                let caps = state.injector.caps_lock_enabled();
+                let (text, dead_key) =
+                    derive_key_text(&mut inner, key, caps, event.name.as_deref());
+                if inner.capture_input_mode() == Some(ChatInputMode::Focusless)
+                    && !decision.passthrough
+                {
+                    let modifiers = Modifiers::from_down_keys(&inner.physical_down);
+                    let effect = inner.compose.apply_key(key, modifiers, text.as_deref());
+                    outcome.compose = effect.changed.then(|| inner.compose.state());
+                    outcome.clipboard = effect.clipboard;
+                }
+                outcome.key_event = Some(build_global_key_event(
+                    key,
+                    KeyPhase::Down,
+                    &inner.physical_down,
+                    caps,
+                    decision.repeat,
+                    text,
+                    dead_key,
+                ));
+            }
+        }
+        EventType::KeyRelease(key) => {
+            // Also catches a release the hook decided on before the capture it ends was published.
+            let hold_released = inner.hold_key == Some(key) && inner.capture != CaptureState::Idle;
+            inner.capture_started_down.remove(&key);
+            inner.physical_down.remove(&key);
+            inner.withheld_keys.remove(&key);
+            inner.key_state_suspects.remove(&key);
+            if inner.is_capturing() && inner.latched_keys.contains(&key) && !outcome.suppress {
+                outcome.reinject_key = Some(key);
+            }
+            outcome.key_event =
+                should_forward_key_event(context, inner.is_capturing(), key, KeyPhase::Up, false)
+                    .then(|| {
+                        build_global_key_event(
+                            key,
+                            KeyPhase::Up,
+                            &inner.physical_down,
+                            state.injector.caps_lock_enabled(),
+                            false,
+                            None,
+                            false,
+                        )
+                    });
+            if hold_released {
+                let reason = CaptureStopReason::Release;
+                (outcome.keys_to_release, outcome.buttons_to_release) = inner.end_capture(reason);
+                outcome.capture_changes = mem::take(&mut inner.capture_changes);
+                outcome.stopped = Some(CaptureStoppedEvent {
+                    reason,
+                    timestamp_ms: event_timestamp_ms(),
+                });
+            }
+        }
+        EventType::ButtonPress(button) => {
+            inner.buttons_down.insert(button);
+        }
+        EventType::ButtonRelease(button) => {
+            inner.buttons_down.remove(&button);
+            // The release reached the game, so hold the button again until capture stops.
+            if inner.is_capturing() && inner.latched_buttons.contains(&button) && !outcome.suppress
+            {
+                outcome.reinject_button = Some(button);
+            }
+        }
+        _ => {}
+    }
+
+    if state.trace.is_enabled() && context.is_traced() {
+        let latched = match event.event_type {
+            EventType::KeyPress(key) | EventType::KeyRelease(key) => {
+                inner.latched_keys.contains(&key)
+            }
+            EventType::ButtonPress(button) | EventType::ButtonRelease(button) => {
+                inner.latched_buttons.contains(&button)
+            }
+            _ => false,
+        };
+        outcome.trace = trace_entry(&event.event_type, &outcome).map(|entry| InputTraceEntry {
+            context,
+            capture: decision.capture,
+            can_suppress: decision.can_suppress,
+            was_down_at_capture: decision.was_down_at_capture,
+            latched,
+            ..entry
+        });
+    }
+
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::testing::{event, key_input, process_event, Harness};
+    use crate::input::{
+        begin_chat_capture, end_chat_capture, parse_passthrough_chords, CaptureTrigger,
+        ChatKeyPersistenceMode,
+    };
+    use crate::quick_chat::QuickChatMacro;
+
+    #[test]
+    fn latched_key_echoes_never_feed_back_into_physical_down() {
+        let harness = Harness::new();
+        harness.press(Key::KeyW);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        harness.deliver_echoes();
+        assert_eq!(harness.physical_down(), [Key::KeyW].into());
+
+        end_chat_capture(&harness.state, CaptureStopReason::Cancel).unwrap();
+        harness.wait_for_sent(key_input(Key::KeyW, true), 4);
+        harness.deliver_echoes();
+        assert_eq!(harness.physical_down(), [Key::KeyW].into());
+
+        harness.release(Key::KeyW);
+        assert!(harness.physical_down().is_empty());
+    }
+
+    #[test]
+    fn hold_release_racing_capture_start_still_closes_it() {
+        let harness = Harness::new();
+        harness.state.lock().unwrap().capture_trigger = CaptureTrigger::Hold;
+        harness.press(Key::Slash);
+
+        // The hook decides the release while still idle; the worker applies it once capture is open.
+        let release = event(EventType::KeyRelease(Key::Slash));
+        let decision = decide_event(
+            &harness.state,
+            &mut harness.hook.borrow_mut(),
+            &release,
+            ForegroundContext::Roblox,
+            true,
+        );
+        assert!(decision.suppress && decision.stop.is_none());
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::None,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        assert!(harness.is_active());
+
+        let outcome = apply_decision(
+            &harness.state,
+            &release,
+            ForegroundContext::Roblox,
+            decision,
+        );
+        assert!(outcome.stopped.is_some());
+        assert!(!harness.is_active());
+    }
+
+    #[test]
+    fn alt_tab_during_capture_reaches_windows() {
+        let harness = Harness::new();
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Full,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+
+        assert!(!harness.press(Key::Alt).suppress);
+        assert!(!harness.press(Key::Tab).suppress);
+        assert!(!harness.release(Key::Tab).suppress);
+        assert!(!harness.release(Key::Alt).suppress);
+
+        let elsewhere = harness.send_in(ForegroundContext::Other, EventType::KeyPress(Key::KeyH));
+        assert!(!elsewhere.suppress && elsewhere.key_event.is_none());
+        assert!(harness.press(Key::KeyH).suppress);
+    }
+
+    #[test]
+    fn reinjected_latch_is_not_mistaken_for_a_new_press() {
+        let harness = Harness::new();
+        harness.press(Key::KeyD);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Wasd,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        harness.deliver_echoes();
+
+        let outcome = process_event(
+            &harness.state,
+            &mut harness.hook.borrow_mut(),
+            &event(EventType::KeyRelease(Key::KeyD)),
+            ForegroundContext::Roblox,
+            false,
+        );
+        assert_eq!(outcome.reinject_key, Some(Key::KeyD));
+        assert_eq!(harness.sent_count(key_input(Key::KeyD, false)), 2);
+
+        harness.deliver_echoes();
+        assert!(harness.physical_down().is_empty());
+    }
+
+    #[test]
+    fn modifier_wheel_scrolls_chat_only_over_roblox() {
+        let harness = Harness::new();
+        assert_eq!(
+            chat_scroll_event(&mut harness.hook.borrow_mut(), ForegroundContext::Roblox, 1),
+            None
+        );
+
+        harness.press(Key::Alt);
+        let scroll = chat_scroll_event(
+            &mut harness.hook.borrow_mut(),
+            ForegroundContext::Roblox,
+            -2,
+        )
+        .unwrap();
+        assert_eq!(scroll.delta, -2);
+        assert_eq!(
+            chat_scroll_event(&mut harness.hook.borrow_mut(), ForegroundContext::Roblox, 0),
+            None
+        );
+        for context in [
+            ForegroundContext::App,
+            ForegroundContext::Secure,
+            ForegroundContext::Other,
+        ] {
+            assert_eq!(
+                chat_scroll_event(&mut harness.hook.borrow_mut(), context, 1),
+                None
+            );
+        }
+
+        harness.state.lock().unwrap().chat_scroll_modifier = ChatScrollModifier::Off;
+        assert_eq!(
+            chat_scroll_event(&mut harness.hook.borrow_mut(), ForegroundContext::Roblox, 1),
+            None
+        );
+        harness.state.lock().unwrap().chat_scroll_modifier = ChatScrollModifier::Shift;
+        harness.press(Key::ShiftRight);
+        assert!(
+            chat_scroll_event(&mut harness.hook.borrow_mut(), ForegroundContext::Roblox, 1)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn quick_chat_chord_is_kept_from_the_game() {
+        let harness = Harness::new();
+        {
+            let mut inner = harness.state.lock().unwrap();
+            let inner = &mut *inner;
+            inner
+                .quick_chat
+                .set_all(
+                    &[QuickChatMacro {
+                        chord: "Alt+Digit1".to_string(),
+                        template: "gg {jobId}".to_string(),
+                    }],
+                    &inner.hotkeys,
+                )
+                .unwrap();
+        }
+
+        harness.press(Key::Alt);
+        let outcome = harness.press(Key::Num1);
+        assert!(outcome.suppress);
+        assert_eq!(
+            outcome.quick_chat.map(|(_, template)| template),
+            Some("gg {jobId}".to_string())
+        );
+        assert!(harness.release(Key::Num1).suppress);
+        harness.release(Key::Alt);
+
+        let outcome = harness.send_in(ForegroundContext::Other, EventType::KeyPress(Key::Alt));
+        assert!(outcome.quick_chat.is_none());
+        let outcome = harness.send_in(ForegroundContext::Other, EventType::KeyPress(Key::Num1));
+        assert!(outcome.quick_chat.is_none() && !outcome.suppress);
+    }
+
+    #[test]
+    fn passthrough_chords_reach_other_apps_during_capture() {
+        let harness = Harness::new();
+        harness.state.lock().unwrap().passthrough_chords =
+            parse_passthrough_chords(&["Ctrl+Shift+KeyM".to_string(), "F9".to_string()]).unwrap();
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::None,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+
+        assert!(!harness.press(Key::F9).suppress);
+        assert!(!harness.release(Key::F9).suppress);
+
+        assert!(harness.press(Key::ControlLeft).suppress);
+        assert!(harness.press(Key::ShiftLeft).suppress);
+        let outcome = harness.press(Key::KeyM);
+        assert!(outcome.suppress && outcome.compose.is_none());
+        assert_eq!(
+            harness.injector.sent(),
+            vec![
+                key_input(Key::ControlLeft, false),
+                key_input(Key::ShiftLeft, false),
+                key_input(Key::KeyM, false),
+            ]
+        );
+        harness.deliver_echoes();
+
+        // The replayed presses are down in the OS now, so their releases must get through.
+        assert!(!harness.release(Key::ControlLeft).suppress);
+        assert!(!harness.release(Key::KeyM).suppress);
+        assert!(!harness.release(Key::ShiftLeft).suppress);
+        assert!(harness.press(Key::KeyM).suppress);
+    }
+
+    #[test]
+    fn enter_in_focusless_capture_stops_as_submit() {
+        let harness = Harness::new();
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::None,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        harness.state.lock().unwrap().capture_changes.clear();
+
+        let outcome = harness.press(Key::Return);
+        assert!(outcome.suppress);
+        assert_eq!(
+            outcome.capture_changes,
+            vec![
+                CaptureState::Stopping {
+                    reason: CaptureStopReason::Submit
+                },
+                CaptureState::Idle,
+            ]
+        );
+    }
+
+    #[test]
+    fn hook_decides_ahead_of_the_worker_without_the_state_lock() {
+        let harness = Harness::new();
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::None,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+
+        // Would deadlock if a decision needed the lock the worker is holding.
+        let _worker = harness.state.lock().unwrap();
+        let mut hook = harness.hook.borrow_mut();
+        let mut decide = |event_type| {
+            decide_event(
+                &harness.state,
+                &mut hook,
+                &event(event_type),
+                ForegroundContext::Roblox,
+                true,
+            )
+        };
+        assert!(decide(EventType::KeyPress(Key::KeyH)).suppress);
+        assert!(decide(EventType::KeyRelease(Key::KeyH)).suppress);
+
+        // The hook stops capture on Enter without waiting for the worker to publish it.
+        let submit = decide(EventType::KeyPress(Key::Return));
+        assert!(submit.suppress);
+        assert_eq!(submit.stop, Some(CaptureStopReason::Submit));
+        decide(EventType::KeyRelease(Key::Return));
+        let after = decide(EventType::KeyPress(Key::KeyH));
+        assert!(!after.suppress && after.capture == CaptureState::Idle);
+    }
+
+    #[test]
+    #[ignore = "benchmark: cargo test hook_decision_latency -- --ignored --nocapture"]
+    fn hook_decision_latency() {
+        const EVENTS: usize = 100_000;
+        let harness = Harness::new();
+        harness.press(Key::KeyW);
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Wasd,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+
+        harness.state.foreground.set(ForegroundContext::Roblox);
+
+        // The real worker applies every decision while the hook keeps going, contending for the state.
+        let (sender, messages) = mpsc::channel();
+        let worker = {
+            let state = harness.state.clone();
+            std::thread::spawn(move || {
+                for message in messages {
+                    if let HookMessage::Input(input) = message {
+                        apply_decision(&state, &input.event, input.context, input.decision);
+                    }
+                }
+            })
+        };
+
+        let mut hook = harness.hook.borrow_mut();
+        let mut samples = Vec::with_capacity(EVENTS);
+        for index in 0..EVENTS {
+            let key = [Key::KeyH, Key::KeyI][index % 2];
+            let event_type = match index % 8 {
+                0 | 1 => EventType::KeyPress(key),
+                2 | 3 => EventType::KeyRelease(key),
+                4 => EventType::ButtonPress(Button::Left),
+                5 => EventType::ButtonRelease(Button::Left),
+                _ => EventType::Wheel {
+                    delta_x: 0,
+                    delta_y: 1,
+                },
+            };
+            let event = event(event_type);
+            let started = Instant::now();
+            let suppressed = handle_event(&harness.state, &mut hook, &sender, &event, true);
+            samples.push(started.elapsed());
+            std::hint::black_box(suppressed);
+        }
+        drop(sender);
+        worker.join().unwrap();
+
+        samples.sort();
+        let percentile = |percent: usize| samples[(samples.len() - 1) * percent / 100];
+        println!(
+            "hook path over {EVENTS} events: p50 {:?}, p99 {:?}, max {:?}",
+            percentile(50),
+            percentile(99),
+            samples[samples.len() - 1],
+        );
+    }
+}
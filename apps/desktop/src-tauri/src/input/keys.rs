@@ -0,0 +1,278 @@
+use rdev::Key;
+
+// X keycodes are evdev codes shifted past the reserved range.
+pub(crate) const X_KEYCODE_OFFSET: u32 = 8;
+
+pub(crate) struct KeySpec {
+    pub(crate) key: Key,
+    // The DOM KeyboardEvent.code, which is also what settings and chords store.
+    pub(crate) code: &'static str,
+    // Each platform only reads its own columns.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) virtual_key: u16,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) evdev: u16,
+    // Windows sends these with the E0 scan code prefix.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) extended: bool,
+}
+
+impl KeySpec {
+    const fn new(
+        key: Key,
+        code: &'static str,
+        virtual_key: u16,
+        evdev: u16,
+        extended: bool,
+    ) -> Self {
+        Self {
+            key,
+            code,
+            virtual_key,
+            evdev,
+            extended,
+        }
+    }
+
+    // rdev has no variant for these keys, so it reports them by the platform's own code. On Linux only
+    // the passive X11 listener does: the evdev grab drops every key rdev cannot name, so while it
+    // runs these can be injected but never seen, and cannot trigger chords or latch.
+    const fn unnamed(code: &'static str, virtual_key: u16, evdev: u16, extended: bool) -> Self {
+        let key = if cfg!(windows) {
+            Key::Unknown(virtual_key as u32)
+        } else {
+            Key::Unknown(evdev as u32 + X_KEYCODE_OFFSET)
+        };
+        Self::new(key, code, virtual_key, evdev, extended)
+    }
+}
+
+// Enter and NumpadEnter share a virtual key and differ only by the extended flag.
+pub(crate) const KEY_TABLE: &[KeySpec] = &[
+    KeySpec::new(Key::KeyA, "KeyA", 0x41, 30, false),
+    KeySpec::new(Key::KeyB, "KeyB", 0x42, 48, false),
+    KeySpec::new(Key::KeyC, "KeyC", 0x43, 46, false),
+    KeySpec::new(Key::KeyD, "KeyD", 0x44, 32, false),
+    KeySpec::new(Key::KeyE, "KeyE", 0x45, 18, false),
+    KeySpec::new(Key::KeyF, "KeyF", 0x46, 33, false),
+    KeySpec::new(Key::KeyG, "KeyG", 0x47, 34, false),
+    KeySpec::new(Key::KeyH, "KeyH", 0x48, 35, false),
+    KeySpec::new(Key::KeyI, "KeyI", 0x49, 23, false),
+    KeySpec::new(Key::KeyJ, "KeyJ", 0x4A, 36, false),
+    KeySpec::new(Key::KeyK, "KeyK", 0x4B, 37, false),
+    KeySpec::new(Key::KeyL, "KeyL", 0x4C, 38, false),
+    KeySpec::new(Key::KeyM, "KeyM", 0x4D, 50, false),
+    KeySpec::new(Key::KeyN, "KeyN", 0x4E, 49, false),
+    KeySpec::new(Key::KeyO, "KeyO", 0x4F, 24, false),
+    KeySpec::new(Key::KeyP, "KeyP", 0x50, 25, false),
+    KeySpec::new(Key::KeyQ, "KeyQ", 0x51, 16, false),
+    KeySpec::new(Key::KeyR, "KeyR", 0x52, 19, false),
+    KeySpec::new(Key::KeyS, "KeyS", 0x53, 31, false),
+    KeySpec::new(Key::KeyT, "KeyT", 0x54, 20, false),
+    KeySpec::new(Key::KeyU, "KeyU", 0x55, 22, false),
+    KeySpec::new(Key::KeyV, "KeyV", 0x56, 47, false),
+    KeySpec::new(Key::KeyW, "KeyW", 0x57, 17, false),
+    KeySpec::new(Key::KeyX, "KeyX", 0x58, 45, false),
+    KeySpec::new(Key::KeyY, "KeyY", 0x59, 21, false),
+    KeySpec::new(Key::KeyZ, "KeyZ", 0x5A, 44, false),
+    KeySpec::new(Key::Num1, "Digit1", 0x31, 2, false),
+    KeySpec::new(Key::Num2, "Digit2", 0x32, 3, false),
+    KeySpec::new(Key::Num3, "Digit3", 0x33, 4, false),
+    KeySpec::new(Key::Num4, "Digit4", 0x34, 5, false),
+    KeySpec::new(Key::Num5, "Digit5", 0x35, 6, false),
+    KeySpec::new(Key::Num6, "Digit6", 0x36, 7, false),
+    KeySpec::new(Key::Num7, "Digit7", 0x37, 8, false),
+    KeySpec::new(Key::Num8, "Digit8", 0x38, 9, false),
+    KeySpec::new(Key::Num9, "Digit9", 0x39, 10, false),
+    KeySpec::new(Key::Num0, "Digit0", 0x30, 11, false),
+    KeySpec::new(Key::Escape, "Escape", 0x1B, 1, false),
+    KeySpec::new(Key::F1, "F1", 0x70, 59, false),
+    KeySpec::new(Key::F2, "F2", 0x71, 60, false),
+    KeySpec::new(Key::F3, "F3", 0x72, 61, false),
+    KeySpec::new(Key::F4, "F4", 0x73, 62, false),
+    KeySpec::new(Key::F5, "F5", 0x74, 63, false),
+    KeySpec::new(Key::F6, "F6", 0x75, 64, false),
+    KeySpec::new(Key::F7, "F7", 0x76, 65, false),
+    KeySpec::new(Key::F8, "F8", 0x77, 66, false),
+    KeySpec::new(Key::F9, "F9", 0x78, 67, false),
+    KeySpec::new(Key::F10, "F10", 0x79, 68, false),
+    KeySpec::new(Key::F11, "F11", 0x7A, 87, false),
+    KeySpec::new(Key::F12, "F12", 0x7B, 88, false),
+    KeySpec::unnamed("F13", 0x7C, 183, false),
+    KeySpec::unnamed("F14", 0x7D, 184, false),
+    KeySpec::unnamed("F15", 0x7E, 185, false),
+    KeySpec::unnamed("F16", 0x7F, 186, false),
+    KeySpec::unnamed("F17", 0x80, 187, false),
+    KeySpec::unnamed("F18", 0x81, 188, false),
+    KeySpec::unnamed("F19", 0x82, 189, false),
+    KeySpec::unnamed("F20", 0x83, 190, false),
+    KeySpec::unnamed("F21", 0x84, 191, false),
+    KeySpec::unnamed("F22", 0x85, 192, false),
+    KeySpec::unnamed("F23", 0x86, 193, false),
+    KeySpec::unnamed("F24", 0x87, 194, false),
+    KeySpec::new(Key::BackQuote, "Backquote", 0xC0, 41, false),
+    KeySpec::new(Key::Minus, "Minus", 0xBD, 12, false),
+    KeySpec::new(Key::Equal, "Equal", 0xBB, 13, false),
+    KeySpec::new(Key::Backspace, "Backspace", 0x08, 14, false),
+    KeySpec::new(Key::Tab, "Tab", 0x09, 15, false),
+    KeySpec::new(Key::LeftBracket, "BracketLeft", 0xDB, 26, false),
+    KeySpec::new(Key::RightBracket, "BracketRight", 0xDD, 27, false),
+    KeySpec::new(Key::BackSlash, "Backslash", 0xDC, 43, false),
+    KeySpec::new(Key::IntlBackslash, "IntlBackslash", 0xE2, 86, false),
+    KeySpec::new(Key::CapsLock, "CapsLock", 0x14, 58, false),
+    KeySpec::new(Key::SemiColon, "Semicolon", 0xBA, 39, false),
+    KeySpec::new(Key::Quote, "Quote", 0xDE, 40, false),
+    KeySpec::new(Key::Return, "Enter", 0x0D, 28, false),
+    KeySpec::new(Key::ShiftLeft, "ShiftLeft", 0xA0, 42, false),
+    KeySpec::new(Key::ShiftRight, "ShiftRight", 0xA1, 54, false),
+    KeySpec::new(Key::ControlLeft, "ControlLeft", 0xA2, 29, false),
+    KeySpec::new(Key::ControlRight, "ControlRight", 0xA3, 97, true),
+    KeySpec::new(Key::Alt, "AltLeft", 0xA4, 56, false),
+    KeySpec::new(Key::AltGr, "AltRight", 0xA5, 100, true),
+    KeySpec::new(Key::MetaLeft, "MetaLeft", 0x5B, 125, true),
+    KeySpec::new(Key::MetaRight, "MetaRight", 0x5C, 126, true),
+    KeySpec::unnamed("ContextMenu", 0x5D, 127, true),
+    KeySpec::new(Key::Space, "Space", 0x20, 57, false),
+    KeySpec::new(Key::PrintScreen, "PrintScreen", 0x2C, 99, true),
+    KeySpec::new(Key::ScrollLock, "ScrollLock", 0x91, 70, false),
+    KeySpec::new(Key::Pause, "Pause", 0x13, 119, false),
+    KeySpec::new(Key::Insert, "Insert", 0x2D, 110, true),
+    KeySpec::new(Key::Home, "Home", 0x24, 102, true),
+    KeySpec::new(Key::PageUp, "PageUp", 0x21, 104, true),
+    KeySpec::new(Key::Delete, "Delete", 0x2E, 111, true),
+    KeySpec::new(Key::End, "End", 0x23, 107, true),
+    KeySpec::new(Key::PageDown, "PageDown", 0x22, 109, true),
+    KeySpec::new(Key::RightArrow, "ArrowRight", 0x27, 106, true),
+    KeySpec::new(Key::LeftArrow, "ArrowLeft", 0x25, 105, true),
+    KeySpec::new(Key::DownArrow, "ArrowDown", 0x28, 108, true),
+    KeySpec::new(Key::UpArrow, "ArrowUp", 0x26, 103, true),
+    KeySpec::new(Key::NumLock, "NumLock", 0x90, 69, true),
+    KeySpec::new(Key::Kp0, "Numpad0", 0x60, 82, false),
+    KeySpec::new(Key::Kp1, "Numpad1", 0x61, 79, false),
+    KeySpec::new(Key::Kp2, "Numpad2", 0x62, 80, false),
+    KeySpec::new(Key::Kp3, "Numpad3", 0x63, 81, false),
+    KeySpec::new(Key::Kp4, "Numpad4", 0x64, 75, false),
+    KeySpec::new(Key::Kp5, "Numpad5", 0x65, 76, false),
+    KeySpec::new(Key::Kp6, "Numpad6", 0x66, 77, false),
+    KeySpec::new(Key::Kp7, "Numpad7", 0x67, 71, false),
+    KeySpec::new(Key::Kp8, "Numpad8", 0x68, 72, false),
+    KeySpec::new(Key::Kp9, "Numpad9", 0x69, 73, false),
+    KeySpec::new(Key::KpMultiply, "NumpadMultiply", 0x6A, 55, false),
+    KeySpec::new(Key::KpPlus, "NumpadAdd", 0x6B, 78, false),
+    KeySpec::new(Key::KpMinus, "NumpadSubtract", 0x6D, 74, false),
+    KeySpec::new(Key::KpDelete, "NumpadDecimal", 0x6E, 83, false),
+    KeySpec::new(Key::KpDivide, "NumpadDivide", 0x6F, 98, true),
+    KeySpec::new(Key::KpReturn, "NumpadEnter", 0x0D, 96, true),
+    KeySpec::unnamed("NumpadEqual", 0x92, 117, false),
+    KeySpec::new(Key::Slash, "Slash", 0xBF, 53, false),
+    KeySpec::new(Key::Dot, "Period", 0xBE, 52, false),
+    KeySpec::new(Key::Comma, "Comma", 0xBC, 51, false),
+    KeySpec::unnamed("AudioVolumeMute", 0xAD, 113, true),
+    KeySpec::unnamed("AudioVolumeDown", 0xAE, 114, true),
+    KeySpec::unnamed("AudioVolumeUp", 0xAF, 115, true),
+    KeySpec::unnamed("MediaTrackNext", 0xB0, 163, true),
+    KeySpec::unnamed("MediaTrackPrevious", 0xB1, 165, true),
+    KeySpec::unnamed("MediaStop", 0xB2, 166, true),
+    KeySpec::unnamed("MediaPlayPause", 0xB3, 164, true),
+];
+
+pub(crate) fn key_spec(key: Key) -> Option<&'static KeySpec> {
+    KEY_TABLE.iter().find(|spec| spec.key == key)
+}
+
+// What rdev reports for a virtual key, except that the extended flag tells NumpadEnter from Enter
+// and the right Windows key gets its name.
+#[cfg(windows)]
+pub(crate) fn key_from_virtual_key(virtual_key: u16, extended: bool) -> Key {
+    let specs = || {
+        KEY_TABLE
+            .iter()
+            .filter(|spec| spec.virtual_key == virtual_key)
+    };
+    specs()
+        .find(|spec| spec.extended == extended)
+        .or_else(|| specs().next())
+        .map_or(Key::Unknown(virtual_key as u32), |spec| spec.key)
+}
+
+pub(crate) fn key_to_code(key: Key) -> String {
+    match key_spec(key) {
+        Some(spec) => spec.code.to_string(),
+        None => format!("{key:?}"),
+    }
+}
+
+pub(crate) fn key_from_code(code: &str) -> Option<Key> {
+    KEY_TABLE
+        .iter()
+        .find(|spec| spec.code == code)
+        .map(|spec| spec.key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn every_key_round_trips_through_its_code() {
+        for spec in KEY_TABLE {
+            assert_eq!(key_to_code(spec.key), spec.code);
+            assert_eq!(key_from_code(spec.code), Some(spec.key), "{}", spec.code);
+        }
+        assert_eq!(key_from_code("Function"), None);
+        assert_eq!(key_to_code(Key::Function), "Function");
+    }
+
+    #[test]
+    fn table_columns_never_collide() {
+        let mut keys = HashSet::new();
+        let mut codes = HashSet::new();
+        let mut evdev = HashSet::new();
+        let mut virtual_keys = HashSet::new();
+        for spec in KEY_TABLE {
+            assert!(keys.insert(spec.key), "{:?} is listed twice", spec.key);
+            assert!(codes.insert(spec.code), "{} is listed twice", spec.code);
+            assert!(
+                evdev.insert(spec.evdev),
+                "evdev {} is listed twice",
+                spec.evdev
+            );
+            assert!(
+                virtual_keys.insert((spec.virtual_key, spec.extended)),
+                "virtual key {:#x} is listed twice",
+                spec.virtual_key
+            );
+        }
+    }
+
+    #[test]
+    fn keys_rdev_cannot_name_use_the_platform_code() {
+        let expected = |virtual_key: u32, evdev: u32| {
+            if cfg!(windows) {
+                Key::Unknown(virtual_key)
+            } else {
+                Key::Unknown(evdev + X_KEYCODE_OFFSET)
+            }
+        };
+
+        assert_eq!(key_from_code("F13"), Some(expected(0x7C, 183)));
+        assert_eq!(key_from_code("F24"), Some(expected(0x87, 194)));
+        assert_eq!(key_from_code("ContextMenu"), Some(expected(0x5D, 127)));
+        assert_eq!(key_from_code("NumpadEqual"), Some(expected(0x92, 117)));
+        assert_eq!(key_from_code("MediaPlayPause"), Some(expected(0xB3, 164)));
+        assert_eq!(key_from_code("IntlBackslash"), Some(Key::IntlBackslash));
+        assert_eq!(key_to_code(expected(0xAF, 115)), "AudioVolumeUp");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn virtual_keys_use_the_extended_flag_only_to_split_shared_keys() {
+        assert_eq!(key_from_virtual_key(0x0D, false), Key::Return);
+        assert_eq!(key_from_virtual_key(0x0D, true), Key::KpReturn);
+        // Numpad arrows with NumLock off arrive without the extended flag.
+        assert_eq!(key_from_virtual_key(0x25, false), Key::LeftArrow);
+        assert_eq!(key_from_virtual_key(0x5C, true), Key::MetaRight);
+        assert_eq!(key_from_virtual_key(0xFF, false), Key::Unknown(0xFF));
+    }
+}
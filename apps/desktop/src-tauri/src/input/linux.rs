@@ -0,0 +1,581 @@
+use super::keys::{self, KEY_TABLE, X_KEYCODE_OFFSET};
+use super::{
+    ImeCaret, ImeEventHandler, InjectedInput, InjectionLedger, KeyInjector, KeyText, KeyboardLayout,
+};
+use evdev_rs::enums::{int_to_ev_key, EventCode, EventType, EV_KEY, EV_SYN};
+use evdev_rs::{Device, InputEvent, TimeVal, UInputDevice};
+use rdev::{Button, Event, Key};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use x11::xtest::{XTestFakeButtonEvent, XTestFakeKeyEvent, XTestQueryExtension};
+use xkbcommon::xkb::{self, compose};
+
+const VIRTUAL_KEYBOARD_NAME: &str = "BloxChat virtual keyboard";
+// Short, since the hook reads the cached context and keys pressed right after switching windows count.
+const FOREGROUND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const INJECTABLE_BUTTONS: [Button; 5] = [
+    Button::Left,
+    Button::Right,
+    Button::Middle,
+    Button::Unknown(1),
+    Button::Unknown(2),
+];
+
+pub(super) struct LinuxKeyInjector {
+    backend: Mutex<Backend>,
+    ledger: InjectionLedger,
+}
+
+enum Backend {
+    Uinput(VirtualKeyboard),
+    XTest,
+    Unavailable,
+}
+
+struct VirtualKeyboard(UInputDevice);
+
+// The libevdev_uinput behind the handle owns just its fd and name strings: no reference back to the
+// Device it was built from, no refcount and no thread affinity. It is never cloned or lent out, and
+// every write happens under the backend lock, so moving it to another thread cannot race anything.
+unsafe impl Send for VirtualKeyboard {}
+
+impl VirtualKeyboard {
+    fn create() -> io::Result<Self> {
+        let device = Device::new()
+            .ok_or_else(|| io::Error::other("libevdev could not allocate a device"))?;
+        device.set_name(VIRTUAL_KEYBOARD_NAME);
+        device.enable(&EventType::EV_KEY)?;
+        device.enable(&EventType::EV_SYN)?;
+
+        let keys = KEY_TABLE
+            .iter()
+            .filter_map(|spec| key_to_evdev_key(spec.key));
+        let buttons = INJECTABLE_BUTTONS
+            .iter()
+            .filter_map(|button| button_to_evdev_key(*button));
+        for code in keys.chain(buttons) {
+            device.enable(&EventCode::EV_KEY(code))?;
+        }
+
+        UInputDevice::create_from_device(&device).map(Self)
+    }
+
+    fn write(&self, code: EV_KEY, up: bool) -> io::Result<()> {
+        let time = TimeVal::new(0, 0);
+        self.0.write_event(&InputEvent::new(
+            &time,
+            &EventCode::EV_KEY(code),
+            if up { 0 } else { 1 },
+        ))?;
+        self.0.write_event(&InputEvent::new(
+            &time,
+            &EventCode::EV_SYN(EV_SYN::SYN_REPORT),
+            0,
+        ))
+    }
+}
+
+impl Default for LinuxKeyInjector {
+    fn default() -> Self {
+        let backend = match VirtualKeyboard::create() {
+            Ok(keyboard) => Backend::Uinput(keyboard),
+            Err(err) if xtest_available() => {
+                eprintln!("Cannot create uinput keyboard ({err}), falling back to XTest");
+                Backend::XTest
+            }
+            Err(err) => {
+                eprintln!("Cannot create uinput keyboard ({err}) and XTest is unavailable");
+                Backend::Unavailable
+            }
+        };
+
+        Self {
+            backend: Mutex::new(backend),
+            ledger: InjectionLedger::default(),
+        }
+    }
+}
+
+impl LinuxKeyInjector {
+    fn send(&self, input: InjectedInput, code: EV_KEY, up: bool) {
+        let backend = match self.backend.lock() {
+            Ok(backend) => backend,
+            Err(err) => {
+                eprintln!("failed to lock input injector: {err}");
+                return;
+            }
+        };
+
+        match &*backend {
+            Backend::Uinput(keyboard) => {
+                // Record before sending: the grab thread can read the event before write returns.
+                self.ledger.record(input, 1, Instant::now());
+                if let Err(err) = keyboard.write(code, up) {
+                    eprintln!("Failed to write to uinput keyboard: {err}");
+                }
+            }
+            Backend::XTest => {
+                self.ledger.record(input, 1, Instant::now());
+                send_xtest(code, up);
+            }
+            Backend::Unavailable => {}
+        }
+    }
+}
+
+impl KeyInjector for LinuxKeyInjector {
+    fn send_key(&self, key: Key, up: bool) {
+        if let Some(code) = key_to_evdev_key(key) {
+            self.send(InjectedInput::Key { key, up }, code, up);
+        }
+    }
+
+    fn send_button(&self, button: Button, up: bool) {
+        if let Some(code) = button_to_evdev_key(button) {
+            self.send(InjectedInput::Button { button, up }, code, up);
+        }
+    }
+
+    fn caps_lock_enabled(&self) -> bool {
+        crate::xdisplay::caps_lock_enabled()
+    }
+
+    fn is_key_down(&self, key: Key) -> Option<bool> {
+        let code = key_to_evdev_key(key)?;
+        crate::xdisplay::is_keycode_down(code as u32 + X_KEYCODE_OFFSET)
+    }
+
+    fn is_available(&self) -> bool {
+        !matches!(self.backend.lock().as_deref(), Ok(Backend::Unavailable))
+    }
+
+    fn take_echo(&self, input: InjectedInput) -> bool {
+        self.ledger.consume(input, Instant::now())
+    }
+}
+
+fn xtest_available() -> bool {
+    crate::xdisplay::with_display(|display| unsafe {
+        let (mut event_base, mut error_base, mut major, mut minor) = (0, 0, 0, 0);
+        XTestQueryExtension(
+            display,
+            &mut event_base,
+            &mut error_base,
+            &mut major,
+            &mut minor,
+        ) != 0
+    })
+    .unwrap_or(false)
+}
+
+fn send_xtest(code: EV_KEY, up: bool) {
+    let is_press = if up { 0 } else { 1 };
+    crate::xdisplay::with_display(|display| unsafe {
+        match x11_button(&code) {
+            Some(button) => XTestFakeButtonEvent(display, button, is_press, 0),
+            None => XTestFakeKeyEvent(display, code as u32 + X_KEYCODE_OFFSET, is_press, 0),
+        };
+        x11::xlib::XFlush(display);
+    });
+}
+
+fn x11_button(code: &EV_KEY) -> Option<u32> {
+    let button = match code {
+        EV_KEY::BTN_LEFT => 1,
+        EV_KEY::BTN_MIDDLE => 2,
+        EV_KEY::BTN_RIGHT => 3,
+        EV_KEY::BTN_SIDE => 8,
+        EV_KEY::BTN_EXTRA => 9,
+        _ => return None,
+    };
+
+    Some(button)
+}
+
+pub(super) struct XkbKeyboardLayout {
+    context: xkb::Context,
+    keymap: Option<xkb::Keymap>,
+    compose: Option<compose::State>,
+    locked_mods: xkb::ModMask,
+    group: xkb::LayoutIndex,
+}
+
+// libxkbcommon refcounts are not atomic, so moving these objects is only sound because every
+// reference to them lives in this struct: the keymap and the compose table each hold the context,
+// and the xkb::State that refresh and key_text build is dropped before they return. Nothing here
+// is cloned or handed out, and both methods take &mut self under the input state lock.
+unsafe impl Send for XkbKeyboardLayout {}
+
+impl Default for XkbKeyboardLayout {
+    fn default() -> Self {
+        Self::with_locale(&current_locale())
+    }
+}
+
+impl XkbKeyboardLayout {
+    fn with_locale(locale: &OsString) -> Self {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let compose = compose::Table::new_from_locale(&context, locale, compose::COMPILE_NO_FLAGS)
+            .ok()
+            .map(|table| compose::State::new(&table, compose::STATE_NO_FLAGS));
+
+        Self {
+            context,
+            keymap: None,
+            compose,
+            locked_mods: 0,
+            group: 0,
+        }
+    }
+
+    fn typed_text(state: &xkb::State, keycode: xkb::Keycode) -> KeyText {
+        let text = state.key_get_utf8(keycode);
+        if text.is_empty() {
+            KeyText::Empty
+        } else {
+            KeyText::Typed(text)
+        }
+    }
+}
+
+impl KeyboardLayout for XkbKeyboardLayout {
+    fn refresh(&mut self) {
+        if let Some(compose) = self.compose.as_mut() {
+            compose.reset();
+        }
+
+        let Some((keymap, state)) = crate::xdisplay::core_keyboard_keymap(&self.context) else {
+            eprintln!("Failed to read the X keyboard layout, falling back to rdev key names");
+            self.keymap = None;
+            return;
+        };
+        // Lock keys pressed outside capture reached the server, so its locked state is current.
+        self.locked_mods = state.serialize_mods(xkb::STATE_MODS_LOCKED);
+        self.group = state.serialize_layout(xkb::STATE_LAYOUT_EFFECTIVE);
+        self.keymap = Some(keymap);
+    }
+
+    fn key_text(&mut self, key: Key, down_keys: &HashSet<Key>, caps: bool) -> Option<KeyText> {
+        let keymap = self.keymap.as_ref()?;
+        let keycode = key_to_xkb_keycode(key)?;
+
+        // Modifiers are withheld from the server during capture, so rebuild the state from what we track.
+        let caps_index = keymap.mod_get_index(xkb::MOD_NAME_CAPS);
+        let caps_mask = if caps_index == xkb::MOD_INVALID {
+            0
+        } else {
+            1 << caps_index
+        };
+        let locked_mods = if caps {
+            self.locked_mods | caps_mask
+        } else {
+            self.locked_mods & !caps_mask
+        };
+        let mut state = xkb::State::new(keymap);
+        state.update_mask(0, 0, locked_mods, 0, 0, self.group);
+        for held in down_keys
+            .iter()
+            .copied()
+            .filter(|held| is_modifier_key(*held))
+        {
+            if let Some(code) = key_to_xkb_keycode(held) {
+                state.update_key(code, xkb::KeyDirection::Down);
+            }
+        }
+
+        let Some(compose) = self.compose.as_mut() else {
+            return Some(Self::typed_text(&state, keycode));
+        };
+        if compose.feed(state.key_get_one_sym(keycode)) == compose::FeedResult::Ignored {
+            return Some(Self::typed_text(&state, keycode));
+        }
+
+        let text = match compose.status() {
+            compose::Status::Composing => KeyText::Dead,
+            compose::Status::Composed => {
+                let text = compose.utf8();
+                compose.reset();
+                text.map_or(KeyText::Empty, KeyText::Typed)
+            }
+            // A dead key followed by something it cannot combine with drops the dead key.
+            compose::Status::Cancelled => {
+                compose.reset();
+                Self::typed_text(&state, keycode)
+            }
+            compose::Status::Nothing => Self::typed_text(&state, keycode),
+        };
+        Some(text)
+    }
+}
+
+// Compose tables are per locale, picked the same way Xlib does.
+fn current_locale() -> OsString {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .find_map(|name| std::env::var_os(name).filter(|value| !value.is_empty()))
+        .unwrap_or_else(|| "C".into())
+}
+
+fn is_modifier_key(key: Key) -> bool {
+    matches!(
+        key,
+        Key::ShiftLeft
+            | Key::ShiftRight
+            | Key::ControlLeft
+            | Key::ControlRight
+            | Key::Alt
+            | Key::AltGr
+            | Key::MetaLeft
+            | Key::MetaRight
+    )
+}
+
+fn key_to_xkb_keycode(key: Key) -> Option<xkb::Keycode> {
+    key_to_evdev_key(key).map(|code| xkb::Keycode::new(code as u32 + X_KEYCODE_OFFSET))
+}
+
+pub(super) fn grab(callback: Box<dyn Fn(&Event) -> bool>) -> Result<(), String> {
+    check_grab_access()?;
+    rdev::grab(move |event: Event| if callback(&event) { None } else { Some(event) })
+        .map_err(|err| format!("{err:?}"))
+}
+
+fn check_grab_access() -> Result<(), String> {
+    // rdev gives up on the whole grab if any device is unreadable, so check them all up front.
+    let devices_readable = std::fs::read_dir("/dev/input")
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+                .all(|entry| File::open(entry.path()).is_ok())
+        })
+        .unwrap_or(false);
+    let uinput_writable = OpenOptions::new().write(true).open("/dev/uinput").is_ok();
+
+    if devices_readable && uinput_writable {
+        return Ok(());
+    }
+
+    Err(
+        "BloxChat needs read access to /dev/input and write access to /dev/uinput to keep \
+         chat keys out of Roblox. Join the input group (sudo usermod -aG input $USER), make \
+         sure /dev/uinput belongs to that group, then log out and back in."
+            .to_string(),
+    )
+}
+
+// Neither rdev's evdev grab nor its X11 listener can be stopped, so a running one is never retried.
+pub(super) fn current_listener_thread() -> Option<u32> {
+    None
+}
+
+pub(super) fn stop_listener_thread(_thread_id: u32) {}
+
+pub(super) fn is_app_window_foreground(app: &AppHandle) -> bool {
+    app.webview_windows()
+        .values()
+        .any(|window| window.is_focused().unwrap_or(false))
+}
+
+// X11 does not expose which widget has focus, let alone whether it is a password field, and the
+// settings page tells the player so.
+pub(super) const DETECTS_PASSWORD_FIELDS: bool = false;
+
+pub(super) fn is_password_field_focused() -> bool {
+    false
+}
+
+pub(super) fn watch_foreground(on_change: Box<dyn Fn() + Send + Sync>) {
+    // X11 only announces _NET_ACTIVE_WINDOW changes to clients selecting root PropertyNotify, so poll it.
+    std::thread::spawn(move || {
+        let mut last_active = None;
+        loop {
+            let active = crate::xdisplay::with_display(|display| unsafe {
+                crate::xdisplay::active_window(display)
+            })
+            .flatten();
+            if active != last_active {
+                last_active = active;
+                on_change();
+            }
+            std::thread::sleep(FOREGROUND_POLL_INTERVAL);
+        }
+    });
+}
+
+// WebKitGTK composes through the GTK input method inside our own process, so there is nothing to bridge.
+pub(super) fn attach_ime(_app: &AppHandle, _on_event: ImeEventHandler) -> Result<bool, String> {
+    Ok(false)
+}
+
+pub(super) fn detach_ime(_app: &AppHandle) {}
+
+pub(super) fn focus_ime(_app: &AppHandle) -> Result<(), String> {
+    Ok(())
+}
+
+pub(super) fn place_ime_candidates(_app: &AppHandle, _caret: ImeCaret) -> Result<(), String> {
+    Ok(())
+}
+
+pub(super) fn can_inject_key(key: Key) -> bool {
+    key_to_evdev_key(key).is_some()
+}
+
+fn button_to_evdev_key(button: Button) -> Option<EV_KEY> {
+    let code = match button {
+        Button::Left => EV_KEY::BTN_LEFT,
+        Button::Right => EV_KEY::BTN_RIGHT,
+        Button::Middle => EV_KEY::BTN_MIDDLE,
+        Button::Unknown(1) => EV_KEY::BTN_SIDE,
+        Button::Unknown(2) => EV_KEY::BTN_EXTRA,
+        _ => return None,
+    };
+
+    Some(code)
+}
+
+fn key_to_evdev_key(key: Key) -> Option<EV_KEY> {
+    keys::key_spec(key).and_then(|spec| int_to_ev_key(spec.evdev as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(name: &str) -> XkbKeyboardLayout {
+        let mut layout = XkbKeyboardLayout::with_locale(&"en_US.UTF-8".into());
+        layout.keymap = xkb::Keymap::new_from_names(
+            &layout.context,
+            "",
+            "",
+            name,
+            "",
+            None,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        );
+        assert!(layout.keymap.is_some(), "no xkb keymap for {name}");
+        layout
+    }
+
+    fn press(layout: &mut XkbKeyboardLayout, held: &[Key], key: Key) -> Option<KeyText> {
+        layout.key_text(key, &held.iter().copied().collect(), false)
+    }
+
+    fn typed(text: &str) -> Option<KeyText> {
+        Some(KeyText::Typed(text.to_string()))
+    }
+
+    #[test]
+    fn keys_rdev_cannot_name_are_injectable_but_invisible_to_the_grab() {
+        // rdev's evdev grab returns no event for a key without a variant, so Unknown keys only
+        // ever come from the X11 listener, numbered by X keycode.
+        let unnamed: Vec<_> = KEY_TABLE
+            .iter()
+            .filter(|spec| matches!(spec.key, Key::Unknown(_)))
+            .collect();
+        assert!(unnamed.iter().any(|spec| spec.code == "F13"));
+        assert!(unnamed.iter().any(|spec| spec.code == "MediaPlayPause"));
+        for spec in unnamed {
+            assert_eq!(
+                spec.key,
+                Key::Unknown(spec.evdev as u32 + X_KEYCODE_OFFSET),
+                "{}",
+                spec.code
+            );
+            assert!(can_inject_key(spec.key), "{}", spec.code);
+        }
+    }
+
+    #[test]
+    fn layout_tables_translate_physical_keys() {
+        let cases: &[(&str, &[Key], Key, &str)] = &[
+            ("us", &[], Key::KeyQ, "q"),
+            ("us", &[Key::ShiftLeft], Key::Num2, "@"),
+            ("fr", &[], Key::KeyQ, "a"),
+            ("fr", &[], Key::Num2, "é"),
+            ("fr", &[Key::ShiftLeft], Key::Num2, "2"),
+            ("fr", &[Key::AltGr], Key::Num0, "@"),
+            ("de", &[], Key::KeyY, "z"),
+            ("de", &[], Key::SemiColon, "ö"),
+            ("de", &[Key::AltGr], Key::KeyQ, "@"),
+            ("de", &[Key::AltGr], Key::KeyE, "€"),
+            ("ru", &[], Key::KeyF, "а"),
+            ("ru", &[Key::ShiftRight], Key::KeyF, "А"),
+        ];
+
+        for (name, held, key, expected) in cases {
+            assert_eq!(
+                press(&mut layout(name), held, *key),
+                typed(expected),
+                "{name} {held:?} {key:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn caps_lock_follows_the_layout() {
+        let mut azerty = layout("fr");
+        assert_eq!(
+            azerty.key_text(Key::KeyQ, &HashSet::new(), true),
+            typed("A")
+        );
+        assert_eq!(
+            azerty.key_text(Key::KeyQ, &HashSet::from([Key::ShiftLeft]), true),
+            typed("a")
+        );
+    }
+
+    #[test]
+    fn dead_keys_compose_with_the_next_press() {
+        let mut azerty = layout("fr");
+        assert_eq!(
+            press(&mut azerty, &[], Key::LeftBracket),
+            Some(KeyText::Dead)
+        );
+        assert_eq!(press(&mut azerty, &[], Key::KeyE), typed("ê"));
+        assert_eq!(
+            press(&mut azerty, &[], Key::LeftBracket),
+            Some(KeyText::Dead)
+        );
+        assert_eq!(press(&mut azerty, &[], Key::KeyQ), typed("â"));
+
+        let mut qwertz = layout("de");
+        assert_eq!(press(&mut qwertz, &[], Key::Equal), Some(KeyText::Dead));
+        assert_eq!(press(&mut qwertz, &[Key::ShiftLeft], Key::KeyE), typed("É"));
+
+        // Shift on its own does not interrupt the sequence.
+        assert_eq!(
+            press(&mut azerty, &[], Key::LeftBracket),
+            Some(KeyText::Dead)
+        );
+        assert_eq!(
+            press(&mut azerty, &[], Key::ShiftLeft),
+            Some(KeyText::Empty)
+        );
+        assert_eq!(press(&mut azerty, &[Key::ShiftLeft], Key::KeyO), typed("Ô"));
+    }
+
+    #[test]
+    fn unreadable_layout_defers_to_the_event_text() {
+        let mut layout = XkbKeyboardLayout::with_locale(&"C".into());
+        assert_eq!(layout.key_text(Key::KeyA, &HashSet::new(), false), None);
+    }
+
+    #[test]
+    fn every_table_key_has_an_evdev_code() {
+        for spec in KEY_TABLE {
+            let code = key_to_evdev_key(spec.key).unwrap();
+            assert_eq!(code as u16, spec.evdev, "{}", spec.code);
+        }
+        assert_eq!(
+            key_to_xkb_keycode(keys::key_from_code("F13").unwrap()),
+            Some(xkb::Keycode::new(191))
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use super::hook::{apply_decision, decide_event, EventOutcome};
+use super::*;
+
+#[derive(Default)]
+pub(super) struct RecordingInjector {
+    pub(super) sent: Mutex<Vec<InjectedInput>>,
+    pub(super) echoes: Mutex<Vec<InjectedInput>>,
+    // Keys the OS reports as up; everything else reads as held.
+    pub(super) released_by_os: Mutex<HashSet<Key>>,
+    // Runs once, just before the next injection goes out.
+    pub(super) before_next_send: Mutex<Option<Box<dyn FnOnce() + Send>>>,
+}
+
+impl RecordingInjector {
+    fn record(&self, input: InjectedInput) {
+        let before_send = self.before_next_send.lock().unwrap().take();
+        if let Some(before_send) = before_send {
+            before_send();
+        }
+        self.sent.lock().unwrap().push(input);
+        self.echoes.lock().unwrap().push(input);
+    }
+
+    pub(super) fn sent(&self) -> Vec<InjectedInput> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl KeyInjector for RecordingInjector {
+    fn send_key(&self, key: Key, up: bool) {
+        self.record(InjectedInput::Key { key, up });
+    }
+
+    fn send_button(&self, button: Button, up: bool) {
+        self.record(InjectedInput::Button { button, up });
+    }
+
+    fn caps_lock_enabled(&self) -> bool {
+        false
+    }
+
+    fn is_key_down(&self, key: Key) -> Option<bool> {
+        Some(!self.released_by_os.lock().unwrap().contains(&key))
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn take_echo(&self, input: InjectedInput) -> bool {
+        let mut echoes = self.echoes.lock().unwrap();
+        match echoes.iter().position(|echo| *echo == input) {
+            Some(index) => {
+                echoes.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// The hook and worker halves back to back, as if the worker kept up with every event.
+pub(super) fn process_event(
+    state: &InputCaptureState,
+    hook: &mut HookDecider,
+    event: &Event,
+    context: ForegroundContext,
+    suppression_enabled: bool,
+) -> EventOutcome {
+    let decision = decide_event(state, hook, event, context, suppression_enabled);
+    apply_decision(state, event, context, decision)
+}
+
+pub(super) struct Harness {
+    pub(super) state: InputCaptureState,
+    pub(super) hook: Rc<RefCell<HookDecider>>,
+    pub(super) injector: Arc<RecordingInjector>,
+}
+
+impl Harness {
+    pub(super) fn new() -> Self {
+        let injector = Arc::new(RecordingInjector::default());
+        let state = InputCaptureState::with_injector(injector.clone());
+        Self {
+            hook: Rc::new(RefCell::new(HookDecider::attach(&state).unwrap())),
+            state,
+            injector,
+        }
+    }
+
+    pub(super) fn send_in(
+        &self,
+        context: ForegroundContext,
+        event_type: EventType,
+    ) -> EventOutcome {
+        process_event(
+            &self.state,
+            &mut self.hook.borrow_mut(),
+            &event(event_type),
+            context,
+            true,
+        )
+    }
+
+    pub(super) fn press(&self, key: Key) -> EventOutcome {
+        self.send_in(ForegroundContext::Roblox, EventType::KeyPress(key))
+    }
+
+    pub(super) fn release(&self, key: Key) -> EventOutcome {
+        self.send_in(ForegroundContext::Roblox, EventType::KeyRelease(key))
+    }
+
+    // Feeds every injection back through the hook, as Windows does with SendInput.
+    pub(super) fn deliver_echoes(&self) {
+        let echoes = self.injector.echoes.lock().unwrap().clone();
+        for input in echoes {
+            let event_type = match input {
+                InjectedInput::Key { key, up: false } => EventType::KeyPress(key),
+                InjectedInput::Key { key, up: true } => EventType::KeyRelease(key),
+                InjectedInput::Button { button, up: false } => EventType::ButtonPress(button),
+                InjectedInput::Button { button, up: true } => EventType::ButtonRelease(button),
+            };
+            let outcome = self.send_in(ForegroundContext::Roblox, event_type);
+            assert!(!outcome.suppress && outcome.key_event.is_none());
+        }
+        assert!(self.injector.echoes.lock().unwrap().is_empty());
+    }
+
+    pub(super) fn physical_down(&self) -> HashSet<Key> {
+        self.state.lock().unwrap().physical_down.clone()
+    }
+
+    pub(super) fn is_active(&self) -> bool {
+        self.state.lock().unwrap().is_capturing()
+    }
+
+    // Latched releases are sent from a delayed retry thread.
+    pub(super) fn wait_for_sent(&self, input: InjectedInput, count: usize) {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while self.sent_count(input) < count {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "{input:?} was not sent"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    pub(super) fn sent_count(&self, input: InjectedInput) -> usize {
+        self.injector
+            .sent()
+            .into_iter()
+            .filter(|sent| *sent == input)
+            .count()
+    }
+}
+
+pub(super) fn event(event_type: EventType) -> Event {
+    Event {
+        time: std::time::SystemTime::now(),
+        name: None,
+        event_type,
+    }
+}
+
+pub(super) fn key_input(key: Key, up: bool) -> InjectedInput {
+    InjectedInput::Key { key, up }
+}
+
+pub(super) struct TableLayout(pub(super) Vec<(Key, KeyText)>);
+
+impl KeyboardLayout for TableLayout {
+    fn refresh(&mut self) {}
+
+    fn key_text(&mut self, key: Key, _down_keys: &HashSet<Key>, _caps: bool) -> Option<KeyText> {
+        self.0
+            .iter()
+            .find(|(mapped, _)| *mapped == key)
+            .map(|(_, text)| text.clone())
+    }
+}
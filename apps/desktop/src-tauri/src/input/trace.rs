@@ -0,0 +1,312 @@
+use super::hook::EventOutcome;
+use super::{
+    event_timestamp_ms, key_to_code, CaptureState, ForegroundContext, InputCaptureState, KeyPhase,
+    MODIFIER_KEYS,
+};
+use anyhow::Result;
+use rdev::{Button, EventType, Key};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const INPUT_TRACE_CAPACITY: usize = 512;
+const REDACTED: &str = "[redacted]";
+
+// Off by default, so recording is a flag check when disabled.
+#[derive(Default)]
+pub(super) struct InputTrace {
+    enabled: AtomicBool,
+    entries: Mutex<VecDeque<InputTraceEntry>>,
+}
+
+impl InputTrace {
+    pub(super) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn record(&self, entry: InputTraceEntry) {
+        let mut entries = match self.entries.lock() {
+            Ok(guard) => guard,
+            Err(err) => {
+                eprintln!("failed to lock input trace: {err}");
+                return;
+            }
+        };
+        if entries.len() == INPUT_TRACE_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InputTraceEntry {
+    pub(super) timestamp_ms: i64,
+    pub(super) code: String,
+    pub(super) phase: KeyPhase,
+    pub(super) text: Option<String>,
+    pub(super) context: ForegroundContext,
+    pub(super) capture: CaptureState,
+    pub(super) echo: bool,
+    pub(super) can_suppress: bool,
+    pub(super) was_down_at_capture: bool,
+    pub(super) latched: bool,
+    pub(super) suppressed: bool,
+    pub(super) released_latches: Vec<String>,
+    pub(super) reinjected: Option<String>,
+    pub(super) replayed: Vec<String>,
+    pub(super) hook_latency_us: u64,
+    // A key that types text, so its code alone can give away what was typed.
+    #[serde(skip)]
+    pub(super) text_key: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InputTraceDump {
+    enabled: bool,
+    capacity: usize,
+    entries: Vec<InputTraceEntry>,
+}
+
+pub(crate) fn get_input_trace_enabled(state: &InputCaptureState) -> bool {
+    state.trace.is_enabled()
+}
+
+// Session only: tracing is a debugging aid and should not survive a restart.
+pub(crate) fn set_input_trace_enabled(state: &InputCaptureState, enabled: bool) -> bool {
+    state.trace.enabled.store(enabled, Ordering::Relaxed);
+    enabled
+}
+
+pub(crate) fn dump_input_trace(
+    state: &InputCaptureState,
+    include_text: bool,
+) -> Result<InputTraceDump> {
+    let entries = state
+        .trace
+        .entries
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input trace: {err}"))?;
+    let entries = entries
+        .iter()
+        .cloned()
+        .map(|entry| {
+            if include_text {
+                return entry;
+            }
+            InputTraceEntry {
+                code: if entry.text_key {
+                    REDACTED.to_string()
+                } else {
+                    entry.code
+                },
+                text: entry.text.map(|_| REDACTED.to_string()),
+                ..entry
+            }
+        })
+        .collect();
+
+    Ok(InputTraceDump {
+        enabled: state.trace.is_enabled(),
+        capacity: INPUT_TRACE_CAPACITY,
+        entries,
+    })
+}
+
+pub(crate) fn clear_input_trace(state: &InputCaptureState) -> Result<()> {
+    state
+        .trace
+        .entries
+        .lock()
+        .map_err(|err| anyhow::anyhow!("lock input trace: {err}"))?
+        .clear();
+    Ok(())
+}
+
+pub(super) fn record_trace(
+    state: &InputCaptureState,
+    entry: Option<InputTraceEntry>,
+    hook_latency: Duration,
+) {
+    if let Some(mut entry) = entry {
+        entry.hook_latency_us = hook_latency.as_micros() as u64;
+        state.trace.record(entry);
+    }
+}
+
+// Fills in what the outcome alone records; the caller adds the state it decided from.
+pub(super) fn trace_entry(
+    event_type: &EventType,
+    outcome: &EventOutcome,
+) -> Option<InputTraceEntry> {
+    let (code, phase) = match *event_type {
+        EventType::KeyPress(key) => (key_to_code(key), KeyPhase::Down),
+        EventType::KeyRelease(key) => (key_to_code(key), KeyPhase::Up),
+        EventType::ButtonPress(button) => (button_to_code(button), KeyPhase::Down),
+        EventType::ButtonRelease(button) => (button_to_code(button), KeyPhase::Up),
+        _ => return None,
+    };
+    let text_key = match *event_type {
+        EventType::KeyPress(key) | EventType::KeyRelease(key) => is_text_key(key),
+        _ => false,
+    };
+
+    Some(InputTraceEntry {
+        timestamp_ms: event_timestamp_ms(),
+        code,
+        phase,
+        text: outcome
+            .key_event
+            .as_ref()
+            .and_then(|key_event| key_event.text.clone()),
+        context: ForegroundContext::Other,
+        capture: CaptureState::Idle,
+        echo: false,
+        can_suppress: false,
+        was_down_at_capture: false,
+        latched: false,
+        suppressed: outcome.suppress,
+        released_latches: outcome
+            .keys_to_release
+            .iter()
+            .map(|key| key_to_code(*key))
+            .chain(
+                outcome
+                    .buttons_to_release
+                    .iter()
+                    .map(|b| button_to_code(*b)),
+            )
+            .collect(),
+        reinjected: outcome
+            .reinject_key
+            .map(key_to_code)
+            .or_else(|| outcome.reinject_button.map(button_to_code)),
+        replayed: outcome
+            .passthrough_replay
+            .iter()
+            .map(|key| key_to_code(*key))
+            .collect(),
+        hook_latency_us: 0,
+        text_key,
+    })
+}
+
+fn button_to_code(button: Button) -> String {
+    format!("Mouse{button:?}")
+}
+
+// Keys that stand for text rather than editing or shortcuts.
+fn is_text_key(key: Key) -> bool {
+    if MODIFIER_KEYS.contains(&key) {
+        return false;
+    }
+    if matches!(
+        key,
+        Key::Return
+            | Key::KpReturn
+            | Key::Escape
+            | Key::Tab
+            | Key::Backspace
+            | Key::Delete
+            | Key::CapsLock
+            | Key::UpArrow
+            | Key::DownArrow
+            | Key::LeftArrow
+            | Key::RightArrow
+            | Key::Home
+            | Key::End
+            | Key::PageUp
+            | Key::PageDown
+    ) {
+        return false;
+    }
+
+    let code = key_to_code(key);
+    !(code.starts_with('F') && code[1..].parse::<u8>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::testing::{Harness, TableLayout};
+    use crate::input::{begin_chat_capture, ChatInputMode, ChatKeyPersistenceMode, KeyText};
+
+    #[test]
+    fn input_trace_records_decisions_and_redacts_typed_keys() {
+        let harness = Harness::new();
+        assert!(harness.press(Key::KeyW).trace.is_none());
+
+        set_input_trace_enabled(&harness.state, true);
+        harness.state.lock().unwrap().layout = Some(Box::new(TableLayout(vec![(
+            Key::KeyH,
+            KeyText::Typed("h".to_string()),
+        )])));
+        begin_chat_capture(
+            &harness.state,
+            ChatKeyPersistenceMode::Wasd,
+            ChatInputMode::Focusless,
+        )
+        .unwrap();
+        for outcome in [harness.release(Key::KeyW), harness.press(Key::KeyH)] {
+            record_trace(&harness.state, outcome.trace, Duration::from_micros(40));
+        }
+
+        let dump = dump_input_trace(&harness.state, false).unwrap();
+        let [release, typed] = &dump.entries[..] else {
+            panic!("expected two trace entries");
+        };
+        assert_eq!(release.code, REDACTED);
+        assert!(release.latched && release.was_down_at_capture && release.suppressed);
+        assert!(release.can_suppress && !release.echo);
+        assert_eq!(typed.code, REDACTED);
+        assert_eq!(typed.text.as_deref(), Some(REDACTED));
+
+        let dump = dump_input_trace(&harness.state, true).unwrap();
+        assert_eq!(dump.entries[0].code, "KeyW");
+        assert_eq!(dump.entries[1].code, "KeyH");
+        assert_eq!(dump.entries[1].text.as_deref(), Some("h"));
+
+        clear_input_trace(&harness.state).unwrap();
+        assert!(dump_input_trace(&harness.state, true)
+            .unwrap()
+            .entries
+            .is_empty());
+    }
+
+    #[test]
+    fn input_trace_ignores_other_windows_and_redacts_keys_outside_capture() {
+        let harness = Harness::new();
+        set_input_trace_enabled(&harness.state, true);
+
+        for context in [ForegroundContext::Secure, ForegroundContext::Other] {
+            for event_type in [
+                EventType::KeyPress(Key::KeyP),
+                EventType::KeyRelease(Key::KeyP),
+            ] {
+                let outcome = harness.send_in(context, event_type);
+                assert!(outcome.trace.is_none());
+                record_trace(&harness.state, outcome.trace, Duration::ZERO);
+            }
+        }
+        assert!(dump_input_trace(&harness.state, true)
+            .unwrap()
+            .entries
+            .is_empty());
+
+        for key in [Key::KeyP, Key::F5] {
+            let outcome = harness.press(key);
+            record_trace(&harness.state, outcome.trace, Duration::ZERO);
+        }
+        let dump = dump_input_trace(&harness.state, false).unwrap();
+        let codes: Vec<_> = dump
+            .entries
+            .iter()
+            .map(|entry| entry.code.as_str())
+            .collect();
+        assert_eq!(codes, [REDACTED, "F5"]);
+    }
+}
@@ -0,0 +1,782 @@
+use super::keys;
+use super::{
+    ImeCaret, ImeEvent, ImeEventHandler, InjectedInput, InjectionLedger, KeyInjector, KeyText,
+    KeyboardLayout,
+};
+use rdev::{Button, Event, EventType, Key};
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime};
+use tauri::{AppHandle, Manager};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::ClientToScreen;
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Accessibility::{SetWinEventHook, HWINEVENTHOOK};
+use windows::Win32::UI::Input::Ime::{
+    ImmAssociateContextEx, ImmGetCandidateWindow, ImmGetCompositionStringW, ImmGetContext,
+    ImmReleaseContext, ImmSetCandidateWindow, ImmSetCompositionWindow, CANDIDATEFORM, CFS_EXCLUDE,
+    CFS_POINT, COMPOSITIONFORM, GCS_COMPSTR, GCS_CURSORPOS, GCS_RESULTSTR, HIMC, IACE_CHILDREN,
+    IACE_DEFAULT, IME_COMPOSITION_STRING, IMN_CHANGECANDIDATE, IMN_OPENCANDIDATE,
+    ISC_SHOWUICOMPOSITIONWINDOW,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, GetKeyState, GetKeyboardLayout, MapVirtualKeyExW, MapVirtualKeyW, SendInput,
+    SetFocus, ToUnicodeEx, HKL, INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT,
+    KEYBD_EVENT_FLAGS, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, MAPVK_VK_TO_VSC,
+    MAPVK_VK_TO_VSC_EX, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN,
+    MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_XDOWN,
+    MOUSEEVENTF_XUP, MOUSEINPUT, MOUSE_EVENT_FLAGS, VIRTUAL_KEY,
+};
+use windows::Win32::UI::Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, GetForegroundWindow, GetGUIThreadInfo, GetMessageW, GetWindowLongW,
+    GetWindowThreadProcessId, PostThreadMessageW, SetWindowsHookExW, UnhookWindowsHookEx,
+    ES_PASSWORD, EVENT_OBJECT_FOCUS, EVENT_SYSTEM_FOREGROUND, GUITHREADINFO, GWL_STYLE, HC_ACTION,
+    KBDLLHOOKSTRUCT, LLKHF_EXTENDED, LLKHF_INJECTED, LLMHF_INJECTED, MSG, MSLLHOOKSTRUCT,
+    WHEEL_DELTA, WH_KEYBOARD_LL, WH_MOUSE_LL, WINEVENT_OUTOFCONTEXT, WM_CHAR, WM_IME_COMPOSITION,
+    WM_IME_ENDCOMPOSITION, WM_IME_NOTIFY, WM_IME_SETCONTEXT, WM_IME_STARTCOMPOSITION, WM_KEYDOWN,
+    WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL,
+    WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_QUIT, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
+    WM_XBUTTONDOWN, WM_XBUTTONUP,
+};
+
+// "BCHT" in dwExtraInfo marks SendInput events as ours; the grab hook reads it back.
+const INJECTION_MARKER: usize = 0x4243_4854;
+
+const IME_SUBCLASS_ID: usize = INJECTION_MARKER;
+const MAIN_WINDOW_LABEL: &str = "main";
+// wFlags bit 2: leave the calling thread's keyboard state, and with it any dead key, untouched.
+const TO_UNICODE_KEEP_STATE: u32 = 0x4;
+const VK_SPACE: u32 = 0x20;
+
+static FOREGROUND_CHANGED: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+static IME_EVENTS: Mutex<Option<ImeEventHandler>> = Mutex::new(None);
+
+type GrabCallback = Box<dyn Fn(&Event) -> bool>;
+
+thread_local! {
+    // WM_CHAR delivers characters outside the BMP as two surrogate messages.
+    static PENDING_HIGH_SURROGATE: Cell<Option<u16>> = const { Cell::new(None) };
+    static GRAB_CALLBACK: RefCell<Option<GrabCallback>> = const { RefCell::new(None) };
+    // Whether the event the grab callback is deciding carries our marker; None outside the grab hook.
+    static HOOK_EVENT_MARKED: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+#[derive(Default)]
+pub(super) struct Win32KeyInjector {
+    ledger: InjectionLedger,
+}
+
+impl KeyInjector for Win32KeyInjector {
+    fn send_key(&self, key: Key, up: bool) {
+        let inputs = key_injection_inputs(key, up);
+        if inputs.is_empty() {
+            return;
+        }
+
+        // Record before sending: the hook can fire before SendInput returns.
+        self.ledger
+            .record(InjectedInput::Key { key, up }, inputs.len(), Instant::now());
+        for input in inputs {
+            send_input(input);
+        }
+    }
+
+    fn send_button(&self, button: Button, up: bool) {
+        let Some((flags, mouse_data)) = button_to_mouse_flags(button, up) else {
+            return;
+        };
+
+        self.ledger
+            .record(InjectedInput::Button { button, up }, 1, Instant::now());
+        send_input(INPUT {
+            r#type: INPUT_MOUSE,
+            Anonymous: INPUT_0 {
+                mi: MOUSEINPUT {
+                    dx: 0,
+                    dy: 0,
+                    mouseData: mouse_data,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: INJECTION_MARKER,
+                },
+            },
+        });
+    }
+
+    fn caps_lock_enabled(&self) -> bool {
+        // 0x14 = VK_CAPITAL. The low-order bit of GetKeyState indicates toggle state.
+        unsafe { GetKeyState(0x14) & 1 != 0 }
+    }
+
+    fn is_key_down(&self, key: Key) -> Option<bool> {
+        let vk = key_to_virtual_key(key)?;
+        // The high-order bit of GetAsyncKeyState is set while the key is down.
+        Some(unsafe { GetAsyncKeyState(vk.0 as i32) } < 0)
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn take_echo(&self, input: InjectedInput) -> bool {
+        let now = Instant::now();
+        match HOOK_EVENT_MARKED.get() {
+            Some(true) => {
+                self.ledger.consume(input, now);
+                true
+            }
+            // A real press of a key we just injected is not an echo, however close it comes.
+            Some(false) => false,
+            // rdev's passive listener hides dwExtraInfo, so only the ledger can tell.
+            None => self.ledger.consume(input, now),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Win32KeyboardLayout {
+    // ToUnicodeEx would keep this per thread, but refresh and key_text run on different threads,
+    // so the dead key lives here and is only replayed right before the press it combines with.
+    pending_dead_key: Option<DeadKey>,
+}
+
+struct DeadKey {
+    vk: u32,
+    scan_code: u32,
+    key_state: [u8; 256],
+}
+
+impl KeyboardLayout for Win32KeyboardLayout {
+    fn refresh(&mut self) {
+        self.pending_dead_key = None;
+    }
+
+    fn key_text(&mut self, key: Key, down_keys: &HashSet<Key>, caps: bool) -> Option<KeyText> {
+        let vk = key_to_virtual_key(key)?.0 as u32;
+        let key_state = keyboard_state(down_keys, caps);
+
+        unsafe {
+            let layout = foreground_layout();
+            let scan_code = MapVirtualKeyExW(vk, MAPVK_VK_TO_VSC, Some(layout));
+            let mut buffer = [0u16; 8];
+            let count = match self.pending_dead_key.take() {
+                Some(dead) => {
+                    ToUnicodeEx(
+                        dead.vk,
+                        dead.scan_code,
+                        &dead.key_state,
+                        &mut buffer,
+                        0,
+                        Some(layout),
+                    );
+                    let count =
+                        ToUnicodeEx(vk, scan_code, &key_state, &mut buffer, 0, Some(layout));
+                    if count <= 0 {
+                        flush_dead_key(layout);
+                    }
+                    // A modifier types nothing, so the dead key still waits for the next press.
+                    if count == 0 {
+                        self.pending_dead_key = Some(dead);
+                    }
+                    count
+                }
+                None => ToUnicodeEx(
+                    vk,
+                    scan_code,
+                    &key_state,
+                    &mut buffer,
+                    TO_UNICODE_KEEP_STATE,
+                    Some(layout),
+                ),
+            };
+            if count < 0 {
+                self.pending_dead_key = Some(DeadKey {
+                    vk,
+                    scan_code,
+                    key_state,
+                });
+            }
+
+            Some(match count {
+                ..0 => KeyText::Dead,
+                0 => KeyText::Empty,
+                count => KeyText::Typed(String::from_utf16_lossy(
+                    &buffer[..(count as usize).min(buffer.len())],
+                )),
+            })
+        }
+    }
+}
+
+// Roblox's input thread owns the layout the player is typing with.
+unsafe fn foreground_layout() -> HKL {
+    let thread_id = GetWindowThreadProcessId(GetForegroundWindow(), None);
+    GetKeyboardLayout(thread_id)
+}
+
+// A bare space completes whatever dead key the replay left in this thread's keyboard state.
+unsafe fn flush_dead_key(layout: HKL) {
+    let scan_code = MapVirtualKeyExW(VK_SPACE, MAPVK_VK_TO_VSC, Some(layout));
+    let mut buffer = [0u16; 8];
+    ToUnicodeEx(VK_SPACE, scan_code, &[0; 256], &mut buffer, 0, Some(layout));
+}
+
+// The keys are withheld from the OS during capture, so build the state ToUnicodeEx sees ourselves.
+fn keyboard_state(down_keys: &HashSet<Key>, caps: bool) -> [u8; 256] {
+    const DOWN: u8 = 0x80;
+    const VK_SHIFT: usize = 0x10;
+    const VK_CONTROL: usize = 0x11;
+    const VK_MENU: usize = 0x12;
+    const VK_CAPITAL: usize = 0x14;
+
+    let mut state = [0u8; 256];
+    for key in down_keys {
+        if let Some(vk) = key_to_virtual_key(*key) {
+            state[vk.0 as usize] = DOWN;
+        }
+        match key {
+            Key::ShiftLeft | Key::ShiftRight => state[VK_SHIFT] = DOWN,
+            Key::ControlLeft | Key::ControlRight => state[VK_CONTROL] = DOWN,
+            Key::Alt => state[VK_MENU] = DOWN,
+            // AltGr is right Alt with an implied Ctrl.
+            Key::AltGr => {
+                state[VK_MENU] = DOWN;
+                state[VK_CONTROL] = DOWN;
+            }
+            _ => {}
+        }
+    }
+    if caps {
+        state[VK_CAPITAL] |= 1;
+    }
+
+    state
+}
+
+// Our own low-level hooks instead of rdev's, which drop the flags and dwExtraInfo we need for echoes.
+pub(super) fn grab(callback: GrabCallback) -> Result<(), String> {
+    GRAB_CALLBACK.set(Some(callback));
+    let result = unsafe { run_grab_hooks() };
+    GRAB_CALLBACK.set(None);
+    result
+}
+
+unsafe fn run_grab_hooks() -> Result<(), String> {
+    let keyboard = SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook), None, 0)
+        .map_err(|err| format!("KeyHookError({err})"))?;
+    let mouse = match SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook), None, 0) {
+        Ok(mouse) => mouse,
+        Err(err) => {
+            let _ = UnhookWindowsHookEx(keyboard);
+            return Err(format!("MouseHookError({err})"));
+        }
+    };
+
+    // Low-level hooks run inside this loop; stop_listener_thread ends it with WM_QUIT.
+    let mut message = MSG::default();
+    while GetMessageW(&mut message, None, 0, 0).as_bool() {}
+
+    let _ = UnhookWindowsHookEx(mouse);
+    let _ = UnhookWindowsHookEx(keyboard);
+    Ok(())
+}
+
+unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let info = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        let key =
+            keys::key_from_virtual_key(info.vkCode as u16, info.flags.contains(LLKHF_EXTENDED));
+        let event_type = match wparam.0 as u32 {
+            WM_KEYDOWN | WM_SYSKEYDOWN => Some(EventType::KeyPress(key)),
+            WM_KEYUP | WM_SYSKEYUP => Some(EventType::KeyRelease(key)),
+            _ => None,
+        };
+        let marked = info.flags.contains(LLKHF_INJECTED) && info.dwExtraInfo == INJECTION_MARKER;
+        if event_type.is_some_and(|event_type| dispatch_grab_event(event_type, marked)) {
+            return LRESULT(1);
+        }
+    }
+    CallNextHookEx(None, code, wparam, lparam)
+}
+
+unsafe extern "system" fn mouse_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let info = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+        // The wheel delta and the X button number both live in the high word.
+        let high_word = (info.mouseData >> 16) as u16;
+        let wheel = (high_word as i16 as i32 / WHEEL_DELTA as i32) as i64;
+        let event_type = match wparam.0 as u32 {
+            WM_LBUTTONDOWN => Some(EventType::ButtonPress(Button::Left)),
+            WM_LBUTTONUP => Some(EventType::ButtonRelease(Button::Left)),
+            WM_RBUTTONDOWN => Some(EventType::ButtonPress(Button::Right)),
+            WM_RBUTTONUP => Some(EventType::ButtonRelease(Button::Right)),
+            WM_MBUTTONDOWN => Some(EventType::ButtonPress(Button::Middle)),
+            WM_MBUTTONUP => Some(EventType::ButtonRelease(Button::Middle)),
+            WM_XBUTTONDOWN => Some(EventType::ButtonPress(Button::Unknown(high_word as u8))),
+            WM_XBUTTONUP => Some(EventType::ButtonRelease(Button::Unknown(high_word as u8))),
+            WM_MOUSEMOVE => Some(EventType::MouseMove {
+                x: info.pt.x as f64,
+                y: info.pt.y as f64,
+            }),
+            WM_MOUSEWHEEL => Some(EventType::Wheel {
+                delta_x: 0,
+                delta_y: wheel,
+            }),
+            WM_MOUSEHWHEEL => Some(EventType::Wheel {
+                delta_x: wheel,
+                delta_y: 0,
+            }),
+            _ => None,
+        };
+        let marked = info.flags & LLMHF_INJECTED != 0 && info.dwExtraInfo == INJECTION_MARKER;
+        if event_type.is_some_and(|event_type| dispatch_grab_event(event_type, marked)) {
+            return LRESULT(1);
+        }
+    }
+    CallNextHookEx(None, code, wparam, lparam)
+}
+
+fn dispatch_grab_event(event_type: EventType, marked: bool) -> bool {
+    let event = Event {
+        time: SystemTime::now(),
+        // The keyboard layout derives text itself, so the event never needs rdev's guess.
+        name: None,
+        event_type,
+    };
+
+    HOOK_EVENT_MARKED.set(Some(marked));
+    let suppress = GRAB_CALLBACK
+        .with_borrow(|callback| callback.as_ref().is_some_and(|callback| callback(&event)));
+    HOOK_EVENT_MARKED.set(None);
+    suppress
+}
+
+pub(super) fn current_listener_thread() -> Option<u32> {
+    Some(unsafe { GetCurrentThreadId() })
+}
+
+pub(super) fn stop_listener_thread(thread_id: u32) {
+    // Ending the passive listener's message loop lets Windows drop its hooks before we grab again.
+    unsafe {
+        let _ = PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+    }
+}
+
+pub(super) fn is_app_window_foreground(app: &AppHandle) -> bool {
+    unsafe {
+        let foreground = GetForegroundWindow();
+        if foreground.0 == std::ptr::null_mut() {
+            return false;
+        }
+
+        for window in app.webview_windows().values() {
+            let Ok(hwnd) = window.hwnd() else {
+                continue;
+            };
+
+            if hwnd.0 == foreground.0 {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+pub(super) const DETECTS_PASSWORD_FIELDS: bool = true;
+
+pub(super) fn is_password_field_focused() -> bool {
+    // Only classic edit controls expose ES_PASSWORD; browsers and custom UIs fall under Other.
+    unsafe {
+        let foreground = GetForegroundWindow();
+        if foreground.0.is_null() {
+            return false;
+        }
+
+        let thread_id = GetWindowThreadProcessId(foreground, None);
+        let mut info = GUITHREADINFO {
+            cbSize: std::mem::size_of::<GUITHREADINFO>() as u32,
+            ..Default::default()
+        };
+        if GetGUIThreadInfo(thread_id, &mut info).is_err() || info.hwndFocus.0.is_null() {
+            return false;
+        }
+
+        GetWindowLongW(info.hwndFocus, GWL_STYLE) & ES_PASSWORD != 0
+    }
+}
+
+pub(super) fn watch_foreground(on_change: Box<dyn Fn() + Send + Sync>) {
+    if FOREGROUND_CHANGED.set(on_change).is_err() {
+        return;
+    }
+
+    // Out-of-context WinEvent callbacks arrive through the message loop of the thread that set the hook.
+    std::thread::spawn(|| unsafe {
+        let hook = SetWinEventHook(
+            EVENT_SYSTEM_FOREGROUND,
+            EVENT_SYSTEM_FOREGROUND,
+            None,
+            Some(foreground_changed),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT,
+        );
+        if hook.is_invalid() {
+            eprintln!("Failed to watch foreground window changes");
+            return;
+        }
+        // Focus moving onto or off a password field inside the same window changes the context too.
+        let focus_hook = SetWinEventHook(
+            EVENT_OBJECT_FOCUS,
+            EVENT_OBJECT_FOCUS,
+            None,
+            Some(foreground_changed),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT,
+        );
+        if focus_hook.is_invalid() {
+            eprintln!("Failed to watch keyboard focus changes");
+        }
+
+        let mut message = MSG::default();
+        while GetMessageW(&mut message, None, 0, 0).as_bool() {}
+    });
+}
+
+unsafe extern "system" fn foreground_changed(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    _hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    if let Some(on_change) = FOREGROUND_CHANGED.get() {
+        on_change();
+    }
+}
+
+// WebView2 composes in its own process where IMM cannot reach, so BloxChat's own window takes
+// keyboard focus during IME capture and relays the composition to the webview.
+pub(super) fn attach_ime(app: &AppHandle, on_event: ImeEventHandler) -> Result<bool, String> {
+    let window = app
+        .get_webview_window(MAIN_WINDOW_LABEL)
+        .ok_or_else(|| "the BloxChat window is not open".to_string())?;
+    *IME_EVENTS
+        .lock()
+        .map_err(|err| format!("lock IME bridge: {err}"))? = Some(on_event);
+
+    // Subclassing and focus both have to happen on the thread that owns the window.
+    app.run_on_main_thread(move || unsafe {
+        let Ok(hwnd) = window.hwnd() else {
+            return;
+        };
+        let hwnd = HWND(hwnd.0);
+        // Games often turn the IME off for their windows; make sure ours has its default context.
+        let _ = ImmAssociateContextEx(hwnd, HIMC::default(), IACE_CHILDREN | IACE_DEFAULT);
+        if !SetWindowSubclass(hwnd, Some(ime_subclass_proc), IME_SUBCLASS_ID, 0).as_bool() {
+            eprintln!("Failed to attach the IME bridge to the BloxChat window");
+            return;
+        }
+        let _ = SetFocus(Some(hwnd));
+    })
+    .map_err(|err| err.to_string())?;
+
+    Ok(true)
+}
+
+pub(super) fn detach_ime(app: &AppHandle) {
+    match IME_EVENTS.lock() {
+        Ok(mut events) => {
+            if events.take().is_none() {
+                return;
+            }
+        }
+        Err(err) => eprintln!("Failed to lock IME bridge: {err}"),
+    }
+
+    let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) else {
+        return;
+    };
+    let _ = app.run_on_main_thread(move || unsafe {
+        if let Ok(hwnd) = window.hwnd() {
+            let _ = RemoveWindowSubclass(HWND(hwnd.0), Some(ime_subclass_proc), IME_SUBCLASS_ID);
+        }
+    });
+}
+
+// Clicks land in the webview and take focus from the bridge; this hands it back.
+pub(super) fn focus_ime(app: &AppHandle) -> Result<(), String> {
+    let window = app
+        .get_webview_window(MAIN_WINDOW_LABEL)
+        .ok_or_else(|| "the BloxChat window is not open".to_string())?;
+    app.run_on_main_thread(move || unsafe {
+        if let Ok(hwnd) = window.hwnd() {
+            let _ = SetFocus(Some(HWND(hwnd.0)));
+        }
+    })
+    .map_err(|err| err.to_string())
+}
+
+pub(super) fn place_ime_candidates(app: &AppHandle, caret: ImeCaret) -> Result<(), String> {
+    let window = app
+        .get_webview_window(MAIN_WINDOW_LABEL)
+        .ok_or_else(|| "the BloxChat window is not open".to_string())?;
+    app.run_on_main_thread(move || unsafe {
+        let Ok(hwnd) = window.hwnd() else {
+            return;
+        };
+        let hwnd = HWND(hwnd.0);
+        let himc = ImmGetContext(hwnd);
+        if himc.is_invalid() {
+            return;
+        }
+
+        let position = POINT {
+            x: caret.x,
+            y: caret.y,
+        };
+        let composition = COMPOSITIONFORM {
+            dwStyle: CFS_POINT,
+            ptCurrentPos: position,
+            ..Default::default()
+        };
+        // Excluding the caret line keeps the candidate list from covering the chat box.
+        let candidates = CANDIDATEFORM {
+            dwIndex: 0,
+            dwStyle: CFS_EXCLUDE,
+            ptCurrentPos: position,
+            rcArea: RECT {
+                left: caret.x,
+                top: caret.y,
+                right: caret.x + 1,
+                bottom: caret.y + caret.height,
+            },
+        };
+        let _ = ImmSetCompositionWindow(himc, &composition);
+        let _ = ImmSetCandidateWindow(himc, &candidates);
+        let _ = ImmReleaseContext(hwnd, himc);
+    })
+    .map_err(|err| err.to_string())
+}
+
+unsafe extern "system" fn ime_subclass_proc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _subclass_id: usize,
+    _ref_data: usize,
+) -> LRESULT {
+    match message {
+        WM_IME_SETCONTEXT => {
+            // The chat box draws the preedit itself; the IME keeps drawing its candidate list.
+            let lparam = LPARAM(lparam.0 & !(ISC_SHOWUICOMPOSITIONWINDOW as isize));
+            return DefSubclassProc(hwnd, message, wparam, lparam);
+        }
+        WM_IME_STARTCOMPOSITION => return LRESULT(0),
+        WM_IME_COMPOSITION => {
+            let himc = ImmGetContext(hwnd);
+            if !himc.is_invalid() {
+                let changed = lparam.0 as u32;
+                if changed & GCS_RESULTSTR.0 != 0 {
+                    if let Some(text) = composition_string(himc, GCS_RESULTSTR) {
+                        emit_ime_event(ImeEvent::Commit { text });
+                    }
+                }
+                if changed & GCS_COMPSTR.0 != 0 {
+                    let text = composition_string(himc, GCS_COMPSTR).unwrap_or_default();
+                    let cursor = ImmGetCompositionStringW(himc, GCS_CURSORPOS, None, 0).max(0);
+                    emit_ime_event(ImeEvent::Preedit {
+                        text,
+                        cursor: cursor as usize,
+                    });
+                }
+                let _ = ImmReleaseContext(hwnd, himc);
+            }
+            // Handled here, so DefWindowProc must not turn the result into WM_IME_CHAR.
+            return LRESULT(0);
+        }
+        WM_IME_ENDCOMPOSITION => {
+            emit_ime_event(ImeEvent::End);
+            return LRESULT(0);
+        }
+        WM_IME_NOTIFY if matches!(wparam.0 as u32, IMN_OPENCANDIDATE | IMN_CHANGECANDIDATE) => {
+            if let Some(event) = candidate_window(hwnd) {
+                emit_ime_event(event);
+            }
+        }
+        WM_CHAR => {
+            if let Some(text) = char_message_text(wparam.0 as u16) {
+                emit_ime_event(ImeEvent::Commit { text });
+            }
+            return LRESULT(0);
+        }
+        _ => {}
+    }
+
+    DefSubclassProc(hwnd, message, wparam, lparam)
+}
+
+fn emit_ime_event(event: ImeEvent) {
+    if let Ok(events) = IME_EVENTS.lock() {
+        if let Some(on_event) = events.as_ref() {
+            on_event(event);
+        }
+    }
+}
+
+unsafe fn composition_string(himc: HIMC, kind: IME_COMPOSITION_STRING) -> Option<String> {
+    // The length comes back in bytes, not UTF-16 units.
+    let bytes = ImmGetCompositionStringW(himc, kind, None, 0);
+    if bytes <= 0 {
+        return None;
+    }
+
+    let mut buffer = vec![0u16; bytes as usize / 2];
+    ImmGetCompositionStringW(himc, kind, Some(buffer.as_mut_ptr().cast()), bytes as u32);
+    Some(String::from_utf16_lossy(&buffer))
+}
+
+unsafe fn candidate_window(hwnd: HWND) -> Option<ImeEvent> {
+    let himc = ImmGetContext(hwnd);
+    if himc.is_invalid() {
+        return None;
+    }
+
+    let mut form = CANDIDATEFORM::default();
+    let found = ImmGetCandidateWindow(himc, 0, &mut form).as_bool();
+    let _ = ImmReleaseContext(hwnd, himc);
+    if !found {
+        return None;
+    }
+
+    let mut origin = if form.dwStyle == CFS_EXCLUDE {
+        POINT {
+            x: form.rcArea.left,
+            y: form.rcArea.top,
+        }
+    } else {
+        form.ptCurrentPos
+    };
+    let _ = ClientToScreen(hwnd, &mut origin);
+    Some(ImeEvent::CandidateWindow {
+        x: origin.x,
+        y: origin.y,
+        width: form.rcArea.right - form.rcArea.left,
+        height: form.rcArea.bottom - form.rcArea.top,
+    })
+}
+
+fn char_message_text(unit: u16) -> Option<String> {
+    PENDING_HIGH_SURROGATE.with(|pending| {
+        if (0xD800..0xDC00).contains(&unit) {
+            pending.set(Some(unit));
+            return None;
+        }
+
+        let units = match pending.take() {
+            Some(high) => vec![high, unit],
+            None => vec![unit],
+        };
+        // Backspace, Enter and Escape arrive here too; the global key hook already handles them.
+        String::from_utf16(&units)
+            .ok()
+            .filter(|text| !text.chars().any(char::is_control))
+    })
+}
+
+pub(super) fn can_inject_key(key: Key) -> bool {
+    key_to_virtual_key(key).is_some()
+}
+
+fn send_input(input: INPUT) {
+    unsafe {
+        let _ = SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+    }
+}
+
+fn key_injection_inputs(key: Key, key_up: bool) -> Vec<INPUT> {
+    let Some(spec) = keys::key_spec(key) else {
+        return Vec::new();
+    };
+    let vk = VIRTUAL_KEY(spec.virtual_key);
+
+    let mut inputs = Vec::with_capacity(2);
+    let mapped = unsafe { MapVirtualKeyW(vk.0 as u32, MAPVK_VK_TO_VSC_EX) };
+    if mapped != 0 {
+        let scan_code = (mapped & 0xFF) as u16;
+        let extended_prefix = mapped & 0xFF00;
+
+        let mut flags = KEYEVENTF_SCANCODE;
+        // Enter and NumpadEnter map to the same scan code, so only the table tells them apart.
+        if spec.extended || extended_prefix == 0xE000 || extended_prefix == 0xE100 {
+            flags |= KEYEVENTF_EXTENDEDKEY;
+        }
+        if key_up {
+            flags |= KEYEVENTF_KEYUP;
+        }
+
+        inputs.push(INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: VIRTUAL_KEY(0),
+                    wScan: scan_code,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: INJECTION_MARKER,
+                },
+            },
+        });
+    }
+
+    let mut flags = KEYBD_EVENT_FLAGS(0);
+    if spec.extended {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+    if key_up {
+        flags |= KEYEVENTF_KEYUP;
+    }
+
+    inputs.push(INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: 0,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: INJECTION_MARKER,
+            },
+        },
+    });
+
+    inputs
+}
+
+fn button_to_mouse_flags(button: Button, button_up: bool) -> Option<(MOUSE_EVENT_FLAGS, u32)> {
+    let flags = match (button, button_up) {
+        (Button::Left, false) => (MOUSEEVENTF_LEFTDOWN, 0),
+        (Button::Left, true) => (MOUSEEVENTF_LEFTUP, 0),
+        (Button::Right, false) => (MOUSEEVENTF_RIGHTDOWN, 0),
+        (Button::Right, true) => (MOUSEEVENTF_RIGHTUP, 0),
+        (Button::Middle, false) => (MOUSEEVENTF_MIDDLEDOWN, 0),
+        (Button::Middle, true) => (MOUSEEVENTF_MIDDLEUP, 0),
+        // rdev reports the side buttons as Unknown(XBUTTON1 | XBUTTON2).
+        (Button::Unknown(code @ (1 | 2)), false) => (MOUSEEVENTF_XDOWN, code as u32),
+        (Button::Unknown(code @ (1 | 2)), true) => (MOUSEEVENTF_XUP, code as u32),
+        _ => return None,
+    };
+
+    Some(flags)
+}
+
+fn key_to_virtual_key(key: Key) -> Option<VIRTUAL_KEY> {
+    keys::key_spec(key).map(|spec| VIRTUAL_KEY(spec.virtual_key))
+}
@@ -1,8 +1,15 @@
+mod clipboard;
 mod commands;
+mod focus;
+mod gamepad;
+mod hotkeys;
 mod input;
 mod media;
+mod quick_chat;
 mod roblox;
 mod updater;
+#[cfg(target_os = "linux")]
+mod xdisplay;
 
 use commands::*;
 use std::path::PathBuf;
@@ -11,11 +18,15 @@ use tauri::{Emitter, Manager};
 #[cfg(desktop)]
 use tauri_plugin_deep_link::DeepLinkExt;
 
+pub(crate) const STORE_PATH: &str = "store.json";
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let mut builder = tauri::Builder::default();
     let initial_logs_path = roblox::default_roblox_logs_path();
     let (watcher_control_tx, watcher_control_rx) = mpsc::channel::<PathBuf>();
+    let input_state = input::InputCaptureState::default();
+    input::install_panic_release(input_state.clone());
 
     #[cfg(desktop)]
     {
@@ -29,15 +40,32 @@ pub fn run() {
             logs_path: Mutex::new(initial_logs_path.clone()),
             watcher_control: Mutex::new(Some(watcher_control_tx)),
         })
-        .manage(input::InputCaptureState::default())
+        .manage(input_state)
         .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_app_exit::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::Destroyed => {
+                let state = window.state::<input::InputCaptureState>();
+                input::release_on_exit(&state, "window close");
+            }
+            tauri::WindowEvent::Focused(_) => {
+                let state = window.state::<input::InputCaptureState>();
+                input::refresh_foreground(window.app_handle(), &state);
+            }
+            _ => {}
+        })
         .setup(move |app| {
             tauri::async_runtime::spawn(updater::check_for_startup_update(app.handle().clone()));
             roblox::start_log_watcher(initial_logs_path.clone(), watcher_control_rx);
             let input_state = app.state::<input::InputCaptureState>().inner().clone();
+            if let Err(err) = input::load_persisted_settings(app.handle(), &input_state) {
+                eprintln!("Failed to load input settings: {err:#}");
+            }
+            input::start_input_watchdog(app.handle().clone(), input_state.clone());
+            input::start_foreground_watcher(app.handle().clone(), input_state.clone());
+            input::start_gamepad_listener(app.handle().clone(), input_state.clone());
             input::start_key_listener(app.handle().clone(), input_state);
             #[cfg(desktop)]
             app.deep_link().register("bloxchat")?;
@@ -49,6 +77,36 @@ pub fn run() {
             focus_roblox,
             start_chat_capture,
             stop_chat_capture,
+            get_capture_state,
+            set_ime_caret,
+            focus_ime_bridge,
+            restore_capture_focus,
+            set_compose_state,
+            get_capture_capabilities,
+            get_input_listener_status,
+            retry_input_listener,
+            get_hotkey_bindings,
+            set_hotkey_binding,
+            reset_hotkey_bindings,
+            get_quick_chat_macros,
+            set_quick_chat_macros,
+            get_gamepad_chat_combo,
+            set_gamepad_chat_combo,
+            get_custom_latch_keys,
+            set_custom_latch_keys,
+            get_passthrough_chords,
+            set_passthrough_chords,
+            test_chord_suppression,
+            get_input_trace_enabled,
+            set_input_trace_enabled,
+            dump_input_trace,
+            clear_input_trace,
+            get_capture_idle_timeout,
+            set_capture_idle_timeout,
+            get_chat_scroll_modifier,
+            set_chat_scroll_modifier,
+            get_capture_trigger,
+            set_capture_trigger,
             read_clipboard_text,
             write_clipboard_text,
             is_image,
@@ -57,6 +115,12 @@ pub fn run() {
             set_roblox_logs_path,
             get_job_id
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                let state = app.state::<input::InputCaptureState>();
+                input::release_on_exit(&state, "exit");
+            }
+        });
 }
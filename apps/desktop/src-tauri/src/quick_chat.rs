@@ -0,0 +1,258 @@
+use anyhow::Result;
+use rdev::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::hotkeys::{Chord, HotkeyRegistry, Modifiers};
+use crate::roblox::{LogSettingsState, RobloxSession};
+
+const STORE_KEY: &str = "quickChatMacros";
+const MAX_MACROS: usize = 32;
+const UNKNOWN_PLACEHOLDER: &str = "unknown";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QuickChatMacro {
+    pub(crate) chord: String,
+    pub(crate) template: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QuickChatEvent {
+    message: String,
+    chord: String,
+    timestamp_ms: i64,
+}
+
+#[derive(Clone, Default, PartialEq)]
+pub(crate) struct QuickChatTable {
+    entries: Vec<(Chord, String)>,
+}
+
+impl QuickChatTable {
+    pub(crate) fn macros(&self) -> Vec<QuickChatMacro> {
+        self.entries
+            .iter()
+            .map(|(chord, template)| QuickChatMacro {
+                chord: chord.to_string(),
+                template: template.clone(),
+            })
+            .collect()
+    }
+
+    // All or nothing, so a bad row never leaves the table half updated.
+    pub(crate) fn set_all(
+        &mut self,
+        macros: &[QuickChatMacro],
+        hotkeys: &HotkeyRegistry,
+    ) -> Result<()> {
+        if macros.len() > MAX_MACROS {
+            anyhow::bail!("At most {MAX_MACROS} quick chat macros are supported");
+        }
+
+        let mut entries = Vec::with_capacity(macros.len());
+        for quick_chat in macros {
+            let entry = parse_entry(quick_chat, hotkeys)?;
+            if entries.iter().any(|(chord, _)| *chord == entry.0) {
+                anyhow::bail!("{} is used by more than one quick chat macro", entry.0);
+            }
+            entries.push(entry);
+        }
+
+        self.entries = entries;
+        Ok(())
+    }
+
+    pub(crate) fn match_key(&self, key: Key, down_keys: &HashSet<Key>) -> Option<(Chord, String)> {
+        let modifiers = Modifiers::from_down_keys(down_keys);
+        self.entries
+            .iter()
+            .find(|(chord, _)| chord.matches(key, modifiers))
+            .cloned()
+    }
+
+    pub(crate) fn uses_chord(&self, chord: &Chord) -> bool {
+        self.entries.iter().any(|(existing, _)| existing == chord)
+    }
+
+    fn apply_json(&mut self, value: serde_json::Value, hotkeys: &HotkeyRegistry) {
+        let macros: Vec<QuickChatMacro> = match serde_json::from_value(value) {
+            Ok(macros) => macros,
+            Err(err) => {
+                eprintln!("Ignoring stored quick chat macros: {err}");
+                return;
+            }
+        };
+
+        self.entries.clear();
+        for quick_chat in macros.iter().take(MAX_MACROS) {
+            match parse_entry(quick_chat, hotkeys) {
+                Ok(entry) if !self.uses_chord(&entry.0) => self.entries.push(entry),
+                Ok((chord, _)) => eprintln!("Ignoring duplicate quick chat macro for {chord}"),
+                Err(err) => eprintln!("Ignoring stored quick chat macro: {err:#}"),
+            }
+        }
+    }
+}
+
+fn parse_entry(quick_chat: &QuickChatMacro, hotkeys: &HotkeyRegistry) -> Result<(Chord, String)> {
+    let chord = Chord::parse(quick_chat.chord.trim())?;
+    if chord.is_reserved() {
+        anyhow::bail!("{chord} is reserved for chat and cannot send a quick chat message");
+    }
+    if let Some(action) = hotkeys.bound_action(&chord) {
+        anyhow::bail!("{chord} is already bound to {}", action.as_str());
+    }
+
+    let template = quick_chat.template.trim();
+    if template.is_empty() {
+        anyhow::bail!("The quick chat message for {chord} is empty");
+    }
+
+    Ok((chord, template.to_string()))
+}
+
+// Fills in {jobId} and {placeId} from the server the player is currently in.
+pub(crate) fn render(template: &str, session: &RobloxSession) -> String {
+    template.replace("{jobId}", &session.job_id).replace(
+        "{placeId}",
+        session.place_id.as_deref().unwrap_or(UNKNOWN_PLACEHOLDER),
+    )
+}
+
+pub(crate) fn send(app: AppHandle, chord: Chord, template: String) {
+    // Rendering may read the Roblox log tail, which must stay off the input hook thread.
+    std::thread::spawn(move || {
+        let session = match crate::roblox::current_session(&app.state::<LogSettingsState>()) {
+            Ok(session) => session,
+            Err(err) => {
+                eprintln!("Failed to read the Roblox session for quick chat: {err:#}");
+                RobloxSession::default()
+            }
+        };
+
+        let _ = app.emit(
+            "quick-chat",
+            QuickChatEvent {
+                message: render(&template, &session),
+                chord: chord.to_string(),
+                timestamp_ms: crate::input::event_timestamp_ms(),
+            },
+        );
+    });
+}
+
+pub(crate) fn load_macros(
+    app: &AppHandle,
+    table: &mut QuickChatTable,
+    hotkeys: &HotkeyRegistry,
+) -> Result<()> {
+    let store = app.store(crate::STORE_PATH)?;
+    if let Some(value) = store.get(STORE_KEY) {
+        table.apply_json(value, hotkeys);
+    }
+
+    Ok(())
+}
+
+pub(crate) fn save_macros(app: &AppHandle, table: &QuickChatTable) -> Result<()> {
+    let store = app.store(crate::STORE_PATH)?;
+    store.set(STORE_KEY, serde_json::json!(table.macros()));
+    store.save()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quick_chat(chord: &str, template: &str) -> QuickChatMacro {
+        QuickChatMacro {
+            chord: chord.to_string(),
+            template: template.to_string(),
+        }
+    }
+
+    #[test]
+    fn macros_match_their_exact_chord() {
+        let mut table = QuickChatTable::default();
+        table
+            .set_all(
+                &[quick_chat("Alt+Digit1", " gg ")],
+                &HotkeyRegistry::default(),
+            )
+            .unwrap();
+
+        let down: HashSet<Key> = [Key::Alt, Key::Num1].into();
+        let (chord, template) = table.match_key(Key::Num1, &down).unwrap();
+        assert_eq!(chord.to_string(), "Alt+Digit1");
+        assert_eq!(template, "gg");
+
+        let bare: HashSet<Key> = [Key::Num1].into();
+        assert!(table.match_key(Key::Num1, &bare).is_none());
+    }
+
+    #[test]
+    fn invalid_tables_are_rejected_whole() {
+        let hotkeys = HotkeyRegistry::default();
+        let mut table = QuickChatTable::default();
+        table
+            .set_all(&[quick_chat("Alt+Digit1", "gg")], &hotkeys)
+            .unwrap();
+
+        for macros in [
+            vec![quick_chat("Alt+Digit2", "hi"), quick_chat("Slash", "hi")],
+            vec![quick_chat("Ctrl+Shift+KeyM", "taken by mute")],
+            vec![
+                quick_chat("Alt+Digit2", "a"),
+                quick_chat("alt + Digit2", "b"),
+            ],
+            vec![quick_chat("Alt+Digit2", "   ")],
+        ] {
+            assert!(table.set_all(&macros, &hotkeys).is_err());
+        }
+        assert_eq!(table.macros(), vec![quick_chat("Alt+Digit1", "gg")]);
+    }
+
+    #[test]
+    fn stored_macros_skip_invalid_entries() {
+        let mut table = QuickChatTable::default();
+        table.apply_json(
+            serde_json::json!([
+                { "chord": "Alt+Digit1", "template": "gg" },
+                { "chord": "Alt+Bogus", "template": "nope" },
+                { "chord": "Alt+Digit1", "template": "duplicate" },
+                { "chord": "Alt+Digit2", "template": "join {jobId}" },
+            ]),
+            &HotkeyRegistry::default(),
+        );
+
+        assert_eq!(
+            table.macros(),
+            vec![
+                quick_chat("Alt+Digit1", "gg"),
+                quick_chat("Alt+Digit2", "join {jobId}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn templates_fill_in_the_current_server() {
+        let session = RobloxSession {
+            job_id: "deadbeef-1111".to_string(),
+            place_id: Some("606849621".to_string()),
+        };
+        assert_eq!(
+            render("Server {jobId} in {placeId}", &session),
+            "Server deadbeef-1111 in 606849621"
+        );
+
+        assert_eq!(
+            render("{jobId} / {placeId}", &RobloxSession::default()),
+            "global / unknown"
+        );
+    }
+}
@@ -1,42 +1,64 @@
 use anyhow::{Context, Result};
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use regex::Regex;
+#[cfg(windows)]
 use std::ffi::OsString;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+#[cfg(windows)]
 use std::os::windows::ffi::OsStringExt;
 use std::path::{Path, PathBuf};
 use std::sync::{mpsc, LazyLock, Mutex};
+#[cfg(windows)]
 use tauri::{AppHandle, Manager};
+#[cfg(windows)]
 use windows::Win32::Foundation::{HWND, MAX_PATH};
+#[cfg(windows)]
 use windows::Win32::System::Threading::{
     OpenProcess, QueryFullProcessImageNameW, PROCESS_QUERY_LIMITED_INFORMATION,
 };
+#[cfg(windows)]
 use windows::Win32::UI::WindowsAndMessaging::{
     FindWindowW, GetForegroundWindow, GetWindowThreadProcessId, IsIconic, SetForegroundWindow,
     ShowWindow, SW_RESTORE,
 };
+#[cfg(windows)]
 use windows_strings::PCWSTR;
 
+#[cfg(target_os = "linux")]
+mod sober;
+
+#[cfg(target_os = "linux")]
+pub(crate) use sober::{focus_roblox, should_steal_focus};
+
 pub(crate) struct LogSettingsState {
     pub(crate) logs_path: Mutex<PathBuf>,
     pub(crate) watcher_control: Mutex<Option<mpsc::Sender<PathBuf>>>,
 }
 
 const DEFAULT_JOB_ID: &str = "global";
-static JOIN_RE: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"Joining game '([a-f0-9-]+)'").expect("valid join regex"));
+static JOIN_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"Joining game '([a-f0-9-]+)'(?: place (\d+))?").expect("valid join regex")
+});
 static LEAVE_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"Disconnect from game|leaveGameInternal|leaveUGCGameInternal")
         .expect("valid leave regex")
 });
 
+#[cfg(windows)]
 pub(crate) fn default_roblox_logs_path() -> PathBuf {
     let mut path = home::home_dir().expect("Could not find home dir");
     path.push("AppData\\Local\\Roblox\\logs");
     path
 }
 
+#[cfg(target_os = "linux")]
+pub(crate) fn default_roblox_logs_path() -> PathBuf {
+    let mut path = home::home_dir().expect("Could not find home dir");
+    path.push(".var/app/org.vinegarhq.Sober/data/sober/sober_logs");
+    path
+}
+
 pub(crate) fn get_roblox_logs_path(state: &LogSettingsState) -> Result<PathBuf> {
     Ok(state
         .logs_path
@@ -82,25 +104,53 @@ fn validate_logs_path(path: &str) -> Result<PathBuf> {
     Ok(next_path)
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RobloxSession {
+    pub(crate) job_id: String,
+    pub(crate) place_id: Option<String>,
+}
+
+impl Default for RobloxSession {
+    fn default() -> Self {
+        Self {
+            job_id: DEFAULT_JOB_ID.to_string(),
+            place_id: None,
+        }
+    }
+}
+
+impl RobloxSession {
+    fn from_join(caps: &regex::Captures) -> Self {
+        Self {
+            job_id: caps[1].to_string(),
+            place_id: caps.get(2).map(|place| place.as_str().to_string()),
+        }
+    }
+}
+
 pub(crate) fn get_job_id(state: &LogSettingsState) -> Result<String> {
+    Ok(current_session(state)?.job_id)
+}
+
+pub(crate) fn current_session(state: &LogSettingsState) -> Result<RobloxSession> {
     let logs_path = state
         .logs_path
         .lock()
         .map_err(|err| anyhow::anyhow!("lock logs_path: {err}"))?
         .clone();
-    Ok(job_id_from_logs_dir(&logs_path))
+    Ok(session_from_logs_dir(&logs_path))
 }
 
-fn job_id_from_logs_dir(logs_dir: &Path) -> String {
+fn session_from_logs_dir(logs_dir: &Path) -> RobloxSession {
     let Some(latest_log) = latest_player_log(logs_dir) else {
-        return DEFAULT_JOB_ID.to_string();
+        return RobloxSession::default();
     };
 
     let Ok(mut file) = File::open(latest_log) else {
-        return DEFAULT_JOB_ID.to_string();
+        return RobloxSession::default();
     };
 
-    job_id_from_file_tail(&mut file).unwrap_or_else(|_| DEFAULT_JOB_ID.to_string())
+    session_from_file_tail(&mut file).unwrap_or_default()
 }
 
 fn latest_player_log(logs_dir: &Path) -> Option<PathBuf> {
@@ -113,10 +163,10 @@ fn latest_player_log(logs_dir: &Path) -> Option<PathBuf> {
     })
 }
 
-fn job_id_from_file_tail(file: &mut File) -> Result<String> {
+fn session_from_file_tail(file: &mut File) -> Result<RobloxSession> {
     let len = file.metadata().context("stat log file")?.len();
     if len == 0 {
-        return Ok(DEFAULT_JOB_ID.to_string());
+        return Ok(RobloxSession::default());
     }
 
     const INITIAL_WINDOW: u64 = 256 * 1024;
@@ -132,8 +182,8 @@ fn job_id_from_file_tail(file: &mut File) -> Result<String> {
         file.read_to_end(&mut bytes).context("read log tail")?;
 
         let text = String::from_utf8_lossy(&bytes);
-        if let Some(job_id) = job_id_from_text_slice(&text) {
-            return Ok(job_id);
+        if let Some(session) = session_from_text_slice(&text) {
+            return Ok(session);
         }
 
         if start == 0 || window >= MAX_WINDOW {
@@ -145,25 +195,24 @@ fn job_id_from_file_tail(file: &mut File) -> Result<String> {
     file.seek(SeekFrom::Start(0))
         .context("seek log file start")?;
 
-    let mut current = DEFAULT_JOB_ID.to_string();
+    let mut current = RobloxSession::default();
     for line in BufReader::new(file).lines().flatten() {
         if let Some(caps) = JOIN_RE.captures(&line) {
-            current = caps[1].to_string();
+            current = RobloxSession::from_join(&caps);
         } else if LEAVE_RE.is_match(&line) {
-            current = DEFAULT_JOB_ID.to_string();
+            current = RobloxSession::default();
         }
     }
 
     Ok(current)
 }
 
-fn job_id_from_text_slice(text: &str) -> Option<String> {
+fn session_from_text_slice(text: &str) -> Option<RobloxSession> {
     let last_join = JOIN_RE
         .captures_iter(text)
         .filter_map(|caps| {
             let m = caps.get(0)?;
-            let id = caps.get(1)?.as_str().to_string();
-            Some((m.start(), id))
+            Some((m.start(), RobloxSession::from_join(&caps)))
         })
         .last();
 
@@ -171,18 +220,19 @@ fn job_id_from_text_slice(text: &str) -> Option<String> {
 
     match (last_join, last_leave) {
         (None, None) => None,
-        (Some((_pos, id)), None) => Some(id),
-        (None, Some(_pos)) => Some(DEFAULT_JOB_ID.to_string()),
-        (Some((join_pos, id)), Some(leave_pos)) => {
+        (Some((_pos, session)), None) => Some(session),
+        (None, Some(_pos)) => Some(RobloxSession::default()),
+        (Some((join_pos, session)), Some(leave_pos)) => {
             if leave_pos > join_pos {
-                Some(DEFAULT_JOB_ID.to_string())
+                Some(RobloxSession::default())
             } else {
-                Some(id)
+                Some(session)
             }
         }
     }
 }
 
+#[cfg(windows)]
 pub(crate) fn should_steal_focus(app: AppHandle) -> bool {
     unsafe {
         let hwnd: HWND = GetForegroundWindow();
@@ -230,6 +280,7 @@ pub(crate) fn should_steal_focus(app: AppHandle) -> bool {
     }
 }
 
+#[cfg(windows)]
 pub(crate) fn focus_roblox() -> bool {
     const CLASS_NAME: &[u16] = &[
         b'R' as u16,
@@ -363,11 +414,30 @@ mod tests {
 
         let text = format!("{join1}\nblah\n{join2}\n");
         assert_eq!(
-            job_id_from_text_slice(&text).unwrap(),
+            session_from_text_slice(&text).unwrap().job_id,
             "deadbeef-1111-2222-3333-444455556666"
         );
 
         let text = format!("{join1}\nleaveGameInternal\n{join2}\nDisconnect from game\n");
-        assert_eq!(job_id_from_text_slice(&text).unwrap(), DEFAULT_JOB_ID);
+        assert_eq!(
+            session_from_text_slice(&text).unwrap().job_id,
+            DEFAULT_JOB_ID
+        );
+    }
+
+    #[test]
+    fn place_id_comes_from_the_join_line() {
+        let join =
+            "! Joining game 'deadbeef-1111-2222-3333-444455556666' place 606849621 at 10.0.0.1";
+        assert_eq!(
+            session_from_text_slice(join).unwrap(),
+            RobloxSession {
+                job_id: "deadbeef-1111-2222-3333-444455556666".to_string(),
+                place_id: Some("606849621".to_string()),
+            }
+        );
+
+        let text = format!("{join}\nDisconnect from game\n");
+        assert_eq!(session_from_text_slice(&text).unwrap().place_id, None);
     }
 }
@@ -0,0 +1,42 @@
+use crate::xdisplay::{activate_window, active_window, window_class, window_list_property};
+use tauri::{AppHandle, Manager};
+use x11::xlib;
+
+// Sober (org.vinegarhq.Sober) runs the Roblox client on Linux under XWayland or X11.
+const SOBER_WINDOW_CLASS: &str = "sober";
+
+pub(crate) fn should_steal_focus(app: AppHandle) -> bool {
+    if app
+        .webview_windows()
+        .values()
+        .any(|window| window.is_focused().unwrap_or(false))
+    {
+        return true;
+    }
+
+    crate::xdisplay::with_display(|display| unsafe {
+        active_window(display).is_some_and(|window| is_sober_window(display, window))
+    })
+    .unwrap_or(false)
+}
+
+pub(crate) fn focus_roblox() -> bool {
+    crate::xdisplay::with_display(|display| unsafe {
+        let root = xlib::XDefaultRootWindow(display);
+        let Some(window) = window_list_property(display, root, c"_NET_CLIENT_LIST")
+            .into_iter()
+            .find(|window| is_sober_window(display, *window))
+        else {
+            return false;
+        };
+
+        activate_window(display, window);
+        true
+    })
+    .unwrap_or(false)
+}
+
+unsafe fn is_sober_window(display: *mut xlib::Display, window: xlib::Window) -> bool {
+    window_class(display, window)
+        .is_some_and(|class| class.to_lowercase().contains(SOBER_WINDOW_CLASS))
+}
@@ -0,0 +1,200 @@
+use std::ffi::{c_char, c_int, c_long, c_uchar, c_uint, c_ulong, CStr};
+use std::ptr;
+use std::sync::Mutex;
+use x11::{xlib, xlib_xcb};
+use xkbcommon::xkb;
+
+// Xlib's XkbUseCoreKbd device spec.
+const XKB_USE_CORE_KBD: c_uint = 0x0100;
+const CAPS_LOCK_INDICATOR: c_uint = 1;
+
+struct Connection(*mut xlib::Display);
+
+// Xlib is not thread-safe on its own; the connection is only used while holding DISPLAY.
+unsafe impl Send for Connection {}
+
+static DISPLAY: Mutex<Option<Connection>> = Mutex::new(None);
+
+pub(crate) fn with_display<T>(f: impl FnOnce(*mut xlib::Display) -> T) -> Option<T> {
+    let mut connection = DISPLAY.lock().ok()?;
+    if connection.is_none() {
+        let display = unsafe { xlib::XOpenDisplay(ptr::null()) };
+        if display.is_null() {
+            return None;
+        }
+        *connection = Some(Connection(display));
+    }
+
+    connection.as_ref().map(|connection| f(connection.0))
+}
+
+pub(crate) fn caps_lock_enabled() -> bool {
+    with_display(|display| unsafe {
+        let mut state: c_uint = 0;
+        xlib::XkbGetIndicatorState(display, XKB_USE_CORE_KBD, &mut state) == 0
+            && state & CAPS_LOCK_INDICATOR != 0
+    })
+    .unwrap_or(false)
+}
+
+pub(crate) fn is_keycode_down(keycode: u32) -> Option<bool> {
+    with_display(|display| unsafe {
+        let mut keymap = [0 as c_char; 32];
+        xlib::XQueryKeymap(display, keymap.as_mut_ptr());
+        keymap
+            .get((keycode / 8) as usize)
+            .is_some_and(|byte| (*byte as u8) & (1 << (keycode % 8)) != 0)
+    })
+}
+
+// The server's keymap and state for the core keyboard, which follow layout switches.
+pub(crate) fn core_keyboard_keymap(context: &xkb::Context) -> Option<(xkb::Keymap, xkb::State)> {
+    with_display(|display| unsafe {
+        let connection = xlib_xcb::XGetXCBConnection(display);
+        if connection.is_null() {
+            return None;
+        }
+        let connection = as_raw_xcb_connection::ValidConnection::new(connection.cast());
+
+        let (mut major, mut minor, mut base_event, mut base_error) = (0, 0, 0, 0);
+        if !xkb::x11::setup_xkb_extension(
+            &connection,
+            xkb::x11::MIN_MAJOR_XKB_VERSION,
+            xkb::x11::MIN_MINOR_XKB_VERSION,
+            xkb::x11::SetupXkbExtensionFlags::NoFlags,
+            &mut major,
+            &mut minor,
+            &mut base_event,
+            &mut base_error,
+        ) {
+            return None;
+        }
+
+        let device_id = xkb::x11::get_core_keyboard_device_id(&connection);
+        if device_id < 0 {
+            return None;
+        }
+        let keymap = xkb::x11::keymap_new_from_device(
+            context,
+            &connection,
+            device_id,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        );
+        if keymap.get_raw_ptr().is_null() {
+            return None;
+        }
+        let state = xkb::x11::state_new_from_device(&keymap, &connection, device_id);
+        if state.get_raw_ptr().is_null() {
+            return None;
+        }
+
+        Some((keymap, state))
+    })
+    .flatten()
+}
+
+pub(crate) unsafe fn intern_atom(display: *mut xlib::Display, name: &CStr) -> xlib::Atom {
+    xlib::XInternAtom(display, name.as_ptr(), xlib::False)
+}
+
+pub(crate) unsafe fn window_list_property(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    name: &CStr,
+) -> Vec<xlib::Window> {
+    let mut actual_type: xlib::Atom = 0;
+    let mut actual_format: c_int = 0;
+    let mut item_count: c_ulong = 0;
+    let mut bytes_after: c_ulong = 0;
+    let mut data: *mut c_uchar = ptr::null_mut();
+
+    let status = xlib::XGetWindowProperty(
+        display,
+        window,
+        intern_atom(display, name),
+        0,
+        1024,
+        xlib::False,
+        xlib::XA_WINDOW,
+        &mut actual_type,
+        &mut actual_format,
+        &mut item_count,
+        &mut bytes_after,
+        &mut data,
+    );
+    if status != xlib::Success as c_int || data.is_null() {
+        return Vec::new();
+    }
+
+    // Format-32 properties come back as an array of C longs.
+    let windows = if actual_format == 32 {
+        std::slice::from_raw_parts(data as *const xlib::Window, item_count as usize).to_vec()
+    } else {
+        Vec::new()
+    };
+    xlib::XFree(data.cast());
+    windows
+}
+
+pub(crate) unsafe fn active_window(display: *mut xlib::Display) -> Option<xlib::Window> {
+    let root = xlib::XDefaultRootWindow(display);
+    window_list_property(display, root, c"_NET_ACTIVE_WINDOW")
+        .first()
+        .copied()
+        .filter(|window| *window != 0)
+}
+
+pub(crate) unsafe fn window_class(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+) -> Option<String> {
+    let mut hint = xlib::XClassHint {
+        res_name: ptr::null_mut(),
+        res_class: ptr::null_mut(),
+    };
+    if xlib::XGetClassHint(display, window, &mut hint) == 0 {
+        return None;
+    }
+
+    let mut class = String::new();
+    for part in [hint.res_name, hint.res_class] {
+        if part.is_null() {
+            continue;
+        }
+        class.push_str(&CStr::from_ptr(part).to_string_lossy());
+        class.push(' ');
+        xlib::XFree(part.cast());
+    }
+
+    Some(class.trim_end().to_string())
+}
+
+pub(crate) unsafe fn activate_window(display: *mut xlib::Display, window: xlib::Window) {
+    let root = xlib::XDefaultRootWindow(display);
+    let mut data = xlib::ClientMessageData::new();
+    // Source indication 2 = pager, which window managers honor without focus-stealing checks.
+    data.set_long(0, 2);
+    data.set_long(1, xlib::CurrentTime as c_long);
+
+    let mut event = xlib::XEvent {
+        client_message: xlib::XClientMessageEvent {
+            type_: xlib::ClientMessage,
+            serial: 0,
+            send_event: xlib::True,
+            display,
+            window,
+            message_type: intern_atom(display, c"_NET_ACTIVE_WINDOW"),
+            format: 32,
+            data,
+        },
+    };
+    xlib::XSendEvent(
+        display,
+        root,
+        xlib::False,
+        xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+        &mut event,
+    );
+    xlib::XMapRaised(display, window);
+    xlib::XFlush(display);
+}
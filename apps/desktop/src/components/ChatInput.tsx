@@ -9,6 +9,7 @@ import {
 import { flushSync } from "react-dom";
 import type { ChatMessage } from "@bloxchat/api";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { useChat } from "../contexts/ChatContext";
 import {
   findEmojiSuggestions,
@@ -27,13 +28,32 @@ export type GlobalKeyPayload = {
   alt: boolean;
   meta: boolean;
   repeat: boolean;
+  dead_key?: boolean;
   timestamp_ms: number;
 };
 
+type ImeCompositionPayload =
+  | { kind: "preedit"; text: string; cursor: number }
+  | { kind: "commit"; text: string }
+  | { kind: "candidateWindow"; x: number; y: number; width: number; height: number }
+  | { kind: "end" };
+
+type Preedit = { text: string; cursor: number };
+
+type ComposeStatePayload = {
+  text: string;
+  cursor: number;
+  selection: { start: number; end: number } | null;
+};
+
+const emptyPreedit: Preedit = { text: "", cursor: 0 };
+
 export type ChatInputKeyAction = "none" | "submit" | "cancel";
 
 export type ChatInputHandle = {
   focusImeInput: () => void;
+  // Swaps in a whole draft, such as a reply mention, with the caret at its end.
+  replaceDraft: (text: string) => void;
   handleGlobalKey: (event: GlobalKeyPayload) => Promise<ChatInputKeyAction>;
 };
 
@@ -44,6 +64,7 @@ interface ChatInputProps {
   maxLength: number;
   mode: ChatInputMode;
   captureActive?: boolean;
+  imeBridge?: boolean;
   onSubmit: () => void;
   onCancel: () => void;
   onFocusRequest?: () => void;
@@ -61,156 +82,6 @@ const toNormalizedRange = (start: number, end: number) => ({
   end: Math.max(start, end),
 });
 
-const shiftedNumberMap: Record<string, string> = {
-  Digit1: "!",
-  Digit2: "@",
-  Digit3: "#",
-  Digit4: "$",
-  Digit5: "%",
-  Digit6: "^",
-  Digit7: "&",
-  Digit8: "*",
-  Digit9: "(",
-  Digit0: ")",
-};
-
-const shiftedSymbolMap: Record<string, string> = {
-  Backquote: "~",
-  Minus: "_",
-  Equal: "+",
-  BracketLeft: "{",
-  BracketRight: "}",
-  Backslash: "|",
-  Semicolon: ":",
-  Quote: "\"",
-  Comma: "<",
-  Period: ">",
-  Slash: "?",
-};
-
-const unshiftedSymbolMap: Record<string, string> = {
-  Backquote: "`",
-  Minus: "-",
-  Equal: "=",
-  BracketLeft: "[",
-  BracketRight: "]",
-  Backslash: "\\",
-  Semicolon: ";",
-  Quote: "'",
-  Comma: ",",
-  Period: ".",
-  Slash: "/",
-};
-
-const forceCodeMappedCodes = new Set([
-  ...Object.keys(shiftedNumberMap),
-  ...Object.keys(shiftedSymbolMap),
-  ...Object.keys(unshiftedSymbolMap),
-  "NumpadAdd",
-  "NumpadSubtract",
-  "NumpadMultiply",
-  "NumpadDivide",
-  "NumpadDecimal",
-]);
-
-const mapPrintableCharacter = (code: string, shift: boolean) => {
-  if (code === "Space") return " ";
-
-  if (/^Key[A-Z]$/.test(code)) {
-    const letter = code.slice(3);
-    return shift ? letter : letter.toLowerCase();
-  }
-
-  if (/^Digit[0-9]$/.test(code)) {
-    return shift ? shiftedNumberMap[code] : code.slice(5);
-  }
-
-  if (code.startsWith("Numpad") && code.length === "Numpad0".length) {
-    const numeric = code.slice("Numpad".length);
-    if (/^[0-9]$/.test(numeric)) return numeric;
-  }
-
-  if (code === "NumpadAdd") return "+";
-  if (code === "NumpadSubtract") return "-";
-  if (code === "NumpadMultiply") return "*";
-  if (code === "NumpadDivide") return "/";
-  if (code === "NumpadDecimal") return ".";
-
-  if (shift && shiftedSymbolMap[code]) return shiftedSymbolMap[code];
-  if (!shift && unshiftedSymbolMap[code]) return unshiftedSymbolMap[code];
-  return null;
-};
-
-const nonTextKeyCodes = new Set([
-  "Escape",
-  "Enter",
-  "NumpadEnter",
-  "Tab",
-  "Backspace",
-  "Delete",
-  "ArrowLeft",
-  "ArrowRight",
-  "ArrowUp",
-  "ArrowDown",
-  "Home",
-  "End",
-  "PageUp",
-  "PageDown",
-  "Insert",
-  "CapsLock",
-  "NumLock",
-  "ScrollLock",
-  "Pause",
-  "PrintScreen",
-  "ShiftLeft",
-  "ShiftRight",
-  "ControlLeft",
-  "ControlRight",
-  "AltLeft",
-  "AltRight",
-  "MetaLeft",
-  "MetaRight",
-]);
-
-const hasControlCharacters = (value: string) => {
-  for (const char of value) {
-    const code = char.charCodeAt(0);
-    if (code < 0x20 || code === 0x7f) {
-      return true;
-    }
-  }
-  return false;
-};
-
-const resolveTextFromGlobalEvent = (event: GlobalKeyPayload) => {
-  if (event.ctrl || event.meta || event.alt) return null;
-  if (nonTextKeyCodes.has(event.code)) return null;
-  if (/^F\d{1,2}$/.test(event.code)) return null;
-
-  const mapped = mapPrintableCharacter(event.code, event.shift);
-  const raw = event.text;
-  const normalized = typeof raw === "string" ? raw.replace(/\r/g, "") : "";
-  const hasValidRaw = normalized.length > 0 && !hasControlCharacters(normalized);
-
-  // Preserve non-ASCII text from layout/IME, but force ASCII letter case by Shift state.
-  if (/^Key[A-Z]$/.test(event.code)) {
-    if (hasValidRaw && !/^[a-zA-Z]$/.test(normalized)) {
-      return normalized;
-    }
-    const letter = event.code.slice(3);
-    const caps = event.caps === true;
-    const uppercase = event.shift !== caps;
-    return uppercase ? letter : letter.toLowerCase();
-  }
-
-  if (mapped !== null && forceCodeMappedCodes.has(event.code)) {
-    return mapped;
-  }
-
-  if (!hasValidRaw) return null;
-  return normalized;
-};
-
 export const ChatInput = forwardRef<ChatInputHandle, ChatInputProps>(
   (
     {
@@ -220,6 +91,7 @@ export const ChatInput = forwardRef<ChatInputHandle, ChatInputProps>(
       maxLength,
       mode,
       captureActive = false,
+      imeBridge = false,
       onSubmit,
       onCancel,
       onFocusRequest,
@@ -232,11 +104,19 @@ export const ChatInput = forwardRef<ChatInputHandle, ChatInputProps>(
     const [selectionStart, setSelectionStart] = useState(0);
     const [selectionEnd, setSelectionEnd] = useState(0);
     const [isCaretVisible, setIsCaretVisible] = useState(true);
+    const [preedit, setPreedit] = useState<Preedit>(emptyPreedit);
 
     const imeInputRef = useRef<HTMLInputElement>(null);
     const focuslessViewportRef = useRef<HTMLDivElement>(null);
     const focuslessAnchorRef = useRef<HTMLSpanElement>(null);
     const { currentJobId } = useChat();
+    const imeCompositionHandlerRef = useRef<(payload: ImeCompositionPayload) => void>(
+      () => {},
+    );
+    const bridgedSessionActive = mode === "ime" && imeBridge && captureActive;
+    const composeSessionActive = mode === "focusless" && captureActive;
+    // The last text the backend compose buffer is known to hold.
+    const composeTextRef = useRef("");
 
     const usernames = useMemo(
       () => Array.from(new Set(messages.map((m) => m.author.username))),
@@ -433,12 +313,70 @@ export const ChatInput = forwardRef<ChatInputHandle, ChatInputProps>(
       return false;
     };
 
+    imeCompositionHandlerRef.current = (payload) => {
+      if (payload.kind === "commit") {
+        setPreedit(emptyPreedit);
+        replaceSelectionWith(payload.text);
+      } else if (payload.kind === "preedit") {
+        setPreedit({ text: payload.text, cursor: payload.cursor });
+      } else if (payload.kind === "end") {
+        setPreedit(emptyPreedit);
+      } else {
+        // The candidate list would sit on top of the mention and emoji suggestions.
+        setShowSuggestions(false);
+      }
+    };
+
+    useEffect(() => {
+      if (!bridgedSessionActive) {
+        setPreedit(emptyPreedit);
+        return;
+      }
+
+      const unlisten = listen<ImeCompositionPayload>("ime-composition", (event) => {
+        imeCompositionHandlerRef.current(event.payload);
+      });
+
+      return () => {
+        unlisten.then((fn) => fn());
+      };
+    }, [bridgedSessionActive]);
+
+    useEffect(() => {
+      if (!composeSessionActive) return;
+
+      const unlisten = listen<ComposeStatePayload>("compose-state", (event) => {
+        const { text, cursor, selection } = event.payload;
+        let anchor = cursor;
+        if (selection !== null) {
+          anchor = selection.start === cursor ? selection.end : selection.start;
+        }
+        composeTextRef.current = text;
+        onChange(text);
+        setSelectionStart(anchor);
+        setSelectionEnd(cursor);
+      });
+
+      return () => {
+        unlisten.then((fn) => fn());
+      };
+    }, [composeSessionActive, onChange]);
+
+    useEffect(() => {
+      // Mentions, emoji and favorites are inserted here, so hand those edits to the backend.
+      if (!composeSessionActive || value === composeTextRef.current) return;
+      composeTextRef.current = value;
+      void invoke("set_compose_state", { text: value, cursor: selectionEnd }).catch((err) =>
+        console.error("Failed to sync the chat draft:", err),
+      );
+    }, [composeSessionActive, value, selectionEnd]);
+
     const handleFocuslessGlobalKey = async (
       event: GlobalKeyPayload,
     ): Promise<ChatInputKeyAction> => {
       if (event.phase !== "down") return "none";
 
-      if (await handleClipboardShortcut(event)) return "none";
+      if (bridgedSessionActive && (await handleClipboardShortcut(event))) return "none";
 
       if (!event.ctrl && !event.meta && !event.alt) {
         if (event.code === "Escape") return "cancel";
@@ -464,6 +402,9 @@ export const ChatInput = forwardRef<ChatInputHandle, ChatInputProps>(
           return "submit";
         }
 
+        // The backend compose buffer applies focusless edits and reports them as compose-state.
+        if (!bridgedSessionActive) return "none";
+
         if (event.code === "Backspace") {
           deleteBackward();
           return "none";
@@ -493,20 +434,9 @@ export const ChatInput = forwardRef<ChatInputHandle, ChatInputProps>(
           moveCaretToBoundary(true, event.shift);
           return "none";
         }
-
-        const textFromEvent = resolveTextFromGlobalEvent(event);
-        if (textFromEvent !== null) {
-          replaceSelectionWith(textFromEvent);
-          return "none";
-        }
-
-        const printable = mapPrintableCharacter(event.code, event.shift);
-        if (printable !== null) {
-          replaceSelectionWith(printable);
-          return "none";
-        }
       }
 
+      // Typed text arrives through compose-state or ime-composition, never from here.
       return "none";
     };
 
@@ -514,16 +444,40 @@ export const ChatInput = forwardRef<ChatInputHandle, ChatInputProps>(
       ref,
       (): ChatInputHandle => ({
         focusImeInput: () => {
+          if (imeBridge) {
+            void invoke("focus_ime_bridge").catch((err) =>
+              console.error("Failed to focus the IME bridge:", err),
+            );
+            updateSelection(value.length, value.length);
+            return;
+          }
+
           imeInputRef.current?.focus();
           const position = imeInputRef.current?.value.length ?? value.length;
           updateSelection(position, position);
         },
+        replaceDraft: (text: string) => {
+          applyValueAndSelection(text, text.length, text.length);
+        },
         handleGlobalKey: async (event: GlobalKeyPayload) => {
-          if (mode !== "focusless") return "none";
+          if (mode !== "focusless" && !bridgedSessionActive) return "none";
+          // Keys typed mid-composition belong to the IME.
+          if (preedit.text.length > 0) return "none";
           return handleFocuslessGlobalKey(event);
         },
       }),
-      [mode, value, selectionStart, selectionEnd, suggestions, showSuggestions, activeIndex],
+      [
+        mode,
+        imeBridge,
+        bridgedSessionActive,
+        preedit,
+        value,
+        selectionStart,
+        selectionEnd,
+        suggestions,
+        showSuggestions,
+        activeIndex,
+      ],
     );
 
     const trimmedLength = value.trim().length;
@@ -543,7 +497,9 @@ export const ChatInput = forwardRef<ChatInputHandle, ChatInputProps>(
       normalizedSelection.end,
     );
     const afterText = value.slice(normalizedSelection.end);
-    const focuslessSessionActive = mode === "focusless" && captureActive;
+    const focuslessSessionActive =
+      (mode === "focusless" && captureActive) || bridgedSessionActive;
+    const isComposing = preedit.text.length > 0;
     const showFocuslessCaret =
       focuslessSessionActive && !hasSelection && isCaretVisible;
     const focuslessCaretOpacityClass = showFocuslessCaret ? "opacity-100" : "opacity-0";
@@ -565,17 +521,33 @@ export const ChatInput = forwardRef<ChatInputHandle, ChatInputProps>(
     }, [focuslessSessionActive]);
 
     useEffect(() => {
-      if (mode !== "focusless") return;
       if (!focuslessSessionActive) return;
       focuslessAnchorRef.current?.scrollIntoView({
         block: "nearest",
         inline: "nearest",
       });
-    }, [mode, focuslessSessionActive, value, selectionStart, selectionEnd]);
+    }, [focuslessSessionActive, value, selectionStart, selectionEnd, preedit]);
+
+    useEffect(() => {
+      if (!bridgedSessionActive) return;
+      const anchor = focuslessAnchorRef.current;
+      if (!anchor) return;
+
+      // The IME positions its windows in physical pixels.
+      const rect = anchor.getBoundingClientRect();
+      const scale = window.devicePixelRatio || 1;
+      void invoke("set_ime_caret", {
+        caret: {
+          x: Math.round(rect.left * scale),
+          y: Math.round(rect.top * scale),
+          height: Math.round(Math.max(rect.height, 16) * scale),
+        },
+      }).catch((err) => console.error("Failed to place the IME candidate window:", err));
+    }, [bridgedSessionActive, value, selectionStart, selectionEnd, preedit]);
 
     return (
       <div className="relative w-full min-w-0">
-        {mode === "ime" ? (
+        {mode === "ime" && !imeBridge ? (
           <input
             ref={imeInputRef}
             value={value}
@@ -644,7 +616,27 @@ export const ChatInput = forwardRef<ChatInputHandle, ChatInputProps>(
             }}
             className="h-10 w-full min-w-0 text-primary text-sm px-2 flex items-center overflow-x-auto overflow-y-hidden select-none outline-none [scrollbar-width:none] [&::-webkit-scrollbar]:hidden"
           >
-            {value.length === 0 ? (
+            {isComposing ? (
+              <span className="inline-block whitespace-pre min-w-full">
+                {beforeText}
+                <span className="underline decoration-dotted underline-offset-4">
+                  {preedit.text.slice(0, preedit.cursor)}
+                </span>
+                <span
+                  aria-hidden
+                  className={focuslessCaretClass}
+                />
+                <span
+                  ref={focuslessAnchorRef}
+                  aria-hidden
+                  className="inline-block h-4 w-0 align-middle"
+                />
+                <span className="underline decoration-dotted underline-offset-4">
+                  {preedit.text.slice(preedit.cursor)}
+                </span>
+                {afterText}
+              </span>
+            ) : value.length === 0 ? (
               <span className="inline-block whitespace-pre min-w-full">
                 <span
                   aria-hidden
@@ -42,6 +42,9 @@ type ChatContextType = {
   chatLimits: ChatLimits;
   sendError: string | null;
   sendMessage: (text: string) => boolean;
+  // Muting hides what others say in the current room until it is unmuted.
+  roomMuted: boolean;
+  toggleRoomMuted: () => void;
 };
 
 const ChatContext = createContext<ChatContextType | undefined>(undefined);
@@ -50,6 +53,7 @@ export const ChatProvider = ({ children }: { children: ReactNode }) => {
   const [currentJobId, setCurrentJobId] = useState("global");
   const [messages, setMessages] = useState<UiChatMessage[]>([]);
   const [sendError, setSendError] = useState<string | null>(null);
+  const [mutedJobIds, setMutedJobIds] = useState<Set<string>>(new Set());
   const sentTimestampsByScopeRef = useRef<Map<string, number[]>>(new Map());
   const { user } = useAuth();
   const currentUserIdRef = useRef<string | null>(null);
@@ -168,6 +172,14 @@ export const ChatProvider = ({ children }: { children: ReactNode }) => {
     return syncJobId();
   };
 
+  const toggleRoomMuted = () => {
+    setMutedJobIds((prev) => {
+      const next = new Set(prev);
+      if (!next.delete(currentJobId)) next.add(currentJobId);
+      return next;
+    });
+  };
+
   useEffect(() => {
     let cancelled = false;
 
@@ -293,6 +305,8 @@ export const ChatProvider = ({ children }: { children: ReactNode }) => {
         chatLimits,
         sendError,
         sendMessage,
+        roomMuted: mutedJobIds.has(currentJobId),
+        toggleRoomMuted,
       }}
     >
       {children}
@@ -2,7 +2,7 @@ import type { RouterOutputs } from "@bloxchat/api";
 import { load } from "@tauri-apps/plugin-store";
 
 export type AuthSession = RouterOutputs["auth"]["login"];
-export type ChatKeyPersistenceMode = "full" | "wasd" | "none";
+export type ChatKeyPersistenceMode = "full" | "wasd" | "custom" | "none";
 export type ChatInputMode = "focusless" | "ime";
 
 type StoreSchema = {
@@ -125,7 +125,12 @@ export const getFavoritedMedia = async () => storeGet("favoritedMedia");
 const normalizeChatKeyPersistenceMode = (
   value: unknown,
 ): ChatKeyPersistenceMode => {
-  if (value === "none" || value === "wasd" || value === "full") {
+  if (
+    value === "none" ||
+    value === "wasd" ||
+    value === "custom" ||
+    value === "full"
+  ) {
     return value;
   }
   return defaults.chatKeyPersistenceMode;
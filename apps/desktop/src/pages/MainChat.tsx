@@ -1,4 +1,5 @@
 import { useChat } from "../contexts/ChatContext";
+import { useAuth } from "../contexts/AuthContext";
 import {
   useState,
   useRef,
@@ -40,10 +41,104 @@ type FavoriteMediaPreview = {
   kind: "image" | "video" | "none";
 };
 
-type CaptureOpenSource = "slash" | "click";
+type CaptureOpenSource = "slash" | "click" | "gamepad" | "hotkey";
+
+type InputListenerStatus =
+  | { state: "starting" }
+  | { state: "grab" }
+  | { state: "passive"; error: string }
+  | { state: "failed"; error: string };
+
+type CaptureSession = {
+  // Composition arrives as ime-composition events instead of through a focused input.
+  imeBridge: boolean;
+  // A stop arrived before capture finished starting, so it never began.
+  cancelled: boolean;
+};
+
+type FocusRestore =
+  | { restored: "previous" }
+  // The previous window could not take focus back, so Roblox was found by the target rules.
+  | { restored: "target"; reason: string };
+
+type CaptureStopReason =
+  | "submit"
+  | "cancel"
+  | "focus-lost"
+  | "idle-timeout"
+  // The hold-to-chat key came up, so the message goes out.
+  | "release";
+
+type CaptureState =
+  | { state: "idle" }
+  | { state: "starting" }
+  | { state: "capturing"; mode: ChatInputMode }
+  | { state: "stopping"; reason: CaptureStopReason };
+
+type ChatScrollPayload = {
+  // Wheel notches; positive scrolls toward older messages.
+  delta: number;
+  timestampMs: number;
+};
+
+const CHAT_SCROLL_STEP_PX = 60;
+
+type HotkeyAction =
+  | "toggleOverlay"
+  | "toggleClickThrough"
+  | "scrollChatUp"
+  | "scrollChatDown"
+  | "replyToLastMessage"
+  | "muteRoom"
+  | "focusBloxchat";
+
+type HotkeyActionPayload = {
+  action: HotkeyAction;
+  chord: string;
+  timestampMs: number;
+};
+
+// Share of the visible chat a scroll hotkey moves, like PageUp/PageDown.
+const HOTKEY_SCROLL_PAGE = 0.8;
+
+type GamepadAction =
+  | "openChat"
+  | "previousMessage"
+  | "nextMessage"
+  | "oldestMessage"
+  | "latestMessage";
+
+type GamepadActionPayload = {
+  action: GamepadAction;
+  timestampMs: number;
+};
+
+type QuickChatPayload = {
+  message: string;
+  chord: string;
+  timestampMs: number;
+};
+
+type CaptureStoppedPayload = {
+  reason: CaptureStopReason;
+  timestampMs: number;
+};
 
 export const MainChat = () => {
-  const { messages, sendMessage, sendError, chatLimits } = useChat();
+  const {
+    messages,
+    sendMessage,
+    sendError,
+    chatLimits,
+    roomMuted,
+    toggleRoomMuted,
+  } = useChat();
+  const { user } = useAuth();
+  const visibleMessages = roomMuted
+    ? messages.filter(
+        (message) => message.author.robloxUserId === user?.robloxUserId,
+      )
+    : messages;
   const [text, setText] = useState("");
   const [favoritedMedia, setFavoritedMedia] = useState<string[]>([]);
   const [favoriteMediaPreviews, setFavoriteMediaPreviews] = useState<
@@ -56,24 +151,51 @@ export const MainChat = () => {
   const [chatInputMode, setChatInputMode] = useState<ChatInputMode>("focusless");
   const [activeCaptureInputMode, setActiveCaptureInputMode] =
     useState<ChatInputMode>("focusless");
+  const [imeBridge, setImeBridge] = useState(false);
+  const [listenerStatus, setListenerStatus] = useState<InputListenerStatus>({
+    state: "starting",
+  });
+  const [listenerRetryError, setListenerRetryError] = useState<string | null>(null);
   const scrollContainerRef = useRef<HTMLDivElement>(null);
   const scrollContentRef = useRef<HTMLDivElement>(null);
   const messagesEndRef = useRef<HTMLDivElement>(null);
   const inputRef = useRef<ChatInputHandle>(null);
   const appWindowRef = useRef<Window | null>(null);
   const shouldAutoScrollRef = useRef(true);
+  const clickThroughRef = useRef(false);
   const captureTransitionRef = useRef(false);
   const endingCaptureRef = useRef(false);
   const chatCaptureActiveRef = useRef(false);
   const chatKeyPersistenceModeRef = useRef<ChatKeyPersistenceMode>("full");
   const chatInputModeRef = useRef<ChatInputMode>("focusless");
   const activeCaptureInputModeRef = useRef<ChatInputMode>("focusless");
+  const imeBridgeRef = useRef(false);
   const textRef = useRef("");
+  const sendMessageRef = useRef(sendMessage);
+  const messagesRef = useRef(visibleMessages);
+  const userIdRef = useRef<string | null>(null);
+  const toggleRoomMutedRef = useRef(toggleRoomMuted);
 
   useEffect(() => {
     textRef.current = text;
   }, [text]);
 
+  useEffect(() => {
+    sendMessageRef.current = sendMessage;
+  }, [sendMessage]);
+
+  useEffect(() => {
+    messagesRef.current = visibleMessages;
+  }, [visibleMessages]);
+
+  useEffect(() => {
+    userIdRef.current = user?.robloxUserId ?? null;
+  }, [user]);
+
+  useEffect(() => {
+    toggleRoomMutedRef.current = toggleRoomMuted;
+  }, [toggleRoomMuted]);
+
   useEffect(() => {
     chatCaptureActiveRef.current = chatCaptureActive;
   }, [chatCaptureActive]);
@@ -90,6 +212,16 @@ export const MainChat = () => {
     activeCaptureInputModeRef.current = activeCaptureInputMode;
   }, [activeCaptureInputMode]);
 
+  useEffect(() => {
+    imeBridgeRef.current = imeBridge;
+  }, [imeBridge]);
+
+  const applyCaptureSession = (session: CaptureSession | null) => {
+    const bridged = session?.imeBridge === true;
+    imeBridgeRef.current = bridged;
+    setImeBridge(bridged);
+  };
+
   const scrollToBottom = (behavior: ScrollBehavior = "smooth") => {
     messagesEndRef.current?.scrollIntoView({ behavior });
   };
@@ -121,6 +253,36 @@ export const MainChat = () => {
     };
   }, []);
 
+  useEffect(() => {
+    // Modifier + wheel over Roblox scrolls chat without taking focus from the game.
+    const unlisten = listen<ChatScrollPayload>("chat-scroll", (event) => {
+      scrollContainerRef.current?.scrollBy({
+        top: -event.payload.delta * CHAT_SCROLL_STEP_PX,
+        behavior: "smooth",
+      });
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  useEffect(() => {
+    // Macros fire over Roblox and send straight away; chat never opens.
+    const unlisten = listen<QuickChatPayload>("quick-chat", (event) => {
+      const didQueue = sendMessageRef.current(
+        replaceEmojiShortcodes(event.payload.message),
+      );
+      if (didQueue) {
+        shouldAutoScrollRef.current = true;
+      }
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   useEffect(() => {
     const el = scrollContentRef.current;
     if (!el || typeof ResizeObserver === "undefined") return;
@@ -150,16 +312,44 @@ export const MainChat = () => {
       .catch((err) => console.error("Failed to load chat capture settings:", err));
   }, []);
 
+  useEffect(() => {
+    // A webview reload mid-capture leaves the backend capturing for a UI that no longer knows about it.
+    invoke<CaptureState>("get_capture_state")
+      .then((captureState) => {
+        if (captureState.state === "idle" || chatCaptureActiveRef.current) return;
+        return invoke("stop_chat_capture", { reason: "cancel" });
+      })
+      .catch((err) => console.error("Failed to sync chat capture state:", err));
+  }, []);
+
   useEffect(() => {
     return () => {
       if (!chatCaptureActiveRef.current) return;
-      void invoke("stop_chat_capture", { reason: "cancel", _reason: "cancel" }).catch((err) =>
+      void invoke("stop_chat_capture", { reason: "cancel" }).catch((err) =>
         console.error("Failed to stop chat capture during cleanup:", err),
       );
       chatCaptureActiveRef.current = false;
     };
   }, []);
 
+  useEffect(() => {
+    invoke<InputListenerStatus>("get_input_listener_status")
+      .then((status) => setListenerStatus(status))
+      .catch((err) => console.error("Failed to load input listener status:", err));
+
+    const unlisten = listen<InputListenerStatus>(
+      "input-listener-status",
+      (event) => {
+        setListenerStatus(event.payload);
+        setListenerRetryError(null);
+      },
+    );
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   useEffect(() => {
     getFavoritedMedia()
       .then((items) => setFavoritedMedia(items))
@@ -228,12 +418,17 @@ export const MainChat = () => {
       setChatCaptureActive(false);
       endingCaptureRef.current = false;
       if (activeCaptureInputModeRef.current === "ime") {
-        void invoke("focus_roblox").catch((err) =>
-          console.error("Failed to focus Roblox:", err),
-        );
+        void invoke<FocusRestore>("restore_capture_focus")
+          .then((restore) => {
+            if (restore.restored === "target") {
+              console.warn("Focused Roblox instead of the previous window:", restore.reason);
+            }
+          })
+          .catch((err) => console.error("Failed to restore focus:", err));
       }
       activeCaptureInputModeRef.current = chatInputModeRef.current;
       setActiveCaptureInputMode(chatInputModeRef.current);
+      applyCaptureSession(null);
     }
   }, []);
 
@@ -254,6 +449,28 @@ export const MainChat = () => {
     await stopChatCaptureSession("cancel");
   }, [stopChatCaptureSession]);
 
+  useEffect(() => {
+    // The backend ends capture on its own after the idle timeout, when Roblox loses focus,
+    // or when the hold-to-chat key is released.
+    const unlisten = listen<CaptureStoppedPayload>("capture-stopped", (event) => {
+      if (!chatCaptureActiveRef.current || endingCaptureRef.current) return;
+      if (event.payload.reason === "release") {
+        void handleCaptureSubmit();
+        return;
+      }
+      console.warn("Chat capture stopped:", event.payload.reason);
+      chatCaptureActiveRef.current = false;
+      setChatCaptureActive(false);
+      activeCaptureInputModeRef.current = chatInputModeRef.current;
+      setActiveCaptureInputMode(chatInputModeRef.current);
+      applyCaptureSession(null);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [handleCaptureSubmit]);
+
   const switchActiveCaptureInputMode = useCallback(async (nextMode: ChatInputMode) => {
     if (
       captureTransitionRef.current ||
@@ -269,13 +486,15 @@ export const MainChat = () => {
 
     captureTransitionRef.current = true;
     try {
-      await invoke("start_chat_capture", {
+      const session = await invoke<CaptureSession>("start_chat_capture", {
         mode: chatKeyPersistenceModeRef.current,
         inputMode: nextMode,
       });
+      if (session.cancelled) return;
 
       activeCaptureInputModeRef.current = nextMode;
       setActiveCaptureInputMode(nextMode);
+      applyCaptureSession(session);
 
       if (nextMode === "ime") {
         await appWindowRef.current?.setFocus();
@@ -304,20 +523,24 @@ export const MainChat = () => {
       const shouldStealFocus = await invoke<boolean>("should_steal_focus");
       if (!shouldStealFocus) return;
 
+      // Clicks and controllers need a focused input for the on-screen keyboard.
       const captureInputMode: ChatInputMode =
-        source === "click" && chatInputModeRef.current === "focusless"
+        (source === "click" || source === "gamepad") &&
+        chatInputModeRef.current === "focusless"
           ? "ime"
           : chatInputModeRef.current;
 
-      await invoke("start_chat_capture", {
+      const session = await invoke<CaptureSession>("start_chat_capture", {
         mode: chatKeyPersistenceModeRef.current,
         inputMode: captureInputMode,
       });
+      if (session.cancelled) return;
 
       chatCaptureActiveRef.current = true;
       setChatCaptureActive(true);
       activeCaptureInputModeRef.current = captureInputMode;
       setActiveCaptureInputMode(captureInputMode);
+      applyCaptureSession(session);
       setShowFavoritesPanel(false);
 
       if (captureInputMode === "ime") {
@@ -333,6 +556,74 @@ export const MainChat = () => {
     }
   }, []);
 
+  const replyToLastMessage = useCallback(async () => {
+    const target = [...messagesRef.current]
+      .reverse()
+      .find((message) => message.author.robloxUserId !== userIdRef.current);
+    if (!target) return;
+
+    if (!chatCaptureActiveRef.current) {
+      await openChatCapture("hotkey");
+      if (!chatCaptureActiveRef.current) return;
+    }
+    // Keep what was already typed, but only ever address one person.
+    const draft = textRef.current.replace(/^@\S+\s*/, "");
+    inputRef.current?.replaceDraft(`@${target.author.username} ${draft}`);
+  }, [openChatCapture]);
+
+  useEffect(() => {
+    // Bound chords never reach the game, so every action the backend can emit is handled here.
+    const unlisten = listen<HotkeyActionPayload>("hotkey-action", (event) => {
+      const appWindow = appWindowRef.current;
+      const { action } = event.payload;
+
+      if (action === "scrollChatUp" || action === "scrollChatDown") {
+        const container = scrollContainerRef.current;
+        if (!container) return;
+        const page = container.clientHeight * HOTKEY_SCROLL_PAGE;
+        container.scrollBy({
+          top: action === "scrollChatUp" ? -page : page,
+          behavior: "smooth",
+        });
+        return;
+      }
+
+      if (action === "replyToLastMessage") {
+        void replyToLastMessage();
+        return;
+      }
+      if (action === "muteRoom") {
+        toggleRoomMutedRef.current();
+        return;
+      }
+
+      if (!appWindow) return;
+      if (action === "toggleOverlay") {
+        void appWindow
+          .isVisible()
+          .then((visible) => (visible ? appWindow.hide() : appWindow.show()))
+          .catch((err) => console.error("Failed to toggle overlay:", err));
+      } else if (action === "toggleClickThrough") {
+        const next = !clickThroughRef.current;
+        void appWindow
+          .setIgnoreCursorEvents(next)
+          .then(() => {
+            clickThroughRef.current = next;
+          })
+          .catch((err) => console.error("Failed to toggle click-through:", err));
+      } else if (action === "focusBloxchat") {
+        void appWindow
+          .show()
+          .then(() => appWindow.setFocus())
+          .catch((err) => console.error("Failed to focus BloxChat:", err));
+      }
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [replyToLastMessage]);
+
   useEffect(() => {
     const unlisten = listen<GlobalKeyPayload>("global-key", async (event) => {
       const payload = event.payload;
@@ -350,13 +641,15 @@ export const MainChat = () => {
         payload.phase === "down" &&
         payload.code === "Slash" &&
         !payload.repeat &&
+        !imeBridgeRef.current &&
         !document.hasFocus()
       ) {
         await switchActiveCaptureInputMode("focusless");
         return;
       }
 
-      if (activeCaptureInputModeRef.current === "focusless") {
+      // The IME bridge takes text from ime-composition; editing keys still come from the hook.
+      if (activeCaptureInputModeRef.current === "focusless" || imeBridgeRef.current) {
         const action = await inputRef.current?.handleGlobalKey(payload);
         if (action === "submit") {
           await handleCaptureSubmit();
@@ -376,6 +669,48 @@ export const MainChat = () => {
     };
   }, [handleCaptureCancel, handleCaptureSubmit, openChatCapture, switchActiveCaptureInputMode]);
 
+  useEffect(() => {
+    const unlisten = listen<GamepadActionPayload>("gamepad-action", (event) => {
+      const { action } = event.payload;
+      if (action === "openChat") {
+        void openChatCapture("gamepad");
+        return;
+      }
+
+      const container = scrollContainerRef.current;
+      const content = scrollContentRef.current;
+      if (!container || !content) return;
+
+      if (action === "oldestMessage") {
+        container.scrollTo({ top: 0, behavior: "smooth" });
+        return;
+      }
+      if (action === "latestMessage") {
+        shouldAutoScrollRef.current = true;
+        container.scrollTo({ top: container.scrollHeight, behavior: "smooth" });
+        return;
+      }
+
+      // Step one message away from the one at the top of the view.
+      const items = Array.from(content.children) as HTMLElement[];
+      if (items.length === 0) return;
+      const viewTop = container.getBoundingClientRect().top;
+      const current = items.findIndex(
+        (item) => item.getBoundingClientRect().bottom > viewTop + 1,
+      );
+      const from = current < 0 ? items.length - 1 : current;
+      const next = Math.min(
+        items.length - 1,
+        Math.max(0, from + (action === "previousMessage" ? -1 : 1)),
+      );
+      items[next].scrollIntoView({ block: "start", behavior: "smooth" });
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [openChatCapture]);
+
   const handleSubmit = (e: FormEvent<HTMLFormElement>) => {
     e.preventDefault();
     if (chatCaptureActiveRef.current) {
@@ -431,13 +766,13 @@ export const MainChat = () => {
         className="flex-1 overflow-y-auto overflow-x-hidden"
       >
         <div ref={scrollContentRef} className="flex flex-col py-4">
-          {messages.length === 0 && (
+          {visibleMessages.length === 0 && !roomMuted && (
             <div className="text-center text-muted-foreground text-xs">
               No messages yet. Say hi!
             </div>
           )}
-          {messages.map((msg, index) => {
-            const prev = index > 0 ? messages[index - 1] : null;
+          {visibleMessages.map((msg, index) => {
+            const prev = index > 0 ? visibleMessages[index - 1] : null;
             const isContinuation = !!(
               prev && prev.author.robloxUserId === msg.author.robloxUserId
             );
@@ -536,6 +871,7 @@ export const MainChat = () => {
               maxLength={chatLimits.maxMessageLength}
               mode={chatCaptureActive ? activeCaptureInputMode : chatInputMode}
               captureActive={chatCaptureActive}
+              imeBridge={chatCaptureActive && imeBridge}
               onSubmit={() => {
                 void handleCaptureSubmit();
               }}
@@ -563,6 +899,47 @@ export const MainChat = () => {
           </Button>
         </form>
       </div>
+      {(listenerStatus.state === "passive" ||
+        listenerStatus.state === "failed") && (
+        <div className="flex items-center gap-2 px-2 py-1 text-[11px] text-yellow-400 border-t border-muted">
+          <span className="flex-1 break-all">
+            {listenerStatus.state === "passive"
+              ? "Keys typed in chat are also reaching Roblox"
+              : "Global key listener failed"}
+            : {listenerStatus.error}
+            {listenerRetryError && ` (retry failed: ${listenerRetryError})`}
+          </span>
+          <Button
+            type="button"
+            size="sm"
+            variant="ghost"
+            onClick={() =>
+              void invoke("retry_input_listener").catch((err) => {
+                console.error("Failed to retry input listener:", err);
+                // Linux cannot stop the running listener, so a retry needs an app restart there.
+                setListenerRetryError(String(err));
+              })
+            }
+          >
+            Retry
+          </Button>
+        </div>
+      )}
+      {roomMuted && (
+        <div className="flex items-center gap-2 px-2 py-1 text-[11px] text-muted-foreground border-t border-muted">
+          <span className="flex-1">
+            This room is muted, so messages from others are hidden.
+          </span>
+          <Button
+            type="button"
+            size="sm"
+            variant="ghost"
+            onClick={toggleRoomMuted}
+          >
+            Unmute
+          </Button>
+        </div>
+      )}
       {sendError && (
         <div className="px-2 py-1 text-[11px] text-red-400 border-t border-muted">
           {sendError}
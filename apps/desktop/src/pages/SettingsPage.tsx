@@ -25,6 +25,77 @@ import { Checkbox } from "../components/ui/checkbox";
 import { useNavigate } from "react-router-dom";
 import { Slider } from "../components/ui/slider";
 
+type ChatScrollModifier = "off" | "alt" | "ctrl" | "shift";
+type CaptureTrigger = "toggle" | "hold";
+
+type CaptureCapabilities = {
+  // False on Linux, where the focused widget of other apps cannot be read.
+  passwordFieldDetection: boolean;
+};
+
+type ChordSuppression = {
+  chord: string;
+  suppressed: boolean;
+  allowedBy: "builtIn" | "allowlist" | null;
+};
+
+const describeChordSuppression = (result: ChordSuppression) => {
+  if (result.allowedBy === "builtIn") {
+    return `${result.chord} always reaches other apps.`;
+  }
+  if (result.allowedBy === "allowlist") {
+    return `${result.chord} reaches other apps through your passthrough list.`;
+  }
+  return `${result.chord} is kept from other apps while chatting.`;
+};
+
+type QuickChatMacro = {
+  chord: string;
+  template: string;
+};
+
+type GamepadButton =
+  | "south"
+  | "east"
+  | "west"
+  | "north"
+  | "leftShoulder"
+  | "rightShoulder"
+  | "back"
+  | "start"
+  | "leftThumb"
+  | "rightThumb";
+
+const GAMEPAD_COMBO_BUTTONS: { value: GamepadButton; label: string }[] = [
+  { value: "south", label: "A / Cross" },
+  { value: "east", label: "B / Circle" },
+  { value: "west", label: "X / Square" },
+  { value: "north", label: "Y / Triangle" },
+  { value: "leftShoulder", label: "Left Bumper" },
+  { value: "rightShoulder", label: "Right Bumper" },
+  { value: "back", label: "Back / Select" },
+  { value: "start", label: "Start" },
+  { value: "leftThumb", label: "Left Stick Click" },
+  { value: "rightThumb", label: "Right Stick Click" },
+];
+
+const formatQuickChatMacros = (macros: QuickChatMacro[]) =>
+  macros.map((entry) => `${entry.chord}: ${entry.template}`).join("\n");
+
+const parseQuickChatMacros = (value: string): QuickChatMacro[] =>
+  value
+    .split("\n")
+    .map((line) => line.trim())
+    .filter(Boolean)
+    .map((line) => {
+      const separator = line.indexOf(":");
+      if (separator < 0) return { chord: line, template: "" };
+      return {
+        chord: line.slice(0, separator).trim(),
+        template: line.slice(separator + 1).trim(),
+      };
+    });
+
 export const SettingsPage = () => {
   const [apiUrl, setApiUrlInput] = useState("");
   const [logsPath, setLogsPathInput] = useState("");
@@ -35,8 +106,24 @@ export const SettingsPage = () => {
   const [joinMessage, setJoinMessageInput] = useState("");
   const [chatKeyPersistenceMode, setChatKeyPersistenceModeInput] =
     useState<ChatKeyPersistenceMode>("full");
+  const [customLatchKeys, setCustomLatchKeysInput] = useState("");
+  const [captureIdleTimeout, setCaptureIdleTimeoutInput] = useState("30");
+  const [passthroughChords, setPassthroughChordsInput] = useState("");
+  const [chordToTest, setChordToTest] = useState("");
+  const [chordTestResult, setChordTestResult] = useState("");
+  const [chatScrollModifier, setChatScrollModifierInput] =
+    useState<ChatScrollModifier>("alt");
+  const [captureTrigger, setCaptureTriggerInput] =
+    useState<CaptureTrigger>("toggle");
+  const [quickChatMacros, setQuickChatMacrosInput] = useState("");
+  const [gamepadChatCombo, setGamepadChatComboInput] = useState<
+    GamepadButton[]
+  >([]);
   const [chatInputMode, setChatInputModeInput] =
     useState<ChatInputMode>("focusless");
+  const [inputTraceEnabled, setInputTraceEnabledInput] = useState(false);
+  const [inputTraceStatus, setInputTraceStatus] = useState("");
+  const [passwordFieldDetection, setPasswordFieldDetection] = useState(true);
   const [appVersion, setAppVersion] = useState("Unknown");
   const [isLoading, setIsLoading] = useState(true);
   const [isSaving, setIsSaving] = useState(false);
@@ -57,7 +144,16 @@ export const SettingsPage = () => {
           currentGuiOpacity,
           currentJoinMessage,
           currentChatKeyPersistenceMode,
+          currentCustomLatchKeys,
+          currentCaptureIdleTimeout,
+          currentPassthroughChords,
+          currentChatScrollModifier,
+          currentCaptureTrigger,
+          currentQuickChatMacros,
+          currentGamepadChatCombo,
           currentChatInputMode,
+          currentInputTraceEnabled,
+          currentCaptureCapabilities,
           currentVersion,
         ] = await Promise.all([
           getApiUrl(),
@@ -68,7 +164,16 @@ export const SettingsPage = () => {
           getGuiOpacity(),
           getJoinMessage(),
           getChatKeyPersistenceMode(),
+          invoke<string[]>("get_custom_latch_keys"),
+          invoke<number>("get_capture_idle_timeout"),
+          invoke<string[]>("get_passthrough_chords"),
+          invoke<ChatScrollModifier>("get_chat_scroll_modifier"),
+          invoke<CaptureTrigger>("get_capture_trigger"),
+          invoke<QuickChatMacro[]>("get_quick_chat_macros"),
+          invoke<GamepadButton[]>("get_gamepad_chat_combo"),
           getChatInputMode(),
+          invoke<boolean>("get_input_trace_enabled"),
+          invoke<CaptureCapabilities>("get_capture_capabilities"),
           getVersion(),
         ]);
 
@@ -81,7 +186,16 @@ export const SettingsPage = () => {
         setGuiOpacityInput(currentGuiOpacity);
         setJoinMessageInput(currentJoinMessage);
         setChatKeyPersistenceModeInput(currentChatKeyPersistenceMode);
+        setCustomLatchKeysInput(currentCustomLatchKeys.join(", "));
+        setCaptureIdleTimeoutInput(String(currentCaptureIdleTimeout));
+        setPassthroughChordsInput(currentPassthroughChords.join(", "));
+        setChatScrollModifierInput(currentChatScrollModifier);
+        setCaptureTriggerInput(currentCaptureTrigger);
+        setQuickChatMacrosInput(formatQuickChatMacros(currentQuickChatMacros));
+        setGamepadChatComboInput(currentGamepadChatCombo);
         setChatInputModeInput(currentChatInputMode);
+        setInputTraceEnabledInput(currentInputTraceEnabled);
+        setPasswordFieldDetection(currentCaptureCapabilities.passwordFieldDetection);
         setInitialGuiOpacity(currentGuiOpacity);
         setAppVersion(currentVersion);
       } catch (loadError) {
@@ -104,6 +218,34 @@ export const SettingsPage = () => {
       const nextLogsPath = (logsPath.trim() || defaultLogsPath).trim();
       const nextOpacity = await setGuiOpacity(guiOpacity);
       const nextJoinMessage = await setJoinMessage(joinMessage);
+      const nextCustomLatchKeys = await invoke<string[]>(
+        "set_custom_latch_keys",
+        { keys: customLatchKeys.split(",") },
+      );
+      const nextCaptureIdleTimeout = await invoke<number>(
+        "set_capture_idle_timeout",
+        { seconds: Number.parseInt(captureIdleTimeout.trim() || "0", 10) || 0 },
+      );
+      const nextPassthroughChords = await invoke<string[]>(
+        "set_passthrough_chords",
+        { chords: passthroughChords.split(",") },
+      );
+      const nextChatScrollModifier = await invoke<ChatScrollModifier>(
+        "set_chat_scroll_modifier",
+        { modifier: chatScrollModifier },
+      );
+      const nextCaptureTrigger = await invoke<CaptureTrigger>(
+        "set_capture_trigger",
+        { trigger: captureTrigger },
+      );
+      const nextQuickChatMacros = await invoke<QuickChatMacro[]>(
+        "set_quick_chat_macros",
+        { macros: parseQuickChatMacros(quickChatMacros) },
+      );
+      const nextGamepadChatCombo = await invoke<GamepadButton[]>(
+        "set_gamepad_chat_combo",
+        { buttons: gamepadChatCombo },
+      );
       const nextKeyPersistence =
         await setChatKeyPersistenceMode(chatKeyPersistenceMode);
       const nextChatInputMode = await setChatInputMode(chatInputMode);
@@ -119,6 +261,13 @@ export const SettingsPage = () => {
       setGuiOpacityInput(nextOpacity);
       setJoinMessageInput(nextJoinMessage);
       setChatKeyPersistenceModeInput(nextKeyPersistence);
+      setCustomLatchKeysInput(nextCustomLatchKeys.join(", "));
+      setCaptureIdleTimeoutInput(String(nextCaptureIdleTimeout));
+      setPassthroughChordsInput(nextPassthroughChords.join(", "));
+      setChatScrollModifierInput(nextChatScrollModifier);
+      setCaptureTriggerInput(nextCaptureTrigger);
+      setQuickChatMacrosInput(formatQuickChatMacros(nextQuickChatMacros));
+      setGamepadChatComboInput(nextGamepadChatCombo);
       setChatInputModeInput(nextChatInputMode);
       document.documentElement.style.setProperty(
         "--gui-opacity",
@@ -136,6 +285,48 @@ export const SettingsPage = () => {
     }
   };
 
+  const testChord = async () => {
+    try {
+      const result = await invoke<ChordSuppression>("test_chord_suppression", {
+        chord: chordToTest,
+      });
+      setChordTestResult(describeChordSuppression(result));
+    } catch (testError) {
+      setChordTestResult(String(testError));
+    }
+  };
+
+  const toggleInputTrace = async (enabled: boolean) => {
+    try {
+      setInputTraceEnabledInput(
+        await invoke<boolean>("set_input_trace_enabled", { enabled }),
+      );
+    } catch (traceError) {
+      setInputTraceStatus(String(traceError));
+    }
+  };
+
+  const copyInputTrace = async () => {
+    try {
+      const trace = await invoke<{ entries: unknown[] }>("dump_input_trace");
+      await invoke("write_clipboard_text", {
+        value: JSON.stringify(trace, null, 2),
+      });
+      setInputTraceStatus(`Copied ${trace.entries.length} events.`);
+    } catch (traceError) {
+      setInputTraceStatus(String(traceError));
+    }
+  };
+
+  const clearInputTrace = async () => {
+    try {
+      await invoke("clear_input_trace");
+      setInputTraceStatus("Trace cleared.");
+    } catch (traceError) {
+      setInputTraceStatus(String(traceError));
+    }
+  };
+
   return (
     <div className="flex h-screen w-screen text-primary p-6">
       <div className="w-full max-w-2xl space-y-6">
@@ -250,14 +441,64 @@ export const SettingsPage = () => {
             >
               <option value="full">Full (preserve all held keys)</option>
               <option value="wasd">WASD only</option>
+              <option value="custom">Custom keys</option>
               <option value="none">None</option>
             </select>
+            {chatKeyPersistenceMode === "custom" && (
+              <input
+                id="chat-custom-latch-keys"
+                className="w-full rounded-md border border-border bg-background px-3 py-2 text-sm"
+                value={customLatchKeys}
+                onChange={(event) => setCustomLatchKeysInput(event.target.value)}
+                disabled={isLoading || isSaving}
+                placeholder="ArrowUp, ArrowLeft, ShiftLeft"
+              />
+            )}
             <p className="text-xs text-muted-foreground">
               Determines which currently held keys stay pressed in Roblox after
               pressing <code>/</code> to chat.
             </p>
           </div>
 
+          <div className="space-y-2">
+            <label htmlFor="passthrough-chords" className="text-sm font-medium">
+              Passthrough Shortcuts
+            </label>
+            <input
+              id="passthrough-chords"
+              className="w-full rounded-md border border-border bg-background px-3 py-2 text-sm"
+              value={passthroughChords}
+              onChange={(event) => setPassthroughChordsInput(event.target.value)}
+              disabled={isLoading || isSaving}
+              placeholder="Ctrl+Shift+KeyM, F9"
+            />
+            <p className="text-xs text-muted-foreground">
+              Shortcuts that still reach Discord, OBS or Steam while you type
+              in chat. Alt+Tab, Alt+F4 and Windows key shortcuts always pass.
+            </p>
+            <div className="flex items-center gap-2">
+              <input
+                id="passthrough-chord-test"
+                className="flex-1 rounded-md border border-border bg-background px-3 py-2 text-sm"
+                value={chordToTest}
+                onChange={(event) => setChordToTest(event.target.value)}
+                disabled={isLoading || isSaving}
+                placeholder="Ctrl+Shift+KeyM"
+              />
+              <Button
+                onClick={testChord}
+                size={"sm"}
+                variant={"secondary"}
+                disabled={isLoading || isSaving || !chordToTest.trim()}
+              >
+                Check
+              </Button>
+            </div>
+            {chordTestResult && (
+              <p className="text-xs text-muted-foreground">{chordTestResult}</p>
+            )}
+          </div>
+
           <div className="space-y-2">
             <label htmlFor="chat-input-mode" className="text-sm font-medium">
               Slash Chat Input Mode
@@ -279,6 +520,133 @@ export const SettingsPage = () => {
             <p className="text-xs text-muted-foreground">
               Use IME Assist for full native CJK composition behavior.
             </p>
+            {!passwordFieldDetection && (
+              <p className="text-xs text-yellow-400">
+                On Linux, BloxChat cannot tell when a password field has focus,
+                so chat keeps capturing keys there. Close chat before typing a
+                password in another app.
+              </p>
+            )}
+          </div>
+
+          <div className="space-y-2">
+            <label
+              htmlFor="capture-idle-timeout"
+              className="text-sm font-medium"
+            >
+              Chat Capture Idle Timeout
+            </label>
+            <input
+              id="capture-idle-timeout"
+              type="number"
+              min={0}
+              max={3600}
+              className="w-full rounded-md border border-border bg-background px-3 py-2 text-sm"
+              value={captureIdleTimeout}
+              onChange={(event) => setCaptureIdleTimeoutInput(event.target.value)}
+              disabled={isLoading || isSaving}
+              placeholder="30"
+            />
+            <p className="text-xs text-muted-foreground">
+              Seconds without typing before chat closes and held keys are
+              released. Set to 0 to keep chat open indefinitely.
+            </p>
+          </div>
+
+          <div className="space-y-2">
+            <label htmlFor="capture-trigger" className="text-sm font-medium">
+              Chat Key Behavior
+            </label>
+            <select
+              id="capture-trigger"
+              className="w-full rounded-md border border-border bg-background px-3 py-2 text-sm"
+              value={captureTrigger}
+              onChange={(event) =>
+                setCaptureTriggerInput(event.target.value as CaptureTrigger)
+              }
+              disabled={isLoading || isSaving}
+            >
+              <option value="toggle">Press to open</option>
+              <option value="hold">Hold to chat</option>
+            </select>
+            <p className="text-xs text-muted-foreground">
+              Hold to chat keeps chat open only while / is held and sends the
+              message when you let go. A quick tap still opens chat normally.
+            </p>
+          </div>
+
+          <div className="space-y-2">
+            <label htmlFor="chat-scroll-modifier" className="text-sm font-medium">
+              Chat Scroll Modifier
+            </label>
+            <select
+              id="chat-scroll-modifier"
+              className="w-full rounded-md border border-border bg-background px-3 py-2 text-sm"
+              value={chatScrollModifier}
+              onChange={(event) =>
+                setChatScrollModifierInput(event.target.value as ChatScrollModifier)
+              }
+              disabled={isLoading || isSaving}
+            >
+              <option value="alt">Alt</option>
+              <option value="ctrl">Ctrl</option>
+              <option value="shift">Shift</option>
+              <option value="off">Off</option>
+            </select>
+            <p className="text-xs text-muted-foreground">
+              Hold this key and use the mouse wheel in Roblox to scroll chat.
+              The game does not see those wheel movements.
+            </p>
+          </div>
+
+          <div className="space-y-2">
+            <label htmlFor="quick-chat-macros" className="text-sm font-medium">
+              Quick Chat Macros
+            </label>
+            <textarea
+              id="quick-chat-macros"
+              rows={4}
+              className="w-full rounded-md border border-border bg-background px-3 py-2 text-sm font-mono"
+              value={quickChatMacros}
+              onChange={(event) => setQuickChatMacrosInput(event.target.value)}
+              disabled={isLoading || isSaving}
+              placeholder={"Alt+Digit1: gg\nAlt+Digit2: Join me at {placeId} ({jobId})"}
+            />
+            <p className="text-xs text-muted-foreground">
+              One <code>Chord: message</code> per line. Pressing the chord in
+              Roblox sends the message without opening chat.{" "}
+              <code>{"{jobId}"}</code> and <code>{"{placeId}"}</code> are
+              replaced with the current server.
+            </p>
+          </div>
+
+          <div className="space-y-2">
+            <span className="text-sm font-medium">Controller Chat Combo</span>
+            <div className="grid grid-cols-2 gap-2">
+              {GAMEPAD_COMBO_BUTTONS.map((button) => (
+                <label
+                  key={button.value}
+                  className="flex items-center gap-2 text-sm"
+                >
+                  <Checkbox
+                    checked={gamepadChatCombo.includes(button.value)}
+                    onCheckedChange={(checked) =>
+                      setGamepadChatComboInput((current) =>
+                        checked === true
+                          ? [...current, button.value]
+                          : current.filter((value) => value !== button.value),
+                      )
+                    }
+                    disabled={isLoading || isSaving}
+                  />
+                  {button.label}
+                </label>
+              ))}
+            </div>
+            <p className="text-xs text-muted-foreground">
+              Press these buttons together in Roblox to open chat. The D-pad
+              steps through messages. Leave everything unchecked to disable.
+            </p>
           </div>
 
           <div className="space-y-2">
@@ -312,6 +680,43 @@ export const SettingsPage = () => {
             </p>
           </div>
 
+          <div className="space-y-2">
+            <label className="flex items-center gap-2 text-sm font-medium">
+              <Checkbox
+                checked={inputTraceEnabled}
+                onCheckedChange={(checked) => toggleInputTrace(checked === true)}
+                disabled={isLoading}
+              />
+              Record Input Trace
+            </label>
+            <div className="flex items-center gap-2">
+              <Button
+                onClick={copyInputTrace}
+                size={"sm"}
+                variant={"secondary"}
+                disabled={isLoading}
+              >
+                Copy Trace
+              </Button>
+              <Button
+                onClick={clearInputTrace}
+                size={"sm"}
+                variant={"secondary"}
+                disabled={isLoading}
+              >
+                Clear Trace
+              </Button>
+            </div>
+            <p className="text-xs text-muted-foreground">
+              Keeps the last 512 key and mouse events made in Roblox or
+              BloxChat for bug reports. Other apps are never recorded, and
+              text keys are left out. Recording stops when the app restarts.
+            </p>
+            {inputTraceStatus && (
+              <p className="text-xs text-muted-foreground">{inputTraceStatus}</p>
+            )}
+          </div>
+
           <div className="space-y-1">
             <label className="text-sm font-medium">App Version</label>
             <p className="text-xs text-muted-foreground">{appVersion}</p>